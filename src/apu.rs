@@ -0,0 +1,323 @@
+use crate::utils::bits::get_bit;
+
+use blip_buffer::BlipBuffer;
+use dmc::DMC;
+use filter::{console_filters, Filter};
+use frame_counter::{FrameClock, FrameCounter};
+use mixer::{PULSE_TABLE, TND_TABLE};
+use noise::Noise;
+use pulse::Pulse;
+use triangle::Triangle;
+
+mod blip_buffer;
+mod dmc;
+mod envelope;
+mod filter;
+mod frame_counter;
+mod length_counter;
+mod mixer;
+mod noise;
+mod pulse;
+mod triangle;
+
+/// The sample rate audio comes out at, unless told otherwise.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// Sample formats [APU::audio_samples] can put out.
+pub trait Sample {
+    /// Takes a sample from -1.0 to 1.0.
+    fn from_f32(value: f32) -> Self;
+}
+
+impl Sample for f32 {
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+impl Sample for i16 {
+    /// Expansion audio can push the mix past full scale, so it gets clipped.
+    fn from_f32(value: f32) -> Self {
+        (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+    }
+}
+
+/// The 2A03's audio processing unit.
+///
+/// 0x4000-0x4003: Pulse 1
+///
+/// 0x4004-0x4007: Pulse 2
+///
+/// 0x4008-0x400B: Triangle
+///
+/// 0x400C-0x400F: Noise
+///
+/// 0x4010-0x4013: DMC
+///
+/// 0x4015: Channel enable (write), status (read)
+///
+/// 0x4017: Frame counter (write only, reads go to the second controller)
+///
+/// Most of it runs at the APU cycle rate, half the CPU clock. It outputs one sample per CPU cycle,
+/// which gets resampled down to the host sample rate.
+/// Refer to: https://www.nesdev.org/wiki/APU
+#[allow(clippy::upper_case_acronyms)]
+pub struct APU {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: DMC,
+    frame_counter: FrameCounter,
+    /// Whether the current CPU cycle is the second half of an APU cycle.
+    odd_cycle: bool,
+    sample_rate: u32,
+    blip_buffer: BlipBuffer,
+    filters: [Filter; 3],
+}
+
+impl Default for APU {
+    fn default() -> Self {
+        APU::new()
+    }
+}
+
+impl APU {
+    pub fn new() -> APU {
+        APU {
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: DMC::default(),
+            frame_counter: FrameCounter::default(),
+            odd_cycle: false,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            blip_buffer: BlipBuffer::new(DEFAULT_SAMPLE_RATE),
+            filters: console_filters(DEFAULT_SAMPLE_RATE),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Usually 44100 or 48000 Hz. Drops whatever hasn't been collected yet.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.blip_buffer = BlipBuffer::new(sample_rate);
+        self.filters = console_filters(sample_rate);
+    }
+
+    pub fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x4000..=0x4003 => self.pulse_1.write(addr - 0x4000, value),
+            0x4004..=0x4007 => self.pulse_2.write(addr - 0x4004, value),
+            0x4008..=0x400B => self.triangle.write(addr - 0x4008, value),
+            0x400C..=0x400F => self.noise.write(addr - 0x400C, value),
+            0x4010..=0x4013 => self.dmc.write(addr - 0x4010, value),
+            // ...D NT21: enable DMC, Noise, Triangle, pulse 2, pulse 1
+            // Also acknowledges the DMC interrupt.
+            0x4015 => {
+                self.pulse_1.length_counter.set_enabled(get_bit(value, 0));
+                self.pulse_2.length_counter.set_enabled(get_bit(value, 1));
+                self.triangle.length_counter.set_enabled(get_bit(value, 2));
+                self.noise.length_counter.set_enabled(get_bit(value, 3));
+                self.dmc.set_enabled(get_bit(value, 4));
+                self.dmc.irq_flag = false;
+            }
+            0x4017 => self.frame_counter.write(value, self.odd_cycle),
+            _ => {}
+        }
+    }
+
+    /// 7654 3210
+    ///
+    /// IF.D NT21
+    ///
+    /// DMC Interrupt, Frame interrupt, DMC active, length counters of Noise, Triangle, pulse 2, pulse 1 above 0.
+    /// Bit 5 isn't driven, so it's left for the caller to fill in from open bus.
+    pub fn peek_status(&self) -> u8 {
+        ((self.dmc.irq_flag as u8) << 7)
+            | ((self.frame_counter.irq_flag as u8) << 6)
+            | ((self.dmc.active() as u8) << 4)
+            | ((self.noise.length_counter.active() as u8) << 3)
+            | ((self.triangle.length_counter.active() as u8) << 2)
+            | ((self.pulse_2.length_counter.active() as u8) << 1)
+            | self.pulse_1.length_counter.active() as u8
+    }
+
+    /// Reading the status acknowledges the frame interrupt, but not the DMC one.
+    pub fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
+
+        self.frame_counter.irq_flag = false;
+
+        status
+    }
+
+    pub fn irq(&self) -> bool {
+        self.frame_counter.irq_flag || self.dmc.irq_flag
+    }
+
+    /// The address the DMC wants to read its next sample byte from, if it needs one.
+    /// The bus is expected to halt the CPU, read it, and hand it over through [APU::finish_dmc_dma].
+    pub fn dmc_dma_request(&self) -> Option<u16> {
+        self.dmc.dma_request()
+    }
+
+    pub fn finish_dmc_dma(&mut self, value: u8) {
+        self.dmc.finish_dma(value);
+    }
+
+    /// Runs the APU for a CPU cycle, and outputs a sample.
+    /// If nobody collects them, the oldest samples get dropped after about a second.
+    /// `expansion` is the output of the cartridge's own audio, mixed in as it is.
+    pub fn tick(&mut self, expansion: f32) {
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+
+        if self.odd_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+
+        match self.frame_counter.clock() {
+            FrameClock::None => {}
+            FrameClock::Quarter => self.clock_quarter_frame(),
+            FrameClock::Half => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+        }
+
+        self.odd_cycle = !self.odd_cycle;
+
+        self.blip_buffer.clock(self.output() + expansion);
+
+        if self.blip_buffer.samples_available() >= self.sample_rate as usize {
+            self.blip_buffer.skip_samples(self.sample_rate as usize / 2);
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.envelope.clock();
+        self.pulse_2.envelope.clock();
+        self.triangle.clock_linear_counter();
+        self.noise.envelope.clock();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.length_counter.clock();
+        self.pulse_1.clock_sweep();
+        self.pulse_2.length_counter.clock();
+        self.pulse_2.clock_sweep();
+        self.triangle.length_counter.clock();
+        self.noise.length_counter.clock();
+    }
+
+    /// The mixer output, before any filtering. 1.0 is about as loud as it gets.
+    /// Refer to: https://www.nesdev.org/wiki/APU_Mixer
+    pub fn output(&self) -> f32 {
+        let pulse = PULSE_TABLE[(self.pulse_1.output() + self.pulse_2.output()) as usize];
+        let tnd = TND_TABLE[3 * self.triangle.output() as usize
+            + 2 * self.noise.output() as usize
+            + self.dmc.output() as usize];
+
+        pulse + tnd
+    }
+
+    /// Takes the samples produced since the last call, at the host sample rate.
+    /// Called once per frame, that's a frame's worth: about 735 samples at 44.1 kHz, or 800 at 48 kHz.
+    ///
+    /// They go through the console's filters, which take out the DC offset, so they're centered around 0.
+    pub fn audio_samples<S: Sample>(&mut self) -> Vec<S> {
+        self.blip_buffer
+            .read_samples()
+            .into_iter()
+            .map(|sample| {
+                let filtered = self
+                    .filters
+                    .iter_mut()
+                    .fold(sample, |sample, filter| filter.apply(sample));
+
+                S::from_f32(filtered)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_read_acknowledges_frame_irq() {
+        let mut apu = APU::new();
+
+        run(&mut apu, 29829);
+        assert!(apu.irq());
+        assert_eq!(apu.peek_status(), 0b0100_0000);
+
+        assert_eq!(apu.read_status(), 0b0100_0000);
+        assert!(!apu.irq());
+        assert_eq!(apu.peek_status(), 0);
+    }
+
+    #[test]
+    fn status_length_counters() {
+        let mut apu = APU::new();
+        apu.write(0x4015, 0b0000_1111);
+
+        apu.write(0x4003, 0b0000_1000);
+        apu.write(0x4007, 0b0000_1000);
+        apu.write(0x400B, 0b0000_1000);
+        apu.write(0x400F, 0b0000_1000);
+        assert_eq!(apu.peek_status(), 0b0000_1111);
+
+        apu.write(0x4015, 0b0000_0101);
+        assert_eq!(apu.peek_status(), 0b0000_0101);
+    }
+
+    fn run(apu: &mut APU, cycles: u32) {
+        for _ in 0..cycles {
+            apu.tick(0.0);
+        }
+    }
+
+    #[test]
+    fn frame_counter_write_delay() {
+        // Written on an even CPU cycle, the reset happens 3 cycles later, on an odd one 4.
+        for (offset, delay) in [(0, 3), (1, 4)] {
+            let mut apu = APU::new();
+            run(&mut apu, offset);
+            apu.write(0x4017, 0);
+
+            // The IRQ comes 29828 cycles into the new frame.
+            run(&mut apu, delay - 1 + 29827);
+            assert!(!apu.irq(), "{offset}");
+            run(&mut apu, 1);
+            assert!(apu.irq(), "{offset}");
+        }
+    }
+
+    #[test]
+    fn five_step_write_clocks_immediately() {
+        let mut apu = APU::new();
+        apu.write(0x4015, 0b0000_0001);
+        apu.write(0x4003, 0b0001_1000);
+
+        // Each write clocks the length counter (loaded with 2) as soon as it takes effect.
+        apu.write(0x4017, 0b1000_0000);
+        run(&mut apu, 3);
+        assert_eq!(apu.peek_status(), 0b0000_0001);
+
+        // Now on an odd cycle.
+        apu.write(0x4017, 0b1000_0000);
+        run(&mut apu, 3);
+        assert_eq!(apu.peek_status(), 0b0000_0001);
+        run(&mut apu, 1);
+        assert_eq!(apu.peek_status(), 0);
+    }
+}
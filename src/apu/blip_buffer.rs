@@ -0,0 +1,198 @@
+use std::f64::consts::PI;
+
+/// NTSC CPU clock, which the APU outputs a sample for every cycle of.
+const CLOCK_RATE: f64 = 1_789_773.0;
+
+/// How many fractional positions between two output samples a step can land on.
+const PHASES: usize = 32;
+
+/// How many output samples a step gets spread over.
+const WIDTH: usize = 16;
+
+/// Where the step kernel cuts off, relative to the output sample rate. Just under Nyquist.
+const CUTOFF: f64 = 0.45;
+
+/// Resamples the APU output down to the host sample rate, without aliasing.
+///
+/// Skipping samples would fold everything above half the sample rate back down, and the square waves
+/// the APU makes have harmonics all the way up. Instead, the APU output is treated as a series of steps
+/// (it only changes every so often anyway), and each step is added in already band-limited:
+/// as a windowed sinc impulse, at its exact fractional position between output samples.
+/// Integrating those impulses gives back the waveform, minus anything the output can't represent.
+/// Refer to: http://www.slack.net/~ant/bl-synth/
+pub(super) struct BlipBuffer {
+    /// One band-limited impulse for each phase, adding up to 1.
+    kernel: Box<[[f32; WIDTH]; PHASES]>,
+    /// Output samples per APU sample.
+    factor: f64,
+    /// The current position, in output samples from the start of `deltas`.
+    time: f64,
+    /// The changes in amplitude, already spread out over output samples.
+    deltas: Vec<f32>,
+    /// The last APU output clocked in.
+    amplitude: f32,
+    /// The running sum of `deltas` that have been read out.
+    integrator: f32,
+}
+
+impl BlipBuffer {
+    pub(super) fn new(sample_rate: u32) -> BlipBuffer {
+        BlipBuffer {
+            kernel: Box::new(step_kernel()),
+            factor: sample_rate as f64 / CLOCK_RATE,
+            time: 0.0,
+            deltas: Vec::new(),
+            amplitude: 0.0,
+            integrator: 0.0,
+        }
+    }
+
+    /// Takes one APU sample, a CPU cycle's worth.
+    pub(super) fn clock(&mut self, amplitude: f32) {
+        if amplitude != self.amplitude {
+            self.add_delta(amplitude - self.amplitude);
+            self.amplitude = amplitude;
+        }
+
+        self.time += self.factor;
+    }
+
+    fn add_delta(&mut self, delta: f32) {
+        let index = self.time as usize;
+        let phase = ((self.time - index as f64) * PHASES as f64) as usize;
+
+        if self.deltas.len() < index + WIDTH {
+            self.deltas.resize(index + WIDTH, 0.0);
+        }
+
+        for (sample, tap) in self.deltas[index..index + WIDTH]
+            .iter_mut()
+            .zip(self.kernel[phase])
+        {
+            *sample += delta * tap;
+        }
+    }
+
+    /// Output samples that nothing clocked in from now on can change anymore.
+    pub(super) fn samples_available(&self) -> usize {
+        self.time as usize
+    }
+
+    /// Takes the finished output samples. They lag behind by half the kernel width.
+    pub(super) fn read_samples(&mut self) -> Vec<f32> {
+        self.take_samples(self.samples_available()).collect()
+    }
+
+    /// Drops the oldest `count` finished samples.
+    pub(super) fn skip_samples(&mut self, count: usize) {
+        self.take_samples(count.min(self.samples_available()))
+            .for_each(drop);
+    }
+
+    fn take_samples(&mut self, count: usize) -> impl Iterator<Item = f32> + '_ {
+        if self.deltas.len() < count {
+            self.deltas.resize(count, 0.0);
+        }
+
+        self.time -= count as f64;
+
+        self.deltas.drain(..count).map(|delta| {
+            self.integrator += delta;
+            self.integrator
+        })
+    }
+}
+
+/// Blackman-windowed sinc impulses, centered on the middle of the kernel plus the phase's offset.
+fn step_kernel() -> [[f32; WIDTH]; PHASES] {
+    let mut kernel = [[0.0; WIDTH]; PHASES];
+    let half_width = (WIDTH / 2) as f64;
+
+    for (phase, taps) in kernel.iter_mut().enumerate() {
+        let offset = phase as f64 / PHASES as f64;
+
+        let impulse: Vec<f64> = (0..WIDTH)
+            .map(|tap| {
+                let x = tap as f64 - offset - half_width + 1.0;
+
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (2.0 * PI * CUTOFF * x).sin() / (2.0 * PI * CUTOFF * x)
+                };
+
+                // The window spans the whole kernel, from -half_width to half_width.
+                let n = (x + half_width) / (2.0 * half_width);
+                let window = 0.42 - 0.5 * (2.0 * PI * n).cos() + 0.08 * (4.0 * PI * n).cos();
+
+                sinc * window
+            })
+            .collect();
+
+        // A step has to end up exactly as high as it started out, whichever phase it landed on.
+        let sum: f64 = impulse.iter().sum();
+
+        for (tap, value) in taps.iter_mut().zip(impulse) {
+            *tap = (value / sum) as f32;
+        }
+    }
+
+    kernel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_phase_sums_to_one() {
+        for (phase, taps) in step_kernel().iter().enumerate() {
+            let sum: f32 = taps.iter().sum();
+
+            assert!((sum - 1.0).abs() < 1e-5, "phase {phase}: {sum}");
+        }
+    }
+
+    #[test]
+    fn steps_settle_at_full_amplitude() {
+        // Landing the step a cycle later each time goes through a good spread of phases.
+        for delay in 0..64 {
+            let mut blip_buffer = BlipBuffer::new(44_100);
+
+            for _ in 0..delay {
+                blip_buffer.clock(0.0);
+            }
+
+            for _ in 0..2000 {
+                blip_buffer.clock(0.5);
+            }
+
+            let samples = blip_buffer.read_samples();
+            let last = *samples.last().unwrap();
+
+            assert!((last - 0.5).abs() < 1e-5, "delay {delay}: {last}");
+        }
+    }
+
+    #[test]
+    fn a_frame_of_samples() {
+        let mut blip_buffer = BlipBuffer::new(44_100);
+        let mut total = 0;
+
+        // NTSC frames alternate between 29780 and 29781 CPU cycles.
+        for frame in 0..60 {
+            for _ in 0..29780 + frame % 2 {
+                blip_buffer.clock(0.25);
+            }
+
+            let samples = blip_buffer.read_samples().len();
+            assert!((733..=735).contains(&samples), "frame {frame}: {samples}");
+
+            total += samples;
+        }
+
+        // 60 frames is a little over a second.
+        let expected = 44_100.0 * 60.0 * 29780.5 / CLOCK_RATE;
+        assert!((total as f64 - expected).abs() <= 1.0, "{total}");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::utils::bits::get_bit;
+
+/// NTSC output rates, in CPU cycles per bit.
+const RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+/// The delta modulation channel. It plays 1-bit delta-encoded samples straight out of CPU memory,
+/// moving a 7-bit output level up or down by 2 for every bit.
+///
+/// Whenever its one byte sample buffer runs empty, it asks for the next byte through DMA,
+/// which halts the CPU for a few cycles. That's left to the bus, see [super::APU::dmc_dma_request].
+/// Refer to: https://www.nesdev.org/wiki/APU_DMC
+#[allow(clippy::upper_case_acronyms)]
+pub(super) struct DMC {
+    irq_enabled: bool,
+    loop_flag: bool,
+    period: u16,
+    timer: u16,
+    output_level: u8,
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift_register: u8,
+    bits_remaining: u8,
+    /// Set when the output cycle started without a byte in the buffer, which leaves the level alone.
+    silence: bool,
+    pub(super) irq_flag: bool,
+}
+
+impl Default for DMC {
+    fn default() -> Self {
+        DMC {
+            irq_enabled: false,
+            loop_flag: false,
+            period: RATES[0],
+            timer: RATES[0],
+            output_level: 0,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            irq_flag: false,
+        }
+    }
+}
+
+impl DMC {
+    /// Takes the register number within the channel (0-3).
+    pub(super) fn write(&mut self, register: u16, value: u8) {
+        match register {
+            // IL.. RRRR: IRQ enable, Loop, Rate
+            0 => {
+                self.irq_enabled = get_bit(value, 7);
+                self.loop_flag = get_bit(value, 6);
+                self.period = RATES[(value & 0x0F) as usize];
+
+                if !self.irq_enabled {
+                    self.irq_flag = false;
+                }
+            }
+            // .DDD DDDD: Direct load of the output level
+            1 => self.output_level = value & 0b0111_1111,
+            // Sample address: %11AAAAAA.AA000000
+            2 => self.sample_address = 0xC000 | ((value as u16) << 6),
+            // Sample length: %LLLL.LLLL0001
+            _ => self.sample_length = ((value as u16) << 4) | 1,
+        }
+    }
+
+    /// Set through $4015. Enabling it only starts the sample over if it already finished.
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    /// The address of the next sample byte, if the sample buffer needs refilling.
+    pub(super) fn dma_request(&self) -> Option<u16> {
+        (self.sample_buffer.is_none() && self.bytes_remaining > 0).then_some(self.current_address)
+    }
+
+    /// Fills the sample buffer with the byte the DMA read.
+    /// The address wraps around from $FFFF to $8000.
+    pub(super) fn finish_dma(&mut self, value: u8) {
+        self.sample_buffer = Some(value);
+        self.current_address = self.current_address.wrapping_add(1) | 0x8000;
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.loop_flag {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_flag = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period;
+
+        // The level only moves if it stays within 0-127.
+        if !self.silence {
+            if self.shift_register & 1 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.shift_register = sample;
+                    self.silence = false;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    /// 0-127
+    pub(super) fn output(&self) -> u8 {
+        self.output_level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands the DMC the byte it asked for, and empties the sample buffer again right away.
+    fn feed(dmc: &mut DMC) -> Option<u16> {
+        let addr = dmc.dma_request();
+
+        if addr.is_some() {
+            dmc.finish_dma(0);
+            dmc.sample_buffer = None;
+        }
+
+        addr
+    }
+
+    #[test]
+    fn sample_address_and_length() {
+        let mut dmc = DMC::default();
+        dmc.write(2, 0x01);
+        dmc.write(3, 0x02);
+        dmc.set_enabled(true);
+
+        assert_eq!(feed(&mut dmc), Some(0xC040));
+
+        // %LLLL.LLLL0001 bytes
+        for _ in 1..33 {
+            assert!(feed(&mut dmc).is_some());
+        }
+
+        assert_eq!(feed(&mut dmc), None);
+        assert!(!dmc.active());
+        assert!(!dmc.irq_flag);
+    }
+
+    #[test]
+    fn irq_at_the_end_of_the_sample() {
+        let mut dmc = DMC::default();
+        dmc.write(0, 0x80);
+        dmc.write(3, 0x00);
+        dmc.set_enabled(true);
+
+        feed(&mut dmc);
+        assert!(dmc.irq_flag);
+        assert!(!dmc.active());
+
+        // Clearing the IRQ enable flag acknowledges it.
+        dmc.write(0, 0x00);
+        assert!(!dmc.irq_flag);
+    }
+
+    #[test]
+    fn loop_reloads_address_and_length() {
+        let mut dmc = DMC::default();
+        dmc.write(0, 0xC0);
+        dmc.write(2, 0x10);
+        dmc.write(3, 0x00);
+        dmc.set_enabled(true);
+
+        assert_eq!(feed(&mut dmc), Some(0xC400));
+        assert_eq!(dmc.dma_request(), Some(0xC400));
+        assert!(dmc.active());
+        // Looping samples never raise the IRQ.
+        assert!(!dmc.irq_flag);
+    }
+
+    #[test]
+    fn enabling_only_restarts_a_finished_sample() {
+        let mut dmc = DMC::default();
+        dmc.write(3, 0x01);
+        dmc.set_enabled(true);
+        feed(&mut dmc);
+
+        dmc.set_enabled(true);
+        assert_eq!(dmc.dma_request(), Some(0xC001));
+
+        dmc.set_enabled(false);
+        assert_eq!(dmc.dma_request(), None);
+
+        dmc.set_enabled(true);
+        assert_eq!(dmc.dma_request(), Some(0xC000));
+    }
+
+    #[test]
+    fn address_wraps_to_8000() {
+        let mut dmc = DMC::default();
+        dmc.write(2, 0xFF);
+        dmc.write(3, 0x04);
+        dmc.set_enabled(true);
+
+        for _ in 0..0x40 {
+            feed(&mut dmc);
+        }
+
+        assert_eq!(dmc.dma_request(), Some(0x8000));
+    }
+
+    #[test]
+    fn output_level_follows_the_sample_bits() {
+        let mut dmc = DMC::default();
+        dmc.write(1, 0x40);
+        dmc.write(3, 0x00);
+        dmc.set_enabled(true);
+        dmc.finish_dma(0b0000_0011);
+
+        let clock_bits = |dmc: &mut DMC, bits| {
+            for _ in 0..bits {
+                dmc.timer = 1;
+                dmc.clock_timer();
+            }
+        };
+
+        // The output cycle that was already running had no sample, so it's silent.
+        clock_bits(&mut dmc, 8);
+        assert_eq!(dmc.output(), 0x40);
+
+        // Two bits up, then six down.
+        clock_bits(&mut dmc, 2);
+        assert_eq!(dmc.output(), 0x44);
+        clock_bits(&mut dmc, 6);
+        assert_eq!(dmc.output(), 0x38);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::utils::bits::get_bit;
+
+/// Volume control shared by the pulse and noise channels.
+/// Either a constant volume, or a decay from 15 to 0, one step per `volume + 1` quarter frames.
+/// Refer to: https://www.nesdev.org/wiki/APU_Envelope
+#[derive(Default)]
+pub(super) struct Envelope {
+    start: bool,
+    divider: u8,
+    decay: u8,
+    /// Shares its bit with the length counter halt flag. Makes the decay start over after reaching 0.
+    loop_flag: bool,
+    constant_volume: bool,
+    /// The constant volume, or the decay period.
+    volume: u8,
+}
+
+impl Envelope {
+    /// 7654 3210
+    ///
+    /// ..LC VVVV
+    ///
+    /// Loop, Constant volume, Volume/period
+    pub(super) fn write(&mut self, value: u8) {
+        self.loop_flag = get_bit(value, 5);
+        self.constant_volume = get_bit(value, 4);
+        self.volume = value & 0x0F;
+    }
+
+    /// Writing the length counter restarts the envelope on the next quarter frame.
+    pub(super) fn restart(&mut self) {
+        self.start = true;
+    }
+
+    /// Clocked by the frame counter, every quarter frame.
+    pub(super) fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.loop_flag {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(envelope: &mut Envelope, quarter_frames: usize) -> Vec<u8> {
+        (0..quarter_frames)
+            .map(|_| {
+                envelope.clock();
+                envelope.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decay() {
+        let mut envelope = Envelope::default();
+
+        // One step every 2 quarter frames.
+        envelope.write(0b0000_0001);
+        envelope.restart();
+
+        assert_eq!(outputs(&mut envelope, 5), [15, 15, 14, 14, 13]);
+
+        // Stays at 0.
+        outputs(&mut envelope, 30);
+        assert_eq!(outputs(&mut envelope, 4), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn decay_loop() {
+        let mut envelope = Envelope::default();
+        envelope.write(0b0010_0000);
+        envelope.restart();
+
+        let decay = outputs(&mut envelope, 18);
+        assert_eq!(decay[..3], [15, 14, 13]);
+        assert_eq!(decay[15..], [0, 15, 14]);
+    }
+
+    #[test]
+    fn constant_volume() {
+        let mut envelope = Envelope::default();
+        envelope.write(0b0001_0111);
+        envelope.restart();
+
+        assert_eq!(outputs(&mut envelope, 3), [7, 7, 7]);
+    }
+}
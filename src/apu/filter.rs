@@ -0,0 +1,101 @@
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy)]
+pub(super) enum FilterKind {
+    HighPass,
+    LowPass,
+}
+
+/// A first-order RC filter, running at the output sample rate.
+pub(super) struct Filter {
+    kind: FilterKind,
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl Filter {
+    pub(super) fn new(kind: FilterKind, cutoff: f32, sample_rate: u32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+
+        let alpha = match kind {
+            FilterKind::HighPass => rc / (rc + dt),
+            FilterKind::LowPass => dt / (rc + dt),
+        };
+
+        Filter {
+            kind,
+            alpha,
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    pub(super) fn apply(&mut self, input: f32) -> f32 {
+        let output = match self.kind {
+            FilterKind::HighPass => {
+                self.alpha * (self.previous_output + input - self.previous_input)
+            }
+            FilterKind::LowPass => {
+                self.previous_output + self.alpha * (input - self.previous_output)
+            }
+        };
+
+        self.previous_input = input;
+        self.previous_output = output;
+
+        output
+    }
+}
+
+/// What's between the mixer and the audio output on a front-loader NES:
+/// a high-pass at 90 Hz, another one at 440 Hz, and a low-pass at 14 kHz.
+/// Refer to: https://www.nesdev.org/wiki/APU_Mixer
+pub(super) fn console_filters(sample_rate: u32) -> [Filter; 3] {
+    [
+        Filter::new(FilterKind::HighPass, 90.0, sample_rate),
+        Filter::new(FilterKind::HighPass, 440.0, sample_rate),
+        Filter::new(FilterKind::LowPass, 14_000.0, sample_rate),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A second's worth of a constant input.
+    fn settle(filter: &mut Filter, input: f32) -> f32 {
+        (0..44_100).fold(0.0, |_, _| filter.apply(input))
+    }
+
+    #[test]
+    fn high_pass_removes_dc() {
+        let mut filter = Filter::new(FilterKind::HighPass, 90.0, 44_100);
+
+        // The jump goes straight through.
+        assert!(filter.apply(1.0) > 0.98);
+        assert!(settle(&mut filter, 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn low_pass_keeps_dc() {
+        let mut filter = Filter::new(FilterKind::LowPass, 14_000.0, 44_100);
+
+        assert!(filter.apply(1.0) < 1.0);
+        assert!((settle(&mut filter, 1.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn console_filters_center_the_output() {
+        let mut filters = console_filters(44_100);
+
+        let output = (0..44_100).fold(0.0, |_, _| {
+            filters
+                .iter_mut()
+                .fold(0.5, |sample, filter| filter.apply(sample))
+        });
+
+        assert!(output.abs() < 1e-4);
+    }
+}
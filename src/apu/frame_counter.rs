@@ -0,0 +1,181 @@
+use crate::utils::bits::get_bit;
+
+/// What the frame counter clocks on a given cycle.
+/// Half frames clock everything quarter frames do, plus length counters and sweep units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum FrameClock {
+    None,
+    Quarter,
+    Half,
+}
+
+/// Drives the envelopes, linear counter, length counters and sweep units at about 240 Hz.
+///
+/// In 4-step mode, a frame is 29830 CPU cycles, with an IRQ at the end.
+/// In 5-step mode, it's 37282 CPU cycles, with a step in the middle that doesn't clock anything, and no IRQ.
+/// Refer to: https://www.nesdev.org/wiki/APU_Frame_Counter
+#[derive(Default)]
+pub(super) struct FrameCounter {
+    five_step: bool,
+    irq_inhibit: bool,
+    pub(super) irq_flag: bool,
+    /// CPU cycles since the start of the frame.
+    cycle: u32,
+    /// Writes to $4017 take effect 3 or 4 CPU cycles later.
+    reset_delay: u8,
+}
+
+impl FrameCounter {
+    /// 7654 3210
+    ///
+    /// MI.. ....
+    ///
+    /// Mode (5-step), IRQ inhibit
+    ///
+    /// `odd_cycle` is whether the write lands between APU cycles, which delays it by one more CPU cycle.
+    pub(super) fn write(&mut self, value: u8, odd_cycle: bool) {
+        self.five_step = get_bit(value, 7);
+        self.irq_inhibit = get_bit(value, 6);
+
+        if self.irq_inhibit {
+            self.irq_flag = false;
+        }
+
+        self.reset_delay = if odd_cycle { 4 } else { 3 };
+    }
+
+    /// Clocked every CPU cycle.
+    pub(super) fn clock(&mut self) -> FrameClock {
+        if self.reset_delay > 0 {
+            self.reset_delay -= 1;
+
+            if self.reset_delay == 0 {
+                self.cycle = 0;
+
+                // Switching to 5-step mode clocks everything right away.
+                if self.five_step {
+                    return FrameClock::Half;
+                }
+            }
+        }
+
+        self.cycle += 1;
+
+        match (self.five_step, self.cycle) {
+            (_, 7457) | (_, 22371) => FrameClock::Quarter,
+            (_, 14913) => FrameClock::Half,
+            (false, 29828) => {
+                self.raise_irq();
+                FrameClock::None
+            }
+            (false, 29829) => {
+                self.raise_irq();
+                FrameClock::Half
+            }
+            (false, 29830) => {
+                self.raise_irq();
+                self.cycle = 0;
+                FrameClock::None
+            }
+            (true, 37281) => FrameClock::Half,
+            (true, 37282) => {
+                self.cycle = 0;
+                FrameClock::None
+            }
+            _ => FrameClock::None,
+        }
+    }
+
+    fn raise_irq(&mut self) {
+        if !self.irq_inhibit {
+            self.irq_flag = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cycles (counted from 1) that clock something, over `cycles` CPU cycles.
+    fn clocks(frame_counter: &mut FrameCounter, cycles: u32) -> Vec<(u32, FrameClock)> {
+        (1..=cycles)
+            .filter_map(|cycle| {
+                let clock = frame_counter.clock();
+
+                (clock != FrameClock::None).then_some((cycle, clock))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn four_step_sequence() {
+        let mut frame_counter = FrameCounter::default();
+
+        assert_eq!(
+            clocks(&mut frame_counter, 29830 + 7457),
+            [
+                (7457, FrameClock::Quarter),
+                (14913, FrameClock::Half),
+                (22371, FrameClock::Quarter),
+                (29829, FrameClock::Half),
+                (29830 + 7457, FrameClock::Quarter),
+            ]
+        );
+    }
+
+    #[test]
+    fn four_step_irq() {
+        let mut frame_counter = FrameCounter::default();
+
+        clocks(&mut frame_counter, 29827);
+        assert!(!frame_counter.irq_flag);
+        frame_counter.clock();
+        assert!(frame_counter.irq_flag);
+
+        // It's raised again on the next two cycles, so acknowledging it right away doesn't stick.
+        frame_counter.irq_flag = false;
+        frame_counter.clock();
+        assert!(frame_counter.irq_flag);
+
+        // Setting the inhibit flag clears it, and keeps it from being raised.
+        frame_counter.write(0b0100_0000, false);
+        assert!(!frame_counter.irq_flag);
+        clocks(&mut frame_counter, 29830 * 2);
+        assert!(!frame_counter.irq_flag);
+    }
+
+    #[test]
+    fn five_step_sequence() {
+        let mut frame_counter = FrameCounter::default();
+        frame_counter.write(0b1000_0000, false);
+
+        // Clocked right away once the write takes effect, then no IRQ at the end.
+        assert_eq!(
+            clocks(&mut frame_counter, 3 + 37282 + 7457),
+            [
+                (3, FrameClock::Half),
+                (3 + 7457, FrameClock::Quarter),
+                (3 + 14913, FrameClock::Half),
+                (3 + 22371, FrameClock::Quarter),
+                (3 + 37281, FrameClock::Half),
+                (3 + 37282 + 7457, FrameClock::Quarter),
+            ]
+        );
+        assert!(!frame_counter.irq_flag);
+    }
+
+    #[test]
+    fn write_delay() {
+        for (odd_cycle, delay) in [(false, 3), (true, 4)] {
+            let mut frame_counter = FrameCounter::default();
+            clocks(&mut frame_counter, 5000);
+
+            // The sequence starts over once the write takes effect.
+            frame_counter.write(0, odd_cycle);
+            let first = clocks(&mut frame_counter, 7460)[0];
+
+            assert_eq!(first, (delay + 7456, FrameClock::Quarter), "{odd_cycle}");
+        }
+    }
+}
@@ -0,0 +1,104 @@
+/// What the upper 5 bits of a length counter load pick, in half frames.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Silences a channel after a set amount of time, unless halted.
+/// Every channel apart from the DMC has one.
+/// Refer to: https://www.nesdev.org/wiki/APU_Length_Counter
+#[derive(Default)]
+pub(super) struct LengthCounter {
+    counter: u8,
+    pub(super) halt: bool,
+    /// Set through $4015. While disabled, the counter stays at 0.
+    enabled: bool,
+}
+
+impl LengthCounter {
+    pub(super) fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0b1_1111) as usize];
+        }
+    }
+
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    /// Clocked by the frame counter, every half frame.
+    pub(super) fn clock(&mut self) {
+        if self.counter > 0 && !self.halt {
+            self.counter -= 1;
+        }
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn half_frames_until_silent(length_counter: &mut LengthCounter) -> usize {
+        let mut half_frames = 0;
+
+        while length_counter.active() {
+            length_counter.clock();
+            half_frames += 1;
+        }
+
+        half_frames
+    }
+
+    #[test]
+    fn length_table() {
+        let mut length_counter = LengthCounter::default();
+        length_counter.set_enabled(true);
+
+        let lengths: Vec<_> = (0..32)
+            .map(|index| {
+                length_counter.load(index);
+                half_frames_until_silent(&mut length_counter)
+            })
+            .collect();
+
+        assert_eq!(
+            lengths,
+            [
+                10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48,
+                20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+            ]
+        );
+    }
+
+    #[test]
+    fn halt_and_enable() {
+        let mut length_counter = LengthCounter::default();
+
+        // Loads are ignored while disabled.
+        length_counter.load(1);
+        assert!(!length_counter.active());
+
+        length_counter.set_enabled(true);
+        length_counter.load(3);
+        length_counter.halt = true;
+        length_counter.clock();
+        length_counter.clock();
+        assert!(length_counter.active());
+
+        length_counter.halt = false;
+        assert_eq!(half_frames_until_silent(&mut length_counter), 2);
+
+        // Disabling clears it right away.
+        length_counter.load(1);
+        length_counter.set_enabled(false);
+        assert!(!length_counter.active());
+    }
+}
@@ -0,0 +1,35 @@
+/// The pulse channels share a DAC, so their output depends on their sum (0-30):
+///
+/// pulse_out = 95.52 / (8128 / (pulse1 + pulse2) + 100)
+pub(super) const PULSE_TABLE: [f32; 31] = pulse_table();
+
+/// Same for triangle, noise and DMC, indexed by 3 * triangle + 2 * noise + dmc (0-202):
+///
+/// tnd_out = 163.67 / (24329 / (3 * triangle + 2 * noise + dmc) + 100)
+pub(super) const TND_TABLE: [f32; 203] = tnd_table();
+
+/// Both formulas divide by zero for silence, which comes out as 0.
+/// Refer to: https://www.nesdev.org/wiki/APU_Mixer
+const fn pulse_table() -> [f32; 31] {
+    let mut table = [0.0; 31];
+    let mut n = 1;
+
+    while n < table.len() {
+        table[n] = 95.52 / (8128.0 / n as f32 + 100.0);
+        n += 1;
+    }
+
+    table
+}
+
+const fn tnd_table() -> [f32; 203] {
+    let mut table = [0.0; 203];
+    let mut n = 1;
+
+    while n < table.len() {
+        table[n] = 163.67 / (24329.0 / n as f32 + 100.0);
+        n += 1;
+    }
+
+    table
+}
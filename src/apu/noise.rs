@@ -0,0 +1,150 @@
+use super::{envelope::Envelope, length_counter::LengthCounter};
+use crate::utils::bits::get_bit;
+
+/// NTSC timer periods, in CPU cycles.
+const PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+/// Pseudo-random noise from a 15-bit linear feedback shift register.
+/// In short mode, the feedback comes from bit 6 instead of bit 1, so the sequence repeats after only 93 steps.
+/// Refer to: https://www.nesdev.org/wiki/APU_Noise
+pub(super) struct Noise {
+    short_mode: bool,
+    period: u16,
+    timer: u16,
+    shift_register: u16,
+    pub(super) envelope: Envelope,
+    pub(super) length_counter: LengthCounter,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            short_mode: false,
+            period: PERIODS[0],
+            timer: 0,
+            // The shift register starts at 1 on power-up. At 0, it would never get out.
+            shift_register: 1,
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
+        }
+    }
+}
+
+impl Noise {
+    /// Takes the register number within the channel (0-3).
+    pub(super) fn write(&mut self, register: u16, value: u8) {
+        match register {
+            // ..LC VVVV: Length counter halt, Constant volume, Volume/envelope period
+            0 => {
+                self.length_counter.halt = get_bit(value, 5);
+                self.envelope.write(value);
+            }
+            1 => {}
+            // M... PPPP: Mode, Period
+            2 => {
+                self.short_mode = get_bit(value, 7);
+                self.period = PERIODS[(value & 0x0F) as usize];
+            }
+            // LLLL L...: Length counter load
+            _ => {
+                self.length_counter.load(value >> 3);
+                self.envelope.restart();
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period;
+
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 1;
+
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    /// 0-15
+    pub(super) fn output(&self) -> u8 {
+        if self.shift_register & 1 != 0 || !self.length_counter.active() {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shifts right away, without waiting for the timer.
+    fn step(noise: &mut Noise) {
+        noise.timer = 0;
+        noise.clock_timer();
+    }
+
+    /// Steps until the shift register gets back to where it started.
+    fn sequence_length(noise: &mut Noise) -> usize {
+        let start = noise.shift_register;
+        let mut steps = 0;
+
+        loop {
+            step(noise);
+            steps += 1;
+
+            if noise.shift_register == start {
+                return steps;
+            }
+        }
+    }
+
+    #[test]
+    fn feedback_taps() {
+        // Bit 0 XOR bit 1.
+        let mut noise = Noise {
+            shift_register: 0b100_0000,
+            ..Noise::default()
+        };
+        step(&mut noise);
+        assert_eq!(noise.shift_register, 0b010_0000);
+
+        // Bit 0 XOR bit 6.
+        noise.write(2, 0b1000_0000);
+        noise.shift_register = 0b100_0000;
+        step(&mut noise);
+        assert_eq!(noise.shift_register, 0x4000 | 0b010_0000);
+    }
+
+    #[test]
+    fn sequence_lengths() {
+        let mut noise = Noise::default();
+        assert_eq!(sequence_length(&mut noise), 32767);
+
+        noise.write(2, 0b1000_0000);
+        assert_eq!(sequence_length(&mut noise), 93);
+    }
+
+    #[test]
+    fn timer_periods() {
+        let mut noise = Noise::default();
+        noise.write(2, 0b0000_0011);
+
+        noise.clock_timer();
+        let shift_register = noise.shift_register;
+
+        for _ in 1..32 {
+            noise.clock_timer();
+        }
+        assert_eq!(noise.shift_register, shift_register);
+
+        noise.clock_timer();
+        assert_ne!(noise.shift_register, shift_register);
+    }
+}
@@ -0,0 +1,223 @@
+use super::{envelope::Envelope, length_counter::LengthCounter};
+use crate::utils::bits::get_bit;
+
+const DUTY_CYCLES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// A square wave with 4 duty cycles, an envelope, a length counter and a sweep unit that bends the pitch.
+/// Refer to: https://www.nesdev.org/wiki/APU_Pulse
+pub(super) struct Pulse {
+    /// The sweep units of the two channels negate differently: pulse 1 uses ones' complement, pulse 2 two's.
+    ones_complement: bool,
+    duty: u8,
+    step: u8,
+    /// 11 bits, in APU cycles.
+    period: u16,
+    timer: u16,
+    pub(super) envelope: Envelope,
+    pub(super) length_counter: LengthCounter,
+    /// 7654 3210
+    ///
+    /// EPPP NSSS
+    ///
+    /// Enabled, divider Period, Negate, Shift count
+    sweep: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    pub(super) fn new(ones_complement: bool) -> Pulse {
+        Pulse {
+            ones_complement,
+            duty: 0,
+            step: 0,
+            period: 0,
+            timer: 0,
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
+            sweep: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+        }
+    }
+
+    /// Takes the register number within the channel (0-3).
+    pub(super) fn write(&mut self, register: u16, value: u8) {
+        match register {
+            // DDLC VVVV: Duty, Length counter halt, Constant volume, Volume/envelope period
+            0 => {
+                self.duty = value >> 6;
+                self.length_counter.halt = get_bit(value, 5);
+                self.envelope.write(value);
+            }
+            1 => {
+                self.sweep = value;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | value as u16,
+            // LLLL LTTT: Length counter load, Timer high
+            _ => {
+                self.period = (self.period & 0x00FF) | (((value & 0b111) as u16) << 8);
+                self.length_counter.load(value >> 3);
+                self.envelope.restart();
+                self.step = 0;
+            }
+        }
+    }
+
+    /// Clocked every APU cycle (every other CPU cycle).
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// The period the sweep unit is heading for. Calculated all the time, even with the sweep disabled.
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> (self.sweep & 0b111);
+
+        if get_bit(self.sweep, 3) {
+            let change = change + self.ones_complement as u16;
+
+            self.period.saturating_sub(change)
+        } else {
+            self.period + change
+        }
+    }
+
+    /// Periods below 8 would be too high-pitched, and targets past 11 bits can't be set, so both mute the channel.
+    fn muted(&self) -> bool {
+        self.period < 8 || self.sweep_target() > 0x07FF
+    }
+
+    /// Clocked by the frame counter, every half frame.
+    pub(super) fn clock_sweep(&mut self) {
+        let enabled = get_bit(self.sweep, 7);
+        let shift = self.sweep & 0b111;
+
+        if self.sweep_divider == 0 && enabled && shift > 0 && !self.muted() {
+            self.period = self.sweep_target();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = (self.sweep >> 4) & 0b111;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// 0-15
+    pub(super) fn output(&self) -> u8 {
+        let high = DUTY_CYCLES[self.duty as usize][self.step as usize] != 0;
+
+        if !high || self.muted() || !self.length_counter.active() {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enabled, with a constant volume of 15, a 50% duty cycle and a long length counter.
+    fn playing(ones_complement: bool, period: u16) -> Pulse {
+        let mut pulse = Pulse::new(ones_complement);
+        pulse.length_counter.set_enabled(true);
+        pulse.write(0, 0b1011_1111);
+        pulse.write(2, period as u8);
+        pulse.write(3, (period >> 8) as u8 | 0b0000_1000);
+
+        pulse
+    }
+
+    /// The output over a whole period of the sequence.
+    fn waveform(pulse: &mut Pulse) -> Vec<u8> {
+        (0..8)
+            .map(|_| {
+                for _ in 0..=pulse.period {
+                    pulse.clock_timer();
+                }
+
+                pulse.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn duty_cycles() {
+        let mut pulse = playing(false, 0x100);
+        assert_eq!(waveform(&mut pulse), [15, 15, 15, 15, 0, 0, 0, 0]);
+
+        pulse.write(0, 0b0011_0001);
+        assert_eq!(waveform(&mut pulse), [1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn negate_differs_between_channels() {
+        let mut pulse_1 = playing(true, 0x100);
+        let mut pulse_2 = playing(false, 0x100);
+
+        pulse_1.write(1, 0b0000_1001);
+        pulse_2.write(1, 0b0000_1001);
+
+        // Pulse 1 subtracts one more.
+        assert_eq!(pulse_1.sweep_target(), 0x07F);
+        assert_eq!(pulse_2.sweep_target(), 0x080);
+
+        pulse_1.write(1, 0b0000_0001);
+        assert_eq!(pulse_1.sweep_target(), 0x180);
+    }
+
+    #[test]
+    fn sweep_updates_the_period() {
+        let mut pulse = playing(false, 0x100);
+
+        // Enabled, every other half frame, shifting by 2.
+        pulse.write(1, 0b1001_0010);
+
+        // The divider starts out at 0, so the first clock already updates it.
+        pulse.clock_sweep();
+        assert_eq!(pulse.period, 0x140);
+        pulse.clock_sweep();
+        assert_eq!(pulse.period, 0x140);
+        pulse.clock_sweep();
+        assert_eq!(pulse.period, 0x190);
+    }
+
+    #[test]
+    fn sweep_muting() {
+        // Too low a period.
+        let mut pulse = playing(false, 7);
+        assert!(pulse.muted());
+        assert!(waveform(&mut pulse).iter().all(|output| *output == 0));
+
+        // A target past $7FF mutes even when the sweep unit is disabled, and doesn't get applied.
+        let mut pulse = playing(false, 0x600);
+        pulse.write(1, 0b0000_0001);
+        assert!(pulse.muted());
+        assert!(waveform(&mut pulse).iter().all(|output| *output == 0));
+
+        pulse.write(1, 0b1000_0001);
+        pulse.clock_sweep();
+        pulse.clock_sweep();
+        assert_eq!(pulse.period, 0x600);
+
+        // Shifting by 0 never changes anything, but can still mute.
+        pulse.write(1, 0b1000_0000);
+        assert!(pulse.muted());
+        pulse.write(1, 0b1000_1000);
+        assert!(!pulse.muted());
+    }
+}
@@ -0,0 +1,135 @@
+use super::length_counter::LengthCounter;
+use crate::utils::bits::get_bit;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+/// A 32-step triangle wave with no volume control.
+/// Besides the length counter, it has a linear counter, which counts quarter frames for finer control.
+/// Refer to: https://www.nesdev.org/wiki/APU_Triangle
+#[derive(Default)]
+pub(super) struct Triangle {
+    step: u8,
+    /// 11 bits, in CPU cycles.
+    period: u16,
+    timer: u16,
+    pub(super) length_counter: LengthCounter,
+    linear_counter: u8,
+    linear_reload_value: u8,
+    linear_reload: bool,
+    /// Halts the length counter, and keeps the linear counter reloading.
+    control: bool,
+}
+
+impl Triangle {
+    /// Takes the register number within the channel (0-3).
+    pub(super) fn write(&mut self, register: u16, value: u8) {
+        match register {
+            // CRRR RRRR: Control, linear counter Reload value
+            0 => {
+                self.control = get_bit(value, 7);
+                self.length_counter.halt = self.control;
+                self.linear_reload_value = value & 0b0111_1111;
+            }
+            1 => {}
+            2 => self.period = (self.period & 0x0700) | value as u16,
+            // LLLL LTTT: Length counter load, Timer high
+            _ => {
+                self.period = (self.period & 0x00FF) | (((value & 0b111) as u16) << 8);
+                self.length_counter.load(value >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle. The sequence only moves on while both counters are running,
+    /// so silencing the triangle leaves it at whatever level it stopped at.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+
+            if self.linear_counter > 0 && self.length_counter.active() {
+                self.step = (self.step + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocked by the frame counter, every quarter frame.
+    pub(super) fn clock_linear_counter(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    /// 0-15
+    pub(super) fn output(&self) -> u8 {
+        SEQUENCE[self.step as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear_counters(triangle: &mut Triangle, quarter_frames: usize) -> Vec<u8> {
+        (0..quarter_frames)
+            .map(|_| {
+                triangle.clock_linear_counter();
+                triangle.linear_counter
+            })
+            .collect()
+    }
+
+    #[test]
+    fn linear_counter_reload_flag() {
+        let mut triangle = Triangle::default();
+        triangle.length_counter.set_enabled(true);
+
+        // Writing the length counter sets the reload flag, which the next quarter frame clears.
+        triangle.write(0, 3);
+        triangle.write(3, 0b0000_1000);
+        assert_eq!(linear_counters(&mut triangle, 5), [3, 2, 1, 0, 0]);
+
+        // With the control flag set, it keeps reloading.
+        triangle.write(0, 0b1000_0011);
+        triangle.write(3, 0b0000_1000);
+        assert_eq!(linear_counters(&mut triangle, 3), [3, 3, 3]);
+
+        // Until it's cleared, and the next reload is the last one.
+        triangle.write(0, 0b0000_0010);
+        assert_eq!(linear_counters(&mut triangle, 4), [2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn sequencer_needs_both_counters() {
+        let mut triangle = Triangle::default();
+        triangle.length_counter.set_enabled(true);
+        triangle.write(0, 1);
+        triangle.write(2, 0);
+        triangle.write(3, 0b0000_1000);
+
+        // The linear counter hasn't been loaded yet.
+        triangle.clock_timer();
+        assert_eq!(triangle.output(), 15);
+
+        triangle.clock_linear_counter();
+        triangle.clock_timer();
+        triangle.clock_timer();
+        assert_eq!(triangle.output(), 13);
+
+        // Stops where it is, instead of dropping to 0.
+        triangle.clock_linear_counter();
+        triangle.clock_timer();
+        assert_eq!(triangle.output(), 13);
+    }
+}
@@ -0,0 +1,74 @@
+/// Everything the CPU sees through its address and data pins.
+/// The CPU is generic over this, so the NES memory map, test harnesses and such can all be plugged in
+/// without touching any instruction code.
+pub trait Bus {
+    /// Reads a byte, the same way the CPU would.
+    /// Note that reads can have side effects (e.g. PPUSTATUS clearing the VBlank flag).
+    fn read(&mut self, addr: u16) -> u8;
+
+    fn write(&mut self, addr: u16, value: u8);
+
+    /// Reads a byte without any side effects. Meant for debuggers, disassemblers and the like.
+    fn peek(&self, addr: u16) -> u8;
+
+    /// Lets everything else on the bus catch up with the CPU, after it has run for `cycles` cycles.
+    fn tick(&mut self, _cycles: usize) {}
+
+    /// Runs any DMA transfers started by the last instruction, with the CPU halted.
+    /// `cycle` is the CPU cycle the halt starts on, as DMA has to line up with odd/even cycles.
+    /// Everything else on the bus keeps running in the meantime.
+    /// Returns how many cycles the CPU was halted for.
+    fn run_dma(&mut self, _cycle: usize) -> usize {
+        0
+    }
+
+    /// State of the shared IRQ line. Anything on the bus can pull it.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// State of the NMI line. The CPU only reacts to it going from low to high.
+    fn nmi(&self) -> bool {
+        false
+    }
+}
+
+/// 64 KB of flat RAM, without any mirroring or memory-mapped registers.
+/// Handy for running CPU test suites that expect to own the whole address space.
+pub struct FlatRam {
+    ram: Box<[u8; 0x10000]>,
+}
+
+impl Default for FlatRam {
+    fn default() -> Self {
+        FlatRam::new()
+    }
+}
+
+impl FlatRam {
+    pub fn new() -> FlatRam {
+        FlatRam {
+            ram: Box::new([0u8; 0x10000]),
+        }
+    }
+
+    /// Copies `data` into RAM, starting at `addr`.
+    /// Panics if it doesn't fit within the address space.
+    pub fn load(&mut self, addr: u16, data: &[u8]) {
+        self.ram[addr as usize..addr as usize + data.len()].copy_from_slice(data);
+    }
+}
+
+impl Bus for FlatRam {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.ram[addr as usize] = value;
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+}
@@ -0,0 +1,67 @@
+/// Standard NES controller buttons, in the order they're shifted out.
+#[derive(Clone, Copy, Debug)]
+pub enum Button {
+    A = 0,
+    B = 1,
+    Select = 2,
+    Start = 3,
+    Up = 4,
+    Down = 5,
+    Left = 6,
+    Right = 7,
+}
+
+/// A standard controller, which is just an 8-bit parallel-in serial-out shift register.
+#[derive(Default)]
+pub struct Controller {
+    /// Currently held buttons, one bit per [Button].
+    buttons: u8,
+    shift_register: u8,
+    /// While the strobe is high, the shift register keeps reloading from the buttons.
+    strobe: bool,
+}
+
+impl Controller {
+    pub fn new() -> Controller {
+        Controller::default()
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.buttons |= 1 << button as u8;
+        } else {
+            self.buttons &= !(1 << button as u8);
+        }
+    }
+
+    /// Called on writes to $4016. Only bit 0 (the strobe) matters.
+    pub fn write(&mut self, value: u8) {
+        self.strobe = value & 1 != 0;
+
+        if self.strobe {
+            self.shift_register = self.buttons;
+        }
+    }
+
+    /// Shifts out the next button. After all 8 are read, official controllers keep returning 1.
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            return self.buttons & 1;
+        }
+
+        let bit = self.shift_register & 1;
+
+        self.shift_register = (self.shift_register >> 1) | 0b1000_0000;
+
+        bit
+    }
+
+    /// Same as [Controller::read], but without shifting.
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            self.buttons & 1
+        } else {
+            self.shift_register & 1
+        }
+    }
+}
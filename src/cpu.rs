@@ -1,4 +1,4 @@
-use crate::memory::Memory;
+use crate::{bus::Bus, memory::Memory, utils::bits::get_bit};
 
 pub mod instructions;
 
@@ -32,6 +32,21 @@ impl From<StatusFlags> for u8 {
     }
 }
 
+impl From<u8> for StatusFlags {
+    fn from(value: u8) -> Self {
+        StatusFlags {
+            carry: get_bit(value, 0),
+            zero: get_bit(value, 1),
+            interrupt_disable: get_bit(value, 2),
+            decimal: get_bit(value, 3),
+            b_flag_4: get_bit(value, 4),
+            b_flag_5: get_bit(value, 5),
+            overflow: get_bit(value, 6),
+            negative: get_bit(value, 7),
+        }
+    }
+}
+
 pub struct CPURegisters {
     pub accumulator: u8,
     pub index_x: u8,
@@ -65,46 +80,553 @@ impl Default for CPURegisters {
 }
 
 #[allow(clippy::upper_case_acronyms)] // No, I don't care about the acronyms.
-pub struct CPU {
+pub struct CPU<B: Bus = Memory> {
     pub registers: CPURegisters,
     pub cycles: usize,
-    pub memory: Memory,
+    pub bus: B,
+    /// NMI is edge-triggered - once requested, it stays pending until serviced.
+    nmi_pending: bool,
+    /// Last seen state of [Bus::nmi], to catch it going high.
+    nmi_line: bool,
+    /// IRQ is level-triggered - devices on the bus hold it through [Bus::irq].
+    /// This is for requesting one from outside, and stays pending until serviced.
+    irq_pending: bool,
+    /// CLI, SEI and PLP change the interrupt disable flag after IRQs have already been polled,
+    /// so the next poll still sees the flag as it was before them. RTI doesn't have this delay.
+    delayed_interrupt_disable: Option<bool>,
+    /// Set by STP. Only a reset gets the CPU going again.
+    halted: bool,
+}
+
+pub const NMI_VECTOR: u16 = 0xFFFA;
+pub const RESET_VECTOR: u16 = 0xFFFC;
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+impl<B: Bus + Default> Default for CPU<B> {
+    fn default() -> Self {
+        CPU::new(B::default())
+    }
 }
 
-impl CPU {
-    pub fn new() -> CPU {
+impl<B: Bus> CPU<B> {
+    pub fn new(bus: B) -> CPU<B> {
         CPU {
             registers: CPURegisters::default(),
             cycles: 0,
-            memory: Memory::new(),
+            bus,
+            nmi_pending: false,
+            nmi_line: false,
+            irq_pending: false,
+            delayed_interrupt_disable: None,
+            halted: false,
         }
     }
 
+    /// Signals an NMI. It will be serviced before the next instruction, regardless of the interrupt disable flag.
+    pub fn request_nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    /// Signals an IRQ. It will be serviced before the next instruction, unless interrupts are disabled.
+    pub fn request_irq(&mut self) {
+        self.irq_pending = true;
+    }
+
+    /// Takes the pending NMI, if there is one. BRK checks this to emulate NMIs hijacking its vector fetch.
+    pub(crate) fn take_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.nmi_pending)
+    }
+
     pub fn incr_cycles(&mut self, amount: usize) {
         self.cycles += amount;
     }
 
-    pub fn incr_pc(&mut self, amount: u16) -> &mut CPU {
-        self.registers.program_counter += amount;
+    pub fn incr_pc(&mut self, amount: u16) -> &mut CPU<B> {
+        self.registers.program_counter = self.registers.program_counter.wrapping_add(amount);
 
         self
     }
 
     /// I am NOT juggling mutable borrows. F--k this.
     pub fn stack_push(&mut self, value: u8) {
-        self.memory
+        self.bus
             .write(0x0100 + self.registers.stack_pointer as u16, value);
 
-        self.registers.stack_pointer -= 1;
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(1);
     }
 
+    /// The stack pointer points at the next free slot, so it has to be incremented before reading.
     pub fn stack_pull(&mut self) -> u8 {
-        let temp = self
-            .memory
-            .fetch(0x0100 + self.registers.stack_pointer as u16);
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_add(1);
+
+        self.bus.read(0x0100 + self.registers.stack_pointer as u16)
+    }
+
+    /// Shared by BRK, IRQ and NMI: pushes the PC and status, disables interrupts and jumps through the vector.
+    /// `b_flag` marks whether the status was pushed by software (BRK) or hardware (IRQ/NMI).
+    /// Takes 7 cycles.
+    pub fn interrupt(&mut self, vector: u16, b_flag: bool) {
+        let bytes: [u8; 2] = self.registers.program_counter.to_le_bytes();
+
+        self.stack_push(bytes[1]);
+
+        self.stack_push(bytes[0]);
+
+        let status = StatusFlags {
+            b_flag_4: b_flag,
+            b_flag_5: true,
+            ..self.registers.status_register
+        };
+
+        self.stack_push(status.into());
+
+        self.registers.status_register.interrupt_disable = true;
+
+        self.registers.program_counter = self.read_vector(vector);
+
+        self.incr_cycles(7);
+    }
+
+    fn read_vector(&mut self, vector: u16) -> u16 {
+        u16::from_le_bytes([self.bus.read(vector), self.bus.read(vector.wrapping_add(1))])
+    }
+
+    /// Puts the CPU into its power-up state, then runs the reset sequence.
+    /// The stack pointer starts at 0x00, so it ends up at 0xFD after reset.
+    pub fn power_on(&mut self) {
+        self.registers = CPURegisters {
+            stack_pointer: 0x00,
+            ..CPURegisters::default()
+        };
+        self.cycles = 0;
+        self.nmi_pending = false;
+        self.nmi_line = false;
+        self.irq_pending = false;
+        self.delayed_interrupt_disable = None;
+
+        self.reset();
+    }
+
+    /// The reset sequence is an interrupt with its stack writes turned into reads.
+    /// The stack pointer still gets decremented three times, but nothing is pushed.
+    /// Other registers are left alone, so this can be used for the reset button as well.
+    /// Takes 7 cycles.
+    pub fn reset(&mut self) {
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(3);
+
+        self.halted = false;
+
+        self.registers.status_register.interrupt_disable = true;
+
+        self.registers.program_counter = self.read_vector(RESET_VECTOR);
+
+        self.incr_cycles(7);
+    }
+
+    /// Fetches the opcode at the program counter, decodes it along with its operands and executes it.
+    /// Afterwards, the rest of the bus gets to catch up, and runs any DMA the instruction started.
+    /// Returns the amount of cycles the instruction took, including the ones the CPU spent halted.
+    pub fn step(&mut self) -> usize {
+        let cycles_before = self.cycles;
+
+        self.execute();
+
+        self.bus.tick(self.cycles - cycles_before);
+
+        let stall = self.bus.run_dma(self.cycles);
+        self.incr_cycles(stall);
+
+        let nmi_line = self.bus.nmi();
+
+        if nmi_line && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+
+        self.nmi_line = nmi_line;
+
+        self.cycles - cycles_before
+    }
+
+    fn execute(&mut self) {
+        // A jammed CPU just sits there, but the rest of the console keeps running.
+        if self.halted {
+            self.incr_cycles(1);
+
+            return;
+        }
+
+        let interrupt_disable = self
+            .delayed_interrupt_disable
+            .take()
+            .unwrap_or(self.registers.status_register.interrupt_disable);
+
+        // Interrupts are polled at instruction boundaries.
+        if self.take_nmi() {
+            self.interrupt(NMI_VECTOR, false);
+
+            return;
+        }
+
+        let irq = self.irq_pending || self.bus.irq();
+
+        if irq && !interrupt_disable {
+            self.irq_pending = false;
+            self.interrupt(IRQ_VECTOR, false);
+
+            return;
+        }
+
+        let opcode = self.bus.read(self.registers.program_counter);
+        let instruction = self.decode_instr(opcode);
+        let interrupt_disable = self.registers.status_register.interrupt_disable;
+
+        instruction.exec(self);
+
+        if instruction.delays_irq_poll() {
+            self.delayed_interrupt_disable = Some(interrupt_disable);
+        }
+    }
+}
+
+/// Loads `program` at $8000 of flat RAM, points the reset vector at it and powers on.
+#[cfg(test)]
+pub(crate) fn cpu_with(program: &[u8]) -> CPU<crate::bus::FlatRam> {
+    let mut bus = crate::bus::FlatRam::new();
+    bus.load(0x8000, program);
+    bus.load(RESET_VECTOR, &[0x00, 0x80]);
+
+    let mut cpu = CPU::new(bus);
+    cpu.power_on();
+
+    cpu
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_on_loads_reset_vector() {
+        let cpu = cpu_with(&[]);
+
+        assert_eq!(cpu.registers.program_counter, 0x8000);
+        assert_eq!(cpu.registers.stack_pointer, 0xFD);
+        assert!(cpu.registers.status_register.interrupt_disable);
+        assert_eq!(cpu.cycles, 7);
+    }
+
+    #[test]
+    fn lda_sets_zero_and_negative() {
+        // LDA #$00, LDA #$80
+        let mut cpu = cpu_with(&[0xA9, 0x00, 0xA9, 0x80]);
+
+        assert_eq!(cpu.step(), 2);
+        assert!(cpu.registers.status_register.zero);
+        assert!(!cpu.registers.status_register.negative);
+
+        assert_eq!(cpu.step(), 2);
+        assert_eq!(cpu.registers.accumulator, 0x80);
+        assert!(!cpu.registers.status_register.zero);
+        assert!(cpu.registers.status_register.negative);
+        assert_eq!(cpu.registers.program_counter, 0x8004);
+    }
+
+    #[test]
+    fn indexed_reads_take_a_cycle_more_across_pages() {
+        // LDX #$01, LDA $0200,X, LDA $02FF,X, LDY #$01, LDA ($10),Y, LDA ($12),Y
+        let mut cpu = cpu_with(&[
+            0xA2, 0x01, 0xBD, 0x00, 0x02, 0xBD, 0xFF, 0x02, 0xA0, 0x01, 0xB1, 0x10, 0xB1, 0x12,
+        ]);
+        cpu.bus.load(0x0010, &[0x00, 0x03, 0xFF, 0x03]);
+        cpu.bus.load(0x0300, &[0x00, 0x42]);
+        cpu.bus.load(0x0400, &[0x24]);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 4);
+        assert_eq!(cpu.step(), 5);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 5);
+        assert_eq!(cpu.registers.accumulator, 0x42);
+        assert_eq!(cpu.step(), 6);
+        assert_eq!(cpu.registers.accumulator, 0x24);
+    }
+
+    #[test]
+    fn stores_always_take_the_extra_cycle() {
+        // LDA #$55, STA $0200,X, STA ($10),Y
+        let mut cpu = cpu_with(&[0xA9, 0x55, 0x9D, 0x00, 0x02, 0x91, 0x10]);
+        cpu.bus.load(0x0010, &[0x00, 0x03]);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 5);
+        assert_eq!(cpu.step(), 6);
+        assert_eq!(cpu.bus.peek(0x0200), 0x55);
+        assert_eq!(cpu.bus.peek(0x0300), 0x55);
+    }
+
+    #[test]
+    fn adc_sets_carry_and_overflow() {
+        // LDA #$7F, ADC #$01, ADC #$80
+        let mut cpu = cpu_with(&[0xA9, 0x7F, 0x69, 0x01, 0x69, 0x80]);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 2);
+        assert_eq!(cpu.registers.accumulator, 0x80);
+        assert!(cpu.registers.status_register.overflow);
+        assert!(!cpu.registers.status_register.carry);
+        assert!(cpu.registers.status_register.negative);
+
+        cpu.step();
+        assert_eq!(cpu.registers.accumulator, 0x00);
+        assert!(cpu.registers.status_register.overflow);
+        assert!(cpu.registers.status_register.carry);
+        assert!(cpu.registers.status_register.zero);
+    }
+
+    #[test]
+    fn sbc_borrows_through_carry() {
+        // SEC, LDA #$00, SBC #$01
+        let mut cpu = cpu_with(&[0x38, 0xA9, 0x00, 0xE9, 0x01]);
+
+        cpu.step();
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.registers.accumulator, 0xFF);
+        assert!(!cpu.registers.status_register.carry);
+        assert!(cpu.registers.status_register.negative);
+    }
+
+    #[test]
+    fn cmp_sets_carry_when_greater_or_equal() {
+        // LDA #$40, CMP #$40, CMP #$41
+        let mut cpu = cpu_with(&[0xA9, 0x40, 0xC9, 0x40, 0xC9, 0x41]);
+
+        cpu.step();
+        cpu.step();
+        assert!(cpu.registers.status_register.carry);
+        assert!(cpu.registers.status_register.zero);
+
+        cpu.step();
+        assert!(!cpu.registers.status_register.carry);
+        assert!(cpu.registers.status_register.negative);
+    }
+
+    #[test]
+    fn branch_cycles() {
+        // LDX #$01 (clears Z), BEQ +2 (not taken), BNE +2 (taken)
+        let mut cpu = cpu_with(&[0xA2, 0x01, 0xF0, 0x02, 0xD0, 0x02]);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 2);
+        assert_eq!(cpu.step(), 3);
+        assert_eq!(cpu.registers.program_counter, 0x8008);
+
+        // BNE -4 at $8100 lands on the previous page.
+        let mut cpu = cpu_with(&[]);
+        cpu.bus.load(0x8100, &[0xD0, 0xFC]);
+        cpu.registers.program_counter = 0x8100;
+
+        assert_eq!(cpu.step(), 4);
+        assert_eq!(cpu.registers.program_counter, 0x80FE);
+    }
+
+    #[test]
+    fn jsr_and_rts() {
+        // JSR $8010 ... RTS
+        let mut cpu = cpu_with(&[0x20, 0x10, 0x80]);
+        cpu.bus.load(0x8010, &[0x60]);
+
+        assert_eq!(cpu.step(), 6);
+        assert_eq!(cpu.registers.program_counter, 0x8010);
+        // The return address minus one, high byte first.
+        assert_eq!(cpu.bus.peek(0x01FD), 0x80);
+        assert_eq!(cpu.bus.peek(0x01FC), 0x02);
+
+        assert_eq!(cpu.step(), 6);
+        assert_eq!(cpu.registers.program_counter, 0x8003);
+        assert_eq!(cpu.registers.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn jmp_indirect_wraps_within_the_page() {
+        // JMP ($02FF)
+        let mut cpu = cpu_with(&[0x6C, 0xFF, 0x02]);
+        cpu.bus.load(0x02FF, &[0x34, 0x12]);
+        cpu.bus.load(0x0200, &[0x56]);
+
+        assert_eq!(cpu.step(), 5);
+        assert_eq!(cpu.registers.program_counter, 0x5634);
+    }
+
+    #[test]
+    fn read_modify_write_cycles() {
+        // INC $10, ASL $0200,X, ROR A
+        let mut cpu = cpu_with(&[0xE6, 0x10, 0x1E, 0x00, 0x02, 0x6A]);
+        cpu.bus.load(0x0010, &[0xFF]);
+        cpu.bus.load(0x0200, &[0x81]);
+
+        assert_eq!(cpu.step(), 5);
+        assert_eq!(cpu.bus.peek(0x0010), 0x00);
+        assert!(cpu.registers.status_register.zero);
+
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.bus.peek(0x0200), 0x02);
+        assert!(cpu.registers.status_register.carry);
+
+        assert_eq!(cpu.step(), 2);
+        assert_eq!(cpu.registers.accumulator, 0x80);
+        assert!(!cpu.registers.status_register.carry);
+    }
+
+    #[test]
+    fn php_pushes_the_b_flag_and_plp_ignores_it() {
+        // PHP, LDA #$00 (sets Z), PLP
+        let mut cpu = cpu_with(&[0x08, 0xA9, 0x00, 0x28]);
+
+        assert_eq!(cpu.step(), 3);
+        assert_eq!(cpu.bus.peek(0x01FD) & 0b0011_0000, 0b0011_0000);
+
+        cpu.step();
+        assert_eq!(cpu.step(), 4);
+        assert!(!cpu.registers.status_register.zero);
+    }
+
+    #[test]
+    fn brk_pushes_pc_and_status_with_b_flag() {
+        // BRK, padding byte
+        let mut cpu = cpu_with(&[0x00, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+        cpu.registers.status_register.interrupt_disable = false;
+
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.registers.program_counter, 0x9000);
+        assert!(cpu.registers.status_register.interrupt_disable);
+
+        // The return address skips the padding byte.
+        assert_eq!(cpu.bus.peek(0x01FD), 0x80);
+        assert_eq!(cpu.bus.peek(0x01FC), 0x02);
+        assert_eq!(cpu.bus.peek(0x01FB) & 0b0011_0000, 0b0011_0000);
+    }
+
+    #[test]
+    fn rti_returns_to_the_pushed_address() {
+        // BRK, padding byte, then RTI in the handler
+        let mut cpu = cpu_with(&[0x00, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+        cpu.bus.load(0x9000, &[0x40]);
+        cpu.registers.status_register.interrupt_disable = false;
+
+        cpu.step();
+        assert_eq!(cpu.step(), 6);
+        assert_eq!(cpu.registers.program_counter, 0x8002);
+        assert!(!cpu.registers.status_register.interrupt_disable);
+        assert_eq!(cpu.registers.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn nmi_hijacks_brk() {
+        let mut cpu = cpu_with(&[0x00, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+        cpu.bus.load(NMI_VECTOR, &[0x00, 0xA0]);
+
+        // An NMI showing up while BRK is already underway.
+        cpu.incr_pc(2);
+        cpu.request_nmi();
+        cpu.brk();
+
+        assert_eq!(cpu.registers.program_counter, 0xA000);
+        // The handler can still tell it was a BRK.
+        assert_eq!(cpu.bus.peek(0x01FB) & 0b0001_0000, 0b0001_0000);
+        // The NMI got used up by the hijack.
+        assert!(!cpu.take_nmi());
+    }
+
+    #[test]
+    fn pending_nmi_runs_before_the_next_instruction() {
+        let mut cpu = cpu_with(&[0xEA]);
+        cpu.bus.load(NMI_VECTOR, &[0x00, 0xA0]);
+
+        cpu.request_nmi();
+
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.registers.program_counter, 0xA000);
+        // Pushed by hardware, so no B flag.
+        assert_eq!(cpu.bus.peek(0x01FB) & 0b0011_0000, 0b0010_0000);
+    }
+
+    #[test]
+    fn cli_delays_irq_by_one_instruction() {
+        // NOP, CLI, NOP, NOP
+        let mut cpu = cpu_with(&[0xEA, 0x58, 0xEA, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+
+        cpu.request_irq();
+
+        cpu.step();
+        assert_eq!(cpu.registers.program_counter, 0x8001);
+
+        // CLI, then the NOP after it still runs before the IRQ.
+        cpu.step();
+        assert_eq!(cpu.step(), 2);
+        assert_eq!(cpu.registers.program_counter, 0x8003);
+
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.registers.program_counter, 0x9000);
+        assert_eq!(cpu.bus.peek(0x01FC), 0x03);
+    }
+
+    #[test]
+    fn irq_sneaks_in_after_sei() {
+        // CLI, SEI, NOP
+        let mut cpu = cpu_with(&[0x58, 0x78, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+
+        cpu.request_irq();
+
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.registers.program_counter, 0x8002);
+
+        // The poll during SEI still saw interrupts enabled, and the pushed status has I set.
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.registers.program_counter, 0x9000);
+        assert_eq!(cpu.bus.peek(0x01FC), 0x02);
+        assert_eq!(cpu.bus.peek(0x01FB) & 0b0000_0100, 0b0000_0100);
+    }
+
+    #[test]
+    fn plp_delays_irq_by_one_instruction() {
+        // PLP, NOP, NOP
+        let mut cpu = cpu_with(&[0x28, 0xEA, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+        // A status with interrupts enabled on top of the stack.
+        cpu.bus.load(0x01FE, &[0x00]);
+
+        cpu.request_irq();
+
+        assert_eq!(cpu.step(), 4);
+        assert!(!cpu.registers.status_register.interrupt_disable);
+        assert_eq!(cpu.step(), 2);
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.bus.peek(0x01FE), 0x80);
+        assert_eq!(cpu.bus.peek(0x01FD), 0x02);
+    }
+
+    #[test]
+    fn rti_enables_irq_immediately() {
+        // BRK, padding byte, NOP, then RTI in the handler
+        let mut cpu = cpu_with(&[0x00, 0xEA, 0xEA]);
+        cpu.bus.load(IRQ_VECTOR, &[0x00, 0x90]);
+        cpu.bus.load(0x9000, &[0x40]);
+        cpu.registers.status_register.interrupt_disable = false;
 
-        self.registers.stack_pointer += 1;
+        cpu.step();
+        cpu.request_irq();
 
-        temp
+        assert_eq!(cpu.step(), 6);
+        assert_eq!(cpu.registers.program_counter, 0x8002);
+        assert_eq!(cpu.step(), 7);
+        assert_eq!(cpu.registers.program_counter, 0x9000);
     }
 }
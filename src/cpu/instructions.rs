@@ -1,6 +1,7 @@
 // 8-bit CPU
 // 16-bit address space
 // 8-bit instructions
+pub mod decoder;
 pub mod exec;
 pub mod implementations;
 
@@ -162,22 +163,43 @@ pub enum Instruction {
     RTI,
     // Unofficial opcodes
     // Refer to: https://www.nesdev.org/wiki/Programming_with_unofficial_opcodes
+    // SKB and IGN are decoded as NOPs with an addressing mode, since they only differ in how many bytes they skip.
+    /// Store Y AND (high byte of address + 1)
     SHY,
+    /// AND + LSR
     ALR,
+    /// AND + copy negative flag to carry
     ANC,
+    /// AND + ROR, with odd carry and overflow behavior
     ARR,
+    /// X = (A AND X) - value
     AXS,
+    /// LDA + LDX
     LAX,
+    /// Store A AND X
     SAX,
+    /// DEC + CMP
     DCP,
+    /// INC + SBC
     ISC,
+    /// ROL + AND
     RLA,
+    /// ROR + ADC
     RRA,
+    /// ASL + ORA
     SLO,
+    /// LSR + EOR
     SRE,
+    /// Store X AND (high byte of address + 1)
     SHX,
+    /// Unstable TXA + AND
     XAA,
+    /// Store A AND X AND (high byte of address + 1)
     AHX,
+    /// Transfer A AND X to stack pointer, then store like AHX
     TAS,
+    /// Load memory AND stack pointer into A, X and stack pointer
     LAS,
+    /// Halt the CPU until it's reset (also known as KIL or JAM)
+    STP,
 }
@@ -1,29 +1,32 @@
-use super::ROM;
-use crate::cpu::instructions::exec::InstructionPair;
-use crate::cpu::instructions::{AddressingMode, Instruction};
+use super::exec::InstructionPair;
+use super::{AddressingMode, Instruction};
+use crate::{bus::Bus, cpu::CPU};
 
-impl ROM {
-    /// Should only be used while fetching an unsigned 8-bit value for an instruction.
-    /// Will panic when it fails to fetch a new byte due to finishing the file or failing to read the file.
-    /// This should generally never happen.
+impl<B: Bus> CPU<B> {
+    /// Should only be used while fetching an unsigned 8-bit operand for an instruction.
+    /// Operands always directly follow the opcode, so no cursor is needed - the PC is advanced by `exec`.
     #[inline]
     fn fetch_u8(&mut self) -> u8 {
-        self.rom_file_iter.next().unwrap().unwrap()
+        self.bus
+            .read(self.registers.program_counter.wrapping_add(1))
     }
 
-    /// Should only be used while fetching a signed 8-bit value for an instruction.
-    /// Will panic when it fails to fetch a new byte due to finishing the file or failing to read the file.
+    /// Should only be used while fetching a signed 8-bit operand for an instruction.
     #[inline]
     fn fetch_i8(&mut self) -> i8 {
         self.fetch_u8() as i8
     }
 
-    /// Fetches two bytes using fetch_u8 and combines it using [u16::from_le_bytes].
-    /// Should only be used while fetching a 16-bit value for an instruction.
-    /// Will panic when it fails to fetch a new byte due to finishing the file or failing to read the file.
+    /// Fetches two bytes following the opcode and combines them using [u16::from_le_bytes].
+    /// Should only be used while fetching a 16-bit operand for an instruction.
     #[inline]
     fn fetch_u16(&mut self) -> u16 {
-        u16::from_le_bytes([self.fetch_u8(), self.fetch_u8()])
+        u16::from_le_bytes([
+            self.bus
+                .read(self.registers.program_counter.wrapping_add(1)),
+            self.bus
+                .read(self.registers.program_counter.wrapping_add(2)),
+        ])
     }
 
     /// Originally, here was a complicated decoding function...
@@ -39,7 +42,7 @@ impl ROM {
                 Instruction::ORA,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
             ),
-            0x02 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x02 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x03 => InstructionPair::new(
                 Instruction::SLO,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
@@ -83,7 +86,7 @@ impl ROM {
                 Instruction::ORA,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0x12 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x12 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x13 => InstructionPair::new(
                 Instruction::SLO,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -137,7 +140,7 @@ impl ROM {
                 Instruction::AND,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
             ),
-            0x22 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x22 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x23 => InstructionPair::new(
                 Instruction::RLA,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
@@ -181,7 +184,7 @@ impl ROM {
                 Instruction::AND,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0x32 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x32 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x33 => InstructionPair::new(
                 Instruction::RLA,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -233,7 +236,7 @@ impl ROM {
                 Instruction::EOR,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
             ),
-            0x42 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x42 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x43 => InstructionPair::new(
                 Instruction::SRE,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
@@ -277,7 +280,7 @@ impl ROM {
                 Instruction::EOR,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0x52 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x52 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x53 => InstructionPair::new(
                 Instruction::SRE,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -329,7 +332,7 @@ impl ROM {
                 Instruction::ADC,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
             ),
-            0x62 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x62 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x63 => InstructionPair::new(
                 Instruction::RRA,
                 AddressingMode::IndexedIndirect(self.fetch_u8()),
@@ -373,7 +376,7 @@ impl ROM {
                 Instruction::ADC,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0x72 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x72 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x73 => InstructionPair::new(
                 Instruction::RRA,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -473,7 +476,7 @@ impl ROM {
                 Instruction::STA,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0x92 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0x92 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0x93 => InstructionPair::new(
                 Instruction::AHX,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -573,7 +576,7 @@ impl ROM {
                 Instruction::LDA,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0xB2 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0xB2 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0xB3 => InstructionPair::new(
                 Instruction::LAX,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -673,7 +676,7 @@ impl ROM {
                 Instruction::CMP,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0xD2 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0xD2 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0xD3 => InstructionPair::new(
                 Instruction::DCP,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -773,7 +776,7 @@ impl ROM {
                 Instruction::SBC,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
             ),
-            0xF2 => InstructionPair::new(Instruction::NOP, AddressingMode::Implicit), // STP
+            0xF2 => InstructionPair::new(Instruction::STP, AddressingMode::Implicit),
             0xF3 => InstructionPair::new(
                 Instruction::ISC,
                 AddressingMode::IndirectIndexed(self.fetch_u8()),
@@ -1,5 +1,5 @@
 use super::*;
-use crate::cpu::CPU;
+use crate::{bus::Bus, cpu::CPU};
 
 #[derive(Debug)]
 pub struct InstructionPair {
@@ -14,7 +14,7 @@ type Cycles = usize;
 /// CAUTION: Use cycles only when the base cycle value is equal to 4. (All that require checking page boundaries.)
 fn absolute_indexed(value: u16, index: u8) -> (Addr, Cycles) {
     let old_hi_nibble = value >> 8;
-    let addr = value + (index as u16);
+    let addr = value.wrapping_add(index as u16);
     let mut cycles: usize = 4;
 
     if (addr >> 8) != old_hi_nibble {
@@ -25,28 +25,57 @@ fn absolute_indexed(value: u16, index: u8) -> (Addr, Cycles) {
     (addr, cycles)
 }
 
+/// Zero page indexing never leaves the zero page.
 #[inline]
-fn indexedindirect(cpu: &CPU, value: u8) -> FetchVal {
-    cpu.memory.fetch(
-        cpu.memory.fetch((value + cpu.registers.index_x) as u16) as u16
-            + (cpu.memory.fetch((value + cpu.registers.index_x + 1) as u16) << 8) as u16,
-    )
+fn zero_page_indexed(value: u8, index: u8) -> Addr {
+    value.wrapping_add(index) as u16
+}
+
+/// Reads a little-endian pointer from the zero page, wrapping around within it.
+#[inline]
+fn zero_page_pointer<B: Bus>(cpu: &mut CPU<B>, value: u8) -> Addr {
+    u16::from_le_bytes([
+        cpu.bus.read(value as u16),
+        cpu.bus.read(value.wrapping_add(1) as u16),
+    ])
+}
+
+#[inline]
+fn indexedindirect_addr<B: Bus>(cpu: &mut CPU<B>, value: u8) -> Addr {
+    let pointer = value.wrapping_add(cpu.registers.index_x);
+
+    zero_page_pointer(cpu, pointer)
+}
+
+#[inline]
+fn indexedindirect<B: Bus>(cpu: &mut CPU<B>, value: u8) -> FetchVal {
+    let addr = indexedindirect_addr(cpu, value);
+
+    cpu.bus.read(addr)
 }
 
 #[inline]
 /// CAUTION: Do not use cycles with STA (Store Accumulator), it's always 6 cycles.
-fn indirectindexed(cpu: &CPU, value: u8) -> (FetchVal, Cycles) {
-    let addr = cpu.memory.fetch(value as u16) as u16
-        + (cpu.memory.fetch((value + 1) as u16) << 8) as u16
-        + cpu.registers.index_y as u16;
+fn indirectindexed_addr<B: Bus>(cpu: &mut CPU<B>, value: u8) -> (Addr, Cycles) {
+    let base = zero_page_pointer(cpu, value);
+    let addr = base.wrapping_add(cpu.registers.index_y as u16);
 
     let mut cycles = 5;
 
-    if (addr << 8) != 0 {
+    if (addr >> 8) != (base >> 8) {
+        // Page crossed.
         cycles += 1;
     }
 
-    (cpu.memory.fetch(addr), cycles)
+    (addr, cycles)
+}
+
+#[inline]
+/// CAUTION: Do not use cycles with STA (Store Accumulator), it's always 6 cycles.
+fn indirectindexed<B: Bus>(cpu: &mut CPU<B>, value: u8) -> (FetchVal, Cycles) {
+    let (addr, cycles) = indirectindexed_addr(cpu, value);
+
+    (cpu.bus.read(addr), cycles)
 }
 
 impl InstructionPair {
@@ -57,7 +86,15 @@ impl InstructionPair {
         }
     }
 
-    pub fn exec(&self, cpu: &mut CPU) {
+    /// CLI, SEI and PLP change the interrupt disable flag on their last cycle, after IRQs are polled.
+    pub fn delays_irq_poll(&self) -> bool {
+        matches!(
+            self.instruction,
+            Instruction::CLI | Instruction::SEI | Instruction::PLP
+        )
+    }
+
+    pub fn exec<B: Bus>(&self, cpu: &mut CPU<B>) {
         let (instruction, addr_mode) = (&self.instruction, &self.addr_mode);
 
         match (instruction, addr_mode) {
@@ -67,31 +104,39 @@ impl InstructionPair {
             }
             (Instruction::LDA, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.lda(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.lda(val, 3);
             }
             (Instruction::LDA, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.lda(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.lda(val, 4);
             }
             (Instruction::LDA, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.lda(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.lda(val, 4);
             }
             (Instruction::LDA, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.lda(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.lda(val, cycles);
             }
             (Instruction::LDA, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.lda(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.lda(val, cycles);
             }
             (Instruction::LDA, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.lda(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.lda(val, 6);
             }
             (Instruction::LDA, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -105,21 +150,27 @@ impl InstructionPair {
             }
             (Instruction::LDX, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.ldx(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.ldx(val, 3);
             }
             (Instruction::LDX, AddressingMode::ZeroPageIndexedY(value)) => {
                 cpu.incr_pc(2);
-                cpu.ldx(cpu.memory.fetch((*value + cpu.registers.index_y) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_y));
+                cpu.ldx(val, 4);
             }
             (Instruction::LDX, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.ldx(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.ldx(val, 4);
             }
             (Instruction::LDX, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.ldx(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.ldx(val, cycles);
             }
             (Instruction::LDY, AddressingMode::Immediate(value)) => {
                 cpu.incr_pc(2);
@@ -127,21 +178,27 @@ impl InstructionPair {
             }
             (Instruction::LDY, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.ldy(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.ldy(val, 3);
             }
             (Instruction::LDY, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.ldy(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.ldy(val, 4);
             }
             (Instruction::LDY, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.ldy(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.ldy(val, 4);
             }
             (Instruction::LDY, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.ldy(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.ldy(val, cycles);
             }
             (Instruction::STA, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
@@ -149,10 +206,7 @@ impl InstructionPair {
             }
             (Instruction::STA, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.sta(
-                    cpu.memory.fetch((*value + cpu.registers.index_x) as u16) as u16,
-                    4,
-                );
+                cpu.sta(zero_page_indexed(*value, cpu.registers.index_x), 4);
             }
             (Instruction::STA, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -165,20 +219,21 @@ impl InstructionPair {
                 cpu.sta(addr, 5);
             }
             (Instruction::STA, AddressingMode::AbsoluteIndexedY(value)) => {
-                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
                 cpu.sta(addr, 5);
             }
             (Instruction::STA, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.sta(indexedindirect(cpu, *value) as u16, 6);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.sta(addr, 6);
             }
             (Instruction::STA, AddressingMode::IndirectIndexed(value)) => {
-                let (addr, _) = indirectindexed(cpu, *value);
+                let (addr, _) = indirectindexed_addr(cpu, *value);
 
                 cpu.incr_pc(2);
-                cpu.sta(addr as u16, 6);
+                cpu.sta(addr, 6);
             }
             (Instruction::STX, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
@@ -186,10 +241,7 @@ impl InstructionPair {
             }
             (Instruction::STX, AddressingMode::ZeroPageIndexedY(value)) => {
                 cpu.incr_pc(2);
-                cpu.stx(
-                    cpu.memory.fetch((*value + cpu.registers.index_y) as u16) as u16,
-                    4,
-                );
+                cpu.stx(zero_page_indexed(*value, cpu.registers.index_y), 4);
             }
             (Instruction::STX, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -201,10 +253,7 @@ impl InstructionPair {
             }
             (Instruction::STY, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.sty(
-                    cpu.memory.fetch((*value + cpu.registers.index_x) as u16) as u16,
-                    4,
-                );
+                cpu.sty(zero_page_indexed(*value, cpu.registers.index_x), 4);
             }
             (Instruction::STY, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -256,31 +305,39 @@ impl InstructionPair {
             }
             (Instruction::AND, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.and(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.and(val, 3);
             }
             (Instruction::AND, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.and(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.and(val, 4);
             }
             (Instruction::AND, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.and(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.and(val, 4);
             }
             (Instruction::AND, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.and(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.and(val, cycles);
             }
             (Instruction::AND, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.and(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.and(val, cycles);
             }
             (Instruction::AND, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.and(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.and(val, 6);
             }
             (Instruction::AND, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -294,31 +351,39 @@ impl InstructionPair {
             }
             (Instruction::EOR, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.eor(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.eor(val, 3);
             }
             (Instruction::EOR, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.eor(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.eor(val, 4);
             }
             (Instruction::EOR, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.eor(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.eor(val, 4);
             }
             (Instruction::EOR, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.eor(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.eor(val, cycles);
             }
             (Instruction::EOR, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.eor(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.eor(val, cycles);
             }
             (Instruction::EOR, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.eor(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.eor(val, 6);
             }
             (Instruction::EOR, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -332,31 +397,39 @@ impl InstructionPair {
             }
             (Instruction::ORA, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.ora(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.ora(val, 3);
             }
             (Instruction::ORA, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.ora(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.ora(val, 4);
             }
             (Instruction::ORA, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.ora(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.ora(val, 4);
             }
             (Instruction::ORA, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.ora(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.ora(val, cycles);
             }
             (Instruction::ORA, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.ora(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.ora(val, cycles);
             }
             (Instruction::ORA, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.ora(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.ora(val, 6);
             }
             (Instruction::ORA, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -366,11 +439,13 @@ impl InstructionPair {
             }
             (Instruction::BIT, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.bit(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.bit(val, 3);
             }
             (Instruction::BIT, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.bit(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.bit(val, 4);
             }
             (Instruction::ADC, AddressingMode::Immediate(value)) => {
                 cpu.incr_pc(2);
@@ -378,31 +453,39 @@ impl InstructionPair {
             }
             (Instruction::ADC, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.adc(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.adc(val, 3);
             }
             (Instruction::ADC, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.adc(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.adc(val, 4);
             }
             (Instruction::ADC, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.adc(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.adc(val, 4);
             }
             (Instruction::ADC, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.adc(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.adc(val, cycles);
             }
             (Instruction::ADC, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.adc(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.adc(val, cycles);
             }
             (Instruction::ADC, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.adc(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.adc(val, 6);
             }
             (Instruction::ADC, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -416,31 +499,39 @@ impl InstructionPair {
             }
             (Instruction::SBC, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.sbc(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.sbc(val, 3);
             }
             (Instruction::SBC, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.sbc(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.sbc(val, 4);
             }
             (Instruction::SBC, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.sbc(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.sbc(val, 4);
             }
             (Instruction::SBC, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.sbc(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.sbc(val, cycles);
             }
             (Instruction::SBC, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.sbc(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.sbc(val, cycles);
             }
             (Instruction::SBC, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.sbc(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.sbc(val, 6);
             }
             (Instruction::SBC, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -454,31 +545,39 @@ impl InstructionPair {
             }
             (Instruction::CMP, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.cmp(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.cmp(val, 3);
             }
             (Instruction::CMP, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.cmp(cpu.memory.fetch((*value + cpu.registers.index_x) as u16), 4);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_x));
+                cpu.cmp(val, 4);
             }
             (Instruction::CMP, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.cmp(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.cmp(val, 4);
             }
             (Instruction::CMP, AddressingMode::AbsoluteIndexedX(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
 
                 cpu.incr_pc(3);
-                cpu.cmp(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.cmp(val, cycles);
             }
             (Instruction::CMP, AddressingMode::AbsoluteIndexedY(value)) => {
                 let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
 
                 cpu.incr_pc(3);
-                cpu.cmp(cpu.memory.fetch(addr), cycles);
+                let val = cpu.bus.read(addr);
+                cpu.cmp(val, cycles);
             }
             (Instruction::CMP, AddressingMode::IndexedIndirect(value)) => {
                 cpu.incr_pc(2);
-                cpu.cmp(indexedindirect(cpu, *value), 6);
+                let val = indexedindirect(cpu, *value);
+                cpu.cmp(val, 6);
             }
             (Instruction::CMP, AddressingMode::IndirectIndexed(value)) => {
                 let (val, cycles) = indirectindexed(cpu, *value);
@@ -492,11 +591,13 @@ impl InstructionPair {
             }
             (Instruction::CPX, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.cpx(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.cpx(val, 3);
             }
             (Instruction::CPX, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.cpx(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.cpx(val, 4);
             }
             (Instruction::CPY, AddressingMode::Immediate(value)) => {
                 cpu.incr_pc(2);
@@ -504,11 +605,13 @@ impl InstructionPair {
             }
             (Instruction::CPY, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
-                cpu.cpy(cpu.memory.fetch(*value as u16), 3);
+                let val = cpu.bus.read(*value as u16);
+                cpu.cpy(val, 3);
             }
             (Instruction::CPY, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
-                cpu.cpy(cpu.memory.fetch(*value), 4);
+                let val = cpu.bus.read(*value);
+                cpu.cpy(val, 4);
             }
             (Instruction::INC, AddressingMode::ZeroPage(value)) => {
                 cpu.incr_pc(2);
@@ -516,7 +619,7 @@ impl InstructionPair {
             }
             (Instruction::INC, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.inc((*value + cpu.registers.index_x) as u16, 6);
+                cpu.inc(zero_page_indexed(*value, cpu.registers.index_x), 6);
             }
             (Instruction::INC, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -524,7 +627,7 @@ impl InstructionPair {
             }
             (Instruction::INC, AddressingMode::AbsoluteIndexedX(value)) => {
                 cpu.incr_pc(3);
-                cpu.inc(value + (cpu.registers.index_x as u16), 7);
+                cpu.inc(value.wrapping_add(cpu.registers.index_x as u16), 7);
             }
             (Instruction::INX, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
@@ -540,7 +643,7 @@ impl InstructionPair {
             }
             (Instruction::DEC, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.dec((*value + cpu.registers.index_x) as u16, 6);
+                cpu.dec(zero_page_indexed(*value, cpu.registers.index_x), 6);
             }
             (Instruction::DEC, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -548,7 +651,7 @@ impl InstructionPair {
             }
             (Instruction::DEC, AddressingMode::AbsoluteIndexedX(value)) => {
                 cpu.incr_pc(3);
-                cpu.dec(value + (cpu.registers.index_x as u16), 7);
+                cpu.dec(value.wrapping_add(cpu.registers.index_x as u16), 7);
             }
             (Instruction::DEX, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
@@ -568,7 +671,7 @@ impl InstructionPair {
             }
             (Instruction::ASL, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.asl((*value + cpu.registers.index_x) as u16, 6);
+                cpu.asl(zero_page_indexed(*value, cpu.registers.index_x), 6);
             }
             (Instruction::ASL, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -576,7 +679,7 @@ impl InstructionPair {
             }
             (Instruction::ASL, AddressingMode::AbsoluteIndexedX(value)) => {
                 cpu.incr_pc(3);
-                cpu.asl(value + (cpu.registers.index_x as u16), 7);
+                cpu.asl(value.wrapping_add(cpu.registers.index_x as u16), 7);
             }
             (Instruction::LSR, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
@@ -588,7 +691,7 @@ impl InstructionPair {
             }
             (Instruction::LSR, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.lsr((*value + cpu.registers.index_x) as u16, 6);
+                cpu.lsr(zero_page_indexed(*value, cpu.registers.index_x), 6);
             }
             (Instruction::LSR, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -596,7 +699,7 @@ impl InstructionPair {
             }
             (Instruction::LSR, AddressingMode::AbsoluteIndexedX(value)) => {
                 cpu.incr_pc(3);
-                cpu.lsr(value + (cpu.registers.index_x as u16), 7);
+                cpu.lsr(value.wrapping_add(cpu.registers.index_x as u16), 7);
             }
             (Instruction::ROL, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
@@ -608,7 +711,7 @@ impl InstructionPair {
             }
             (Instruction::ROL, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.rol((*value + cpu.registers.index_x) as u16, 6);
+                cpu.rol(zero_page_indexed(*value, cpu.registers.index_x), 6);
             }
             (Instruction::ROL, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -616,7 +719,7 @@ impl InstructionPair {
             }
             (Instruction::ROL, AddressingMode::AbsoluteIndexedX(value)) => {
                 cpu.incr_pc(3);
-                cpu.rol(value + (cpu.registers.index_x as u16), 7);
+                cpu.rol(value.wrapping_add(cpu.registers.index_x as u16), 7);
             }
             (Instruction::ROR, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
@@ -628,7 +731,7 @@ impl InstructionPair {
             }
             (Instruction::ROR, AddressingMode::ZeroPageIndexedX(value)) => {
                 cpu.incr_pc(2);
-                cpu.ror((*value + cpu.registers.index_x) as u16, 6);
+                cpu.ror(zero_page_indexed(*value, cpu.registers.index_x), 6);
             }
             (Instruction::ROR, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -636,7 +739,7 @@ impl InstructionPair {
             }
             (Instruction::ROR, AddressingMode::AbsoluteIndexedX(value)) => {
                 cpu.incr_pc(3);
-                cpu.ror(value + (cpu.registers.index_x as u16), 7);
+                cpu.ror(value.wrapping_add(cpu.registers.index_x as u16), 7);
             }
             (Instruction::JMP, AddressingMode::Absolute(value)) => {
                 cpu.incr_pc(3);
@@ -645,14 +748,10 @@ impl InstructionPair {
             (Instruction::JMP, AddressingMode::Indirect(value)) => {
                 let bytes: [u8; 2] = value.to_le_bytes();
 
-                let second_addr = if bytes[1] == 0xFF {
-                    u16::from_le_bytes([bytes[0], 0x00])
-                } else {
-                    *value + 1
-                };
+                // The 6502 doesn't carry into the high byte when the pointer sits at the end of a page.
+                let second_addr = u16::from_le_bytes([bytes[0].wrapping_add(1), bytes[1]]);
 
-                let addr =
-                    u16::from_le_bytes([cpu.memory.fetch(*value), cpu.memory.fetch(second_addr)]);
+                let addr = u16::from_le_bytes([cpu.bus.read(*value), cpu.bus.read(second_addr)]);
 
                 cpu.incr_pc(3);
                 cpu.jmp(addr, 5);
@@ -676,79 +775,407 @@ impl InstructionPair {
             (Instruction::BEQ, AddressingMode::Relative(value)) => {
                 cpu.incr_pc(2);
                 cpu.beq(*value);
-            },
+            }
             (Instruction::BMI, AddressingMode::Relative(value)) => {
                 cpu.incr_pc(2);
                 cpu.bmi(*value);
-            },
+            }
             (Instruction::BNE, AddressingMode::Relative(value)) => {
                 cpu.incr_pc(2);
                 cpu.bne(*value);
-            },
+            }
             (Instruction::BPL, AddressingMode::Relative(value)) => {
                 cpu.incr_pc(2);
                 cpu.bpl(*value);
-            },
+            }
             (Instruction::BVC, AddressingMode::Relative(value)) => {
                 cpu.incr_pc(2);
                 cpu.bvc(*value);
-            },
+            }
             (Instruction::BVS, AddressingMode::Relative(value)) => {
                 cpu.incr_pc(2);
                 cpu.bvs(*value);
-            },
+            }
             (Instruction::CLC, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.clc();
-            },
+            }
             (Instruction::CLD, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.cld();
-            },
+            }
             (Instruction::CLI, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.cli();
-            },
+            }
             (Instruction::CLV, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.clv();
-            },
+            }
             (Instruction::SEC, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.sec();
-            },
+            }
             (Instruction::SED, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.sed();
-            },
+            }
             (Instruction::SEI, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.sei();
-            },
-            (Instruction::BRK, AddressingMode::Implicit) => todo!(),
+            }
+            (Instruction::BRK, AddressingMode::Implicit) => {
+                cpu.incr_pc(2); // BRK skips over a padding byte.
+                cpu.brk();
+            }
             (Instruction::NOP, AddressingMode::Implicit) => {
                 cpu.incr_pc(1);
                 cpu.incr_cycles(2); // Not even bothering with writing a NOP impl.
-            },
-            (Instruction::RTI, AddressingMode::Implicit) => todo!(),
-            _ => todo!("Hit an unimplemented illegal instruction: {:?}", &self),
-            /*
-            Instruction::SHY => todo!(),
-            Instruction::ALR => todo!(),
-            Instruction::ANC => todo!(),
-            Instruction::ARR => todo!(),
-            Instruction::AXS => todo!(),
-            Instruction::LAX => todo!(),
-            Instruction::SAX => todo!(),
-            Instruction::DCP => todo!(),
-            Instruction::ISC => todo!(),
-            Instruction::RLA => todo!(),
-            Instruction::RRA => todo!(),
-            Instruction::SLO => todo!(),
-            Instruction::SRE => todo!(),
-            Instruction::SKB => todo!(),
-            Instruction::IGN => todo!(),
-            */
+            }
+            (Instruction::RTI, AddressingMode::Implicit) => {
+                cpu.incr_pc(1);
+                cpu.rti();
+            }
+            (Instruction::STP, AddressingMode::Implicit) => cpu.stp(),
+            (Instruction::NOP, AddressingMode::Immediate(_)) => {
+                cpu.incr_pc(2);
+                cpu.incr_cycles(2);
+            }
+            (Instruction::NOP, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.nop_read(*value as u16, 3);
+            }
+            (Instruction::NOP, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.nop_read(zero_page_indexed(*value, cpu.registers.index_x), 4);
+            }
+            (Instruction::NOP, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.nop_read(*value, 4);
+            }
+            (Instruction::NOP, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.nop_read(addr, cycles);
+            }
+            (Instruction::SLO, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.slo(*value as u16, 5);
+            }
+            (Instruction::SLO, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.slo(zero_page_indexed(*value, cpu.registers.index_x), 6);
+            }
+            (Instruction::SLO, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.slo(*value, 6);
+            }
+            (Instruction::SLO, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.slo(addr, 7);
+            }
+            (Instruction::SLO, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                cpu.slo(addr, 7);
+            }
+            (Instruction::SLO, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.slo(addr, 8);
+            }
+            (Instruction::SLO, AddressingMode::IndirectIndexed(value)) => {
+                let (addr, _) = indirectindexed_addr(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.slo(addr, 8);
+            }
+            (Instruction::RLA, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.rla(*value as u16, 5);
+            }
+            (Instruction::RLA, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.rla(zero_page_indexed(*value, cpu.registers.index_x), 6);
+            }
+            (Instruction::RLA, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.rla(*value, 6);
+            }
+            (Instruction::RLA, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.rla(addr, 7);
+            }
+            (Instruction::RLA, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                cpu.rla(addr, 7);
+            }
+            (Instruction::RLA, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.rla(addr, 8);
+            }
+            (Instruction::RLA, AddressingMode::IndirectIndexed(value)) => {
+                let (addr, _) = indirectindexed_addr(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.rla(addr, 8);
+            }
+            (Instruction::SRE, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.sre(*value as u16, 5);
+            }
+            (Instruction::SRE, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.sre(zero_page_indexed(*value, cpu.registers.index_x), 6);
+            }
+            (Instruction::SRE, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.sre(*value, 6);
+            }
+            (Instruction::SRE, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.sre(addr, 7);
+            }
+            (Instruction::SRE, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                cpu.sre(addr, 7);
+            }
+            (Instruction::SRE, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.sre(addr, 8);
+            }
+            (Instruction::SRE, AddressingMode::IndirectIndexed(value)) => {
+                let (addr, _) = indirectindexed_addr(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.sre(addr, 8);
+            }
+            (Instruction::RRA, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.rra(*value as u16, 5);
+            }
+            (Instruction::RRA, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.rra(zero_page_indexed(*value, cpu.registers.index_x), 6);
+            }
+            (Instruction::RRA, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.rra(*value, 6);
+            }
+            (Instruction::RRA, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.rra(addr, 7);
+            }
+            (Instruction::RRA, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                cpu.rra(addr, 7);
+            }
+            (Instruction::RRA, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.rra(addr, 8);
+            }
+            (Instruction::RRA, AddressingMode::IndirectIndexed(value)) => {
+                let (addr, _) = indirectindexed_addr(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.rra(addr, 8);
+            }
+            (Instruction::DCP, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.dcp(*value as u16, 5);
+            }
+            (Instruction::DCP, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.dcp(zero_page_indexed(*value, cpu.registers.index_x), 6);
+            }
+            (Instruction::DCP, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.dcp(*value, 6);
+            }
+            (Instruction::DCP, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.dcp(addr, 7);
+            }
+            (Instruction::DCP, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                cpu.dcp(addr, 7);
+            }
+            (Instruction::DCP, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.dcp(addr, 8);
+            }
+            (Instruction::DCP, AddressingMode::IndirectIndexed(value)) => {
+                let (addr, _) = indirectindexed_addr(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.dcp(addr, 8);
+            }
+            (Instruction::ISC, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.isc(*value as u16, 5);
+            }
+            (Instruction::ISC, AddressingMode::ZeroPageIndexedX(value)) => {
+                cpu.incr_pc(2);
+                cpu.isc(zero_page_indexed(*value, cpu.registers.index_x), 6);
+            }
+            (Instruction::ISC, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.isc(*value, 6);
+            }
+            (Instruction::ISC, AddressingMode::AbsoluteIndexedX(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_x);
+
+                cpu.incr_pc(3);
+                cpu.isc(addr, 7);
+            }
+            (Instruction::ISC, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, _) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                cpu.isc(addr, 7);
+            }
+            (Instruction::ISC, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.isc(addr, 8);
+            }
+            (Instruction::ISC, AddressingMode::IndirectIndexed(value)) => {
+                let (addr, _) = indirectindexed_addr(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.isc(addr, 8);
+            }
+            (Instruction::SAX, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                cpu.sax(*value as u16, 3);
+            }
+            (Instruction::SAX, AddressingMode::ZeroPageIndexedY(value)) => {
+                cpu.incr_pc(2);
+                cpu.sax(zero_page_indexed(*value, cpu.registers.index_y), 4);
+            }
+            (Instruction::SAX, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                cpu.sax(*value, 4);
+            }
+            (Instruction::SAX, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let addr = indexedindirect_addr(cpu, *value);
+                cpu.sax(addr, 6);
+            }
+            (Instruction::LAX, AddressingMode::Immediate(value)) => {
+                cpu.incr_pc(2);
+                cpu.lax_imm(*value);
+            }
+            (Instruction::LAX, AddressingMode::ZeroPage(value)) => {
+                cpu.incr_pc(2);
+                let val = cpu.bus.read(*value as u16);
+                cpu.lax(val, 3);
+            }
+            (Instruction::LAX, AddressingMode::ZeroPageIndexedY(value)) => {
+                cpu.incr_pc(2);
+                let val = cpu
+                    .bus
+                    .read(zero_page_indexed(*value, cpu.registers.index_y));
+                cpu.lax(val, 4);
+            }
+            (Instruction::LAX, AddressingMode::Absolute(value)) => {
+                cpu.incr_pc(3);
+                let val = cpu.bus.read(*value);
+                cpu.lax(val, 4);
+            }
+            (Instruction::LAX, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                let val = cpu.bus.read(addr);
+                cpu.lax(val, cycles);
+            }
+            (Instruction::LAX, AddressingMode::IndexedIndirect(value)) => {
+                cpu.incr_pc(2);
+                let val = indexedindirect(cpu, *value);
+                cpu.lax(val, 6);
+            }
+            (Instruction::LAX, AddressingMode::IndirectIndexed(value)) => {
+                let (val, cycles) = indirectindexed(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.lax(val, cycles);
+            }
+            (Instruction::ANC, AddressingMode::Immediate(value)) => {
+                cpu.incr_pc(2);
+                cpu.anc(*value);
+            }
+            (Instruction::ALR, AddressingMode::Immediate(value)) => {
+                cpu.incr_pc(2);
+                cpu.alr(*value);
+            }
+            (Instruction::ARR, AddressingMode::Immediate(value)) => {
+                cpu.incr_pc(2);
+                cpu.arr(*value);
+            }
+            (Instruction::AXS, AddressingMode::Immediate(value)) => {
+                cpu.incr_pc(2);
+                cpu.axs(*value);
+            }
+            (Instruction::XAA, AddressingMode::Immediate(value)) => {
+                cpu.incr_pc(2);
+                cpu.xaa(*value);
+            }
+            (Instruction::AHX, AddressingMode::AbsoluteIndexedY(value)) => {
+                cpu.incr_pc(3);
+                cpu.ahx(*value, 5);
+            }
+            (Instruction::AHX, AddressingMode::IndirectIndexed(value)) => {
+                let base = zero_page_pointer(cpu, *value);
+
+                cpu.incr_pc(2);
+                cpu.ahx(base, 6);
+            }
+            (Instruction::SHX, AddressingMode::AbsoluteIndexedY(value)) => {
+                cpu.incr_pc(3);
+                cpu.shx(*value);
+            }
+            (Instruction::SHY, AddressingMode::AbsoluteIndexedX(value)) => {
+                cpu.incr_pc(3);
+                cpu.shy(*value);
+            }
+            (Instruction::TAS, AddressingMode::AbsoluteIndexedY(value)) => {
+                cpu.incr_pc(3);
+                cpu.tas(*value);
+            }
+            (Instruction::LAS, AddressingMode::AbsoluteIndexedY(value)) => {
+                let (addr, cycles) = absolute_indexed(*value, cpu.registers.index_y);
+
+                cpu.incr_pc(3);
+                let val = cpu.bus.read(addr);
+                cpu.las(val, cycles);
+            }
+            // Every opcode is covered above, this only catches invalid pairs that the decoder never emits.
+            _ => unreachable!("Hit an invalid instruction: {:?}", &self),
         }
     }
 }
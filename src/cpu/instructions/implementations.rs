@@ -1,21 +1,19 @@
 use crate::{
-    cpu::{StatusFlags, CPU},
+    bus::Bus,
+    cpu::{StatusFlags, CPU, IRQ_VECTOR, NMI_VECTOR},
     utils::bits::get_bit,
 };
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     fn set_z_flag(&mut self, value: u8) {
-        if value == 0 {
-            self.registers.status_register.zero = true;
-        }
+        self.registers.status_register.zero = value == 0;
     }
 
     fn set_n_flag(&mut self, value: u8) {
-        if get_bit(value, 7) {
-            self.registers.status_register.negative = true;
-        }
+        self.registers.status_register.negative = get_bit(value, 7);
     }
 
+    /// A taken branch costs an extra cycle, and another one if it lands on a different page.
     fn branch(&mut self, value: i8) {
         let old_bytes: [u8; 2] = self.registers.program_counter.to_le_bytes();
 
@@ -26,8 +24,8 @@ impl CPU {
 
         let new_bytes: [u8; 2] = new_pc.to_le_bytes();
 
-        if old_bytes[0] == new_bytes[0] {
-            self.incr_cycles(2);
+        if old_bytes[1] != new_bytes[1] {
+            self.incr_cycles(1);
         }
 
         self.registers.program_counter = new_pc;
@@ -35,6 +33,27 @@ impl CPU {
         self.incr_cycles(1);
     }
 
+    /// Read-modify-write instructions write the unmodified value back while they're busy modifying it.
+    /// Some mappers (e.g. MMC1) can tell the difference, so the extra write has to happen.
+    fn rmw_read(&mut self, addr: u16) -> u8 {
+        let value = self.bus.read(addr);
+
+        self.bus.write(addr, value);
+
+        value
+    }
+
+    /// Shared by CMP, CPX and CPY.
+    fn compare(&mut self, register: u8, value: u8) {
+        let result = register.wrapping_sub(value);
+
+        self.registers.status_register.carry = register >= value;
+
+        self.set_z_flag(result);
+
+        self.set_n_flag(result);
+    }
+
     pub fn lda(&mut self, value: u8, cycles: usize) {
         self.registers.accumulator = value;
 
@@ -66,19 +85,19 @@ impl CPU {
     }
 
     pub fn sta(&mut self, addr: u16, cycles: usize) {
-        self.memory.write(addr, self.registers.accumulator);
+        self.bus.write(addr, self.registers.accumulator);
 
         self.incr_cycles(cycles);
     }
 
     pub fn stx(&mut self, addr: u16, cycles: usize) {
-        self.memory.write(addr, self.registers.index_x);
+        self.bus.write(addr, self.registers.index_x);
 
         self.incr_cycles(cycles);
     }
 
     pub fn sty(&mut self, addr: u16, cycles: usize) {
-        self.memory.write(addr, self.registers.index_y);
+        self.bus.write(addr, self.registers.index_y);
 
         self.incr_cycles(cycles);
     }
@@ -140,39 +159,26 @@ impl CPU {
     }
 
     pub fn pha(&mut self) {
-        self.memory.write(
-            0x0100 + self.registers.stack_pointer as u16,
-            self.registers.accumulator,
-        );
-
-        self.registers.stack_pointer -= 1;
+        self.stack_push(self.registers.accumulator);
 
         self.incr_cycles(3);
     }
 
+    /// PHP always pushes both B flags set.
     pub fn php(&mut self) {
-        assert!((0x0100 + self.registers.stack_pointer as u16) > 0x00FF);
-        assert!((0x0100 + self.registers.stack_pointer as u16) < 0x0200);
-
-        self.memory.write(
-            0x0100 + self.registers.stack_pointer as u16,
-            self.registers.status_register.into(),
-        );
+        let status = StatusFlags {
+            b_flag_4: true,
+            b_flag_5: true,
+            ..self.registers.status_register
+        };
 
-        self.registers.stack_pointer -= 1;
+        self.stack_push(status.into());
 
         self.incr_cycles(3);
     }
 
     pub fn pla(&mut self) {
-        assert!((0x0100 + self.registers.stack_pointer as u16) > 0x00FF);
-        assert!((0x0100 + self.registers.stack_pointer as u16) < 0x0200);
-
-        self.registers.accumulator = self
-            .memory
-            .fetch(0x0100 + self.registers.stack_pointer as u16);
-
-        self.registers.stack_pointer += 1;
+        self.registers.accumulator = self.stack_pull();
 
         self.set_z_flag(self.registers.accumulator);
 
@@ -181,27 +187,16 @@ impl CPU {
         self.incr_cycles(4);
     }
 
+    /// The B flags don't really exist in the status register, so the pulled ones are ignored.
     pub fn plp(&mut self) {
-        assert!((0x0100 + self.registers.stack_pointer as u16) > 0x00FF);
-        assert!((0x0100 + self.registers.stack_pointer as u16) < 0x0200);
-
-        let temp = self
-            .memory
-            .fetch(0x0100 + self.registers.stack_pointer as u16);
+        let old_status = self.registers.status_register;
 
         self.registers.status_register = StatusFlags {
-            carry: get_bit(temp, 0),
-            zero: get_bit(temp, 1),
-            interrupt_disable: get_bit(temp, 2),
-            decimal: get_bit(temp, 3),
-            b_flag_4: get_bit(temp, 4),
-            b_flag_5: get_bit(temp, 5),
-            overflow: get_bit(temp, 6),
-            negative: get_bit(temp, 7),
+            b_flag_4: old_status.b_flag_4,
+            b_flag_5: old_status.b_flag_5,
+            ..StatusFlags::from(self.stack_pull())
         };
 
-        self.registers.stack_pointer += 1;
-
         self.incr_cycles(4);
     }
 
@@ -238,9 +233,7 @@ impl CPU {
     pub fn bit(&mut self, value: u8, cycles: usize) {
         self.set_z_flag(self.registers.accumulator & value);
 
-        if get_bit(value, 6) {
-            self.registers.status_register.overflow = true;
-        }
+        self.registers.status_register.overflow = get_bit(value, 6);
 
         self.set_n_flag(value);
 
@@ -248,21 +241,18 @@ impl CPU {
     }
 
     pub fn adc(&mut self, value: u8, cycles: usize) {
-        let old_bit_7 = get_bit(self.registers.accumulator, 7);
+        let accumulator = self.registers.accumulator;
 
-        let (temp_val, temp_overflow) = self.registers.accumulator.overflowing_add(value);
-        let (temp_val, temp_overflow_2) =
-            temp_val.overflowing_add(self.registers.status_register.carry as u8);
+        let sum = accumulator as u16 + value as u16 + self.registers.status_register.carry as u16;
+        let result = sum as u8;
 
-        self.registers.accumulator = temp_val;
+        self.registers.status_register.carry = sum > 0xFF;
 
-        if temp_overflow || temp_overflow_2 {
-            self.registers.status_register.carry = true;
-        };
+        // Overflow happens when both operands share a sign, but the result doesn't.
+        self.registers.status_register.overflow =
+            get_bit(!(accumulator ^ value) & (accumulator ^ result), 7);
 
-        if old_bit_7 != get_bit(self.registers.accumulator, 7) {
-            self.registers.status_register.overflow = true;
-        };
+        self.registers.accumulator = result;
 
         self.set_z_flag(self.registers.accumulator);
 
@@ -271,76 +261,33 @@ impl CPU {
         self.incr_cycles(cycles);
     }
 
+    /// The NES' 2A03 lacks decimal mode, so SBC is simply ADC with the operand inverted.
     pub fn sbc(&mut self, value: u8, cycles: usize) {
-        let old_bit_7 = get_bit(self.registers.accumulator, 7);
-
-        let (temp_val, temp_overflow) = self.registers.accumulator.overflowing_sub(value);
-        let (temp_val, temp_overflow_2) =
-            temp_val.overflowing_sub(!self.registers.status_register.carry as u8); // NOT of the carry bit
-
-        self.registers.accumulator = temp_val;
-
-        if temp_overflow || temp_overflow_2 {
-            self.registers.status_register.carry = false;
-        };
-
-        if old_bit_7 != get_bit(self.registers.accumulator, 7) {
-            self.registers.status_register.overflow = true;
-        };
-
-        self.set_z_flag(self.registers.accumulator);
-
-        self.set_n_flag(self.registers.accumulator);
-
-        self.incr_cycles(cycles);
+        self.adc(!value, cycles);
     }
 
     pub fn cmp(&mut self, value: u8, cycles: usize) {
-        if self.registers.accumulator >= value {
-            self.registers.status_register.carry = true;
-        }
-
-        if self.registers.accumulator == value {
-            self.registers.status_register.zero = true;
-        }
-
-        self.set_n_flag(self.registers.accumulator - value);
+        self.compare(self.registers.accumulator, value);
 
         self.incr_cycles(cycles);
     }
 
     pub fn cpx(&mut self, value: u8, cycles: usize) {
-        if self.registers.index_x >= value {
-            self.registers.status_register.carry = true;
-        }
-
-        if self.registers.index_x == value {
-            self.registers.status_register.zero = true;
-        }
-
-        self.set_n_flag(self.registers.index_x - value);
+        self.compare(self.registers.index_x, value);
 
         self.incr_cycles(cycles);
     }
 
     pub fn cpy(&mut self, value: u8, cycles: usize) {
-        if self.registers.index_y >= value {
-            self.registers.status_register.carry = true;
-        }
-
-        if self.registers.index_y == value {
-            self.registers.status_register.zero = true;
-        }
-
-        self.set_n_flag(self.registers.index_y - value);
+        self.compare(self.registers.index_y, value);
 
         self.incr_cycles(cycles);
     }
 
     pub fn inc(&mut self, value: u16, cycles: usize) {
-        let result = self.memory.fetch(value) + 1;
+        let result = self.rmw_read(value).wrapping_add(1);
 
-        self.memory.write(value, result);
+        self.bus.write(value, result);
 
         self.set_z_flag(result);
 
@@ -350,7 +297,7 @@ impl CPU {
     }
 
     pub fn inx(&mut self) {
-        self.registers.index_x += 1;
+        self.registers.index_x = self.registers.index_x.wrapping_add(1);
 
         self.set_z_flag(self.registers.index_x);
 
@@ -360,7 +307,7 @@ impl CPU {
     }
 
     pub fn iny(&mut self) {
-        self.registers.index_y += 1;
+        self.registers.index_y = self.registers.index_y.wrapping_add(1);
 
         self.set_z_flag(self.registers.index_y);
 
@@ -370,9 +317,9 @@ impl CPU {
     }
 
     pub fn dec(&mut self, value: u16, cycles: usize) {
-        let result = self.memory.fetch(value) - 1;
+        let result = self.rmw_read(value).wrapping_sub(1);
 
-        self.memory.write(value, result);
+        self.bus.write(value, result);
 
         self.set_z_flag(result);
 
@@ -382,7 +329,7 @@ impl CPU {
     }
 
     pub fn dex(&mut self) {
-        self.registers.index_x -= 1;
+        self.registers.index_x = self.registers.index_x.wrapping_sub(1);
 
         self.set_z_flag(self.registers.index_x);
 
@@ -392,7 +339,7 @@ impl CPU {
     }
 
     pub fn dey(&mut self) {
-        self.registers.index_y -= 1;
+        self.registers.index_y = self.registers.index_y.wrapping_sub(1);
 
         self.set_z_flag(self.registers.index_y);
 
@@ -404,7 +351,7 @@ impl CPU {
     pub fn asl_acc(&mut self) {
         self.registers.status_register.carry = get_bit(self.registers.accumulator, 7);
 
-        self.registers.accumulator = self.registers.accumulator << 1;
+        self.registers.accumulator <<= 1;
 
         self.set_z_flag(self.registers.accumulator);
 
@@ -414,11 +361,13 @@ impl CPU {
     }
 
     pub fn asl(&mut self, value: u16, cycles: usize) {
-        self.registers.status_register.carry = get_bit(self.memory.fetch(value), 7);
+        let temp = self.rmw_read(value);
 
-        let result = self.memory.fetch(value) << 1;
+        self.registers.status_register.carry = get_bit(temp, 7);
 
-        self.memory.write(value, result);
+        let result = temp << 1;
+
+        self.bus.write(value, result);
 
         self.set_z_flag(result);
 
@@ -430,7 +379,7 @@ impl CPU {
     pub fn lsr_acc(&mut self) {
         self.registers.status_register.carry = get_bit(self.registers.accumulator, 0);
 
-        self.registers.accumulator = self.registers.accumulator >> 1;
+        self.registers.accumulator >>= 1;
 
         self.set_z_flag(self.registers.accumulator);
 
@@ -440,11 +389,13 @@ impl CPU {
     }
 
     pub fn lsr(&mut self, value: u16, cycles: usize) {
-        self.registers.status_register.carry = get_bit(self.memory.fetch(value), 0);
+        let temp = self.rmw_read(value);
+
+        self.registers.status_register.carry = get_bit(temp, 0);
 
-        let result = self.memory.fetch(value) >> 1;
+        let result = temp >> 1;
 
-        self.memory.write(value, result);
+        self.bus.write(value, result);
 
         self.set_z_flag(result);
 
@@ -468,11 +419,13 @@ impl CPU {
 
     pub fn rol(&mut self, value: u16, cycles: usize) {
         let old_carry = self.registers.status_register.carry;
-        self.registers.status_register.carry = get_bit(self.memory.fetch(value), 7);
+        let temp = self.rmw_read(value);
 
-        let result = (self.memory.fetch(value) << 1) + (old_carry as u8);
+        self.registers.status_register.carry = get_bit(temp, 7);
 
-        self.memory.write(value, result);
+        let result = (temp << 1) + (old_carry as u8);
+
+        self.bus.write(value, result);
 
         self.set_z_flag(result);
 
@@ -497,11 +450,13 @@ impl CPU {
 
     pub fn ror(&mut self, value: u16, cycles: usize) {
         let old_carry = self.registers.status_register.carry;
-        self.registers.status_register.carry = get_bit(self.memory.fetch(value), 0);
+        let temp = self.rmw_read(value);
+
+        self.registers.status_register.carry = get_bit(temp, 0);
 
-        let result = (self.memory.fetch(value) >> 1) + (old_carry as u8) * 0b1000_0000;
+        let result = (temp >> 1) + (old_carry as u8) * 0b1000_0000;
 
-        self.memory.write(value, result);
+        self.bus.write(value, result);
 
         self.set_z_flag(result);
 
@@ -516,22 +471,23 @@ impl CPU {
         self.incr_cycles(cycles);
     }
 
+    /// Pushes the address of the last byte of the JSR instruction, high byte first.
     pub fn jsr(&mut self, value: u16) {
-        let bytes: [u8; 2] = (self.registers.program_counter - 1).to_le_bytes();
-
-        self.stack_push(bytes[0]);
+        let bytes: [u8; 2] = self.registers.program_counter.wrapping_sub(1).to_le_bytes();
 
         self.stack_push(bytes[1]);
 
+        self.stack_push(bytes[0]);
+
         self.registers.program_counter = value;
 
         self.incr_cycles(6);
     }
 
     pub fn rts(&mut self) {
-        let (byte_2, byte_1) = (self.stack_pull(), self.stack_pull());
+        let (low_byte, high_byte) = (self.stack_pull(), self.stack_pull());
 
-        self.registers.program_counter = u16::from_le_bytes([byte_1, byte_2]) + 1;
+        self.registers.program_counter = u16::from_le_bytes([low_byte, high_byte]).wrapping_add(1);
 
         self.incr_cycles(6);
     }
@@ -642,11 +598,484 @@ impl CPU {
         self.incr_cycles(2);
     }
 
+    /// If an NMI arrives while BRK is pushing onto the stack, it hijacks the vector fetch.
+    /// The pushed status still has the B flag set, so the handler can tell what happened.
     pub fn brk(&mut self) {
-        self.registers.status_register.b_flag_4 = true;
-        self.registers.status_register.b_flag_5 = true;
-        self.registers.status_register.interrupt_disable = true;
+        let vector = if self.take_nmi() {
+            NMI_VECTOR
+        } else {
+            IRQ_VECTOR
+        };
+
+        self.interrupt(vector, true);
+    }
+
+    pub fn rti(&mut self) {
+        let old_status = self.registers.status_register;
+
+        self.registers.status_register = StatusFlags {
+            b_flag_4: old_status.b_flag_4,
+            b_flag_5: old_status.b_flag_5,
+            ..StatusFlags::from(self.stack_pull())
+        };
+
+        let (low_byte, high_byte) = (self.stack_pull(), self.stack_pull());
+
+        // Unlike RTS, RTI returns to the exact address that was pushed.
+        self.registers.program_counter = u16::from_le_bytes([low_byte, high_byte]);
+
+        self.incr_cycles(6);
+    }
+
+    // Unofficial opcodes
+    // Refer to: https://www.nesdev.org/wiki/Programming_with_unofficial_opcodes
+
+    /// Covers both SKB (skip byte) and IGN (ignore) NOPs, which still perform a read.
+    /// Jams the CPU: it stops fetching instructions and ignores interrupts, until it's reset.
+    /// The program counter is left pointing at the STP itself.
+    pub fn stp(&mut self) {
+        self.halted = true;
+
+        self.incr_cycles(2);
+    }
+
+    pub fn nop_read(&mut self, addr: u16, cycles: usize) {
+        self.bus.read(addr);
+
+        self.incr_cycles(cycles);
+    }
+
+    /// ASL + ORA
+    pub fn slo(&mut self, addr: u16, cycles: usize) {
+        let value = self.rmw_read(addr);
+
+        self.registers.status_register.carry = get_bit(value, 7);
+
+        let result = value << 1;
+
+        self.bus.write(addr, result);
+
+        self.ora(result, cycles);
+    }
+
+    /// ROL + AND
+    pub fn rla(&mut self, addr: u16, cycles: usize) {
+        let value = self.rmw_read(addr);
+        let old_carry = self.registers.status_register.carry;
+
+        self.registers.status_register.carry = get_bit(value, 7);
+
+        let result = (value << 1) + (old_carry as u8);
+
+        self.bus.write(addr, result);
+
+        self.and(result, cycles);
+    }
+
+    /// LSR + EOR
+    pub fn sre(&mut self, addr: u16, cycles: usize) {
+        let value = self.rmw_read(addr);
+
+        self.registers.status_register.carry = get_bit(value, 0);
+
+        let result = value >> 1;
+
+        self.bus.write(addr, result);
+
+        self.eor(result, cycles);
+    }
+
+    /// ROR + ADC. The carry out of ROR feeds into ADC.
+    pub fn rra(&mut self, addr: u16, cycles: usize) {
+        let value = self.rmw_read(addr);
+        let old_carry = self.registers.status_register.carry;
+
+        self.registers.status_register.carry = get_bit(value, 0);
+
+        let result = (value >> 1) + (old_carry as u8) * 0b1000_0000;
+
+        self.bus.write(addr, result);
+
+        self.adc(result, cycles);
+    }
+
+    /// Stores A AND X. Doesn't affect any flags.
+    pub fn sax(&mut self, addr: u16, cycles: usize) {
+        self.bus
+            .write(addr, self.registers.accumulator & self.registers.index_x);
+
+        self.incr_cycles(cycles);
+    }
+
+    /// LDA + LDX
+    pub fn lax(&mut self, value: u8, cycles: usize) {
+        self.registers.index_x = value;
+
+        self.lda(value, cycles);
+    }
+
+    /// Immediate LAX (also known as LXA or ATX) is unstable, as it ORs the accumulator with a "magic" constant first.
+    /// The constant varies between chips, 0xFF makes it behave like the other LAX variants.
+    pub fn lax_imm(&mut self, value: u8) {
+        let result = (self.registers.accumulator | 0xFF) & value;
+
+        self.lax(result, 2);
+    }
+
+    /// DEC + CMP
+    pub fn dcp(&mut self, addr: u16, cycles: usize) {
+        let result = self.rmw_read(addr).wrapping_sub(1);
+
+        self.bus.write(addr, result);
+
+        self.cmp(result, cycles);
     }
 
+    /// INC + SBC
+    pub fn isc(&mut self, addr: u16, cycles: usize) {
+        let result = self.rmw_read(addr).wrapping_add(1);
 
+        self.bus.write(addr, result);
+
+        self.sbc(result, cycles);
+    }
+
+    /// AND, then copies the negative flag into carry.
+    pub fn anc(&mut self, value: u8) {
+        self.and(value, 2);
+
+        self.registers.status_register.carry = self.registers.status_register.negative;
+    }
+
+    /// AND + LSR on the accumulator
+    pub fn alr(&mut self, value: u8) {
+        let temp = self.registers.accumulator & value;
+
+        self.registers.status_register.carry = get_bit(temp, 0);
+
+        self.registers.accumulator = temp >> 1;
+
+        self.set_z_flag(self.registers.accumulator);
+
+        self.set_n_flag(self.registers.accumulator);
+
+        self.incr_cycles(2);
+    }
+
+    /// AND + ROR on the accumulator, except the carry and overflow flags come from bits 6 and 5 of the result.
+    pub fn arr(&mut self, value: u8) {
+        let temp = self.registers.accumulator & value;
+
+        self.registers.accumulator =
+            (temp >> 1) + (self.registers.status_register.carry as u8) * 0b1000_0000;
+
+        let bit_6 = get_bit(self.registers.accumulator, 6);
+        let bit_5 = get_bit(self.registers.accumulator, 5);
+
+        self.registers.status_register.carry = bit_6;
+        self.registers.status_register.overflow = bit_6 ^ bit_5;
+
+        self.set_z_flag(self.registers.accumulator);
+
+        self.set_n_flag(self.registers.accumulator);
+
+        self.incr_cycles(2);
+    }
+
+    /// X = (A AND X) - value, setting flags like CMP. Ignores the carry going in.
+    pub fn axs(&mut self, value: u8) {
+        let temp = self.registers.accumulator & self.registers.index_x;
+
+        self.compare(temp, value);
+
+        self.registers.index_x = temp.wrapping_sub(value);
+
+        self.incr_cycles(2);
+    }
+
+    /// Highly unstable, the "magic" constant ORed into the accumulator depends on the chip and even temperature.
+    /// 0xEE is the commonly observed value.
+    pub fn xaa(&mut self, value: u8) {
+        let result = (self.registers.accumulator | 0xEE) & self.registers.index_x & value;
+
+        self.lda(result, 2);
+    }
+
+    /// Shared by AHX, SHX, SHY and TAS.
+    /// These store the value ANDed with the high byte of the base address plus one.
+    /// When indexing crosses a page, the high byte of the target address gets replaced by the stored value.
+    fn unstable_store(&mut self, base: u16, index: u8, value: u8) {
+        let [_, base_high] = base.to_le_bytes();
+        let addr = base.wrapping_add(index as u16);
+        let [addr_low, addr_high] = addr.to_le_bytes();
+
+        let result = value & base_high.wrapping_add(1);
+
+        let addr = if addr_high != base_high {
+            u16::from_le_bytes([addr_low, result])
+        } else {
+            addr
+        };
+
+        self.bus.write(addr, result);
+    }
+
+    /// Stores A AND X AND (H + 1). Also known as SHA.
+    pub fn ahx(&mut self, base: u16, cycles: usize) {
+        self.unstable_store(
+            base,
+            self.registers.index_y,
+            self.registers.accumulator & self.registers.index_x,
+        );
+
+        self.incr_cycles(cycles);
+    }
+
+    /// Stores X AND (H + 1).
+    pub fn shx(&mut self, base: u16) {
+        self.unstable_store(base, self.registers.index_y, self.registers.index_x);
+
+        self.incr_cycles(5);
+    }
+
+    /// Stores Y AND (H + 1).
+    pub fn shy(&mut self, base: u16) {
+        self.unstable_store(base, self.registers.index_x, self.registers.index_y);
+
+        self.incr_cycles(5);
+    }
+
+    /// Puts A AND X into the stack pointer, then stores it like AHX.
+    pub fn tas(&mut self, base: u16) {
+        self.registers.stack_pointer = self.registers.accumulator & self.registers.index_x;
+
+        self.unstable_store(base, self.registers.index_y, self.registers.stack_pointer);
+
+        self.incr_cycles(5);
+    }
+
+    /// Loads memory AND the stack pointer into A, X and the stack pointer.
+    pub fn las(&mut self, value: u8, cycles: usize) {
+        let result = value & self.registers.stack_pointer;
+
+        self.registers.stack_pointer = result;
+
+        self.lax(result, cycles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::{Bus, FlatRam},
+        cpu::{cpu_with, CPU},
+    };
+
+    /// Runs `count` instructions, returning how many cycles the last one took.
+    fn run(cpu: &mut CPU<FlatRam>, count: usize) -> usize {
+        (0..count).map(|_| cpu.step()).last().unwrap_or(0)
+    }
+
+    #[test]
+    fn lax_immediate_ors_in_0xff() {
+        // LDA #$12, LAX #$34
+        let mut cpu = cpu_with(&[0xA9, 0x12, 0xAB, 0x34]);
+
+        assert_eq!(run(&mut cpu, 2), 2);
+        assert_eq!(cpu.registers.accumulator, 0x34);
+        assert_eq!(cpu.registers.index_x, 0x34);
+    }
+
+    #[test]
+    fn xaa_ors_in_0xee() {
+        // LDA #$00, LDX #$FF, XAA #$FF
+        let mut cpu = cpu_with(&[0xA9, 0x00, 0xA2, 0xFF, 0x8B, 0xFF]);
+
+        assert_eq!(run(&mut cpu, 3), 2);
+        assert_eq!(cpu.registers.accumulator, 0xEE);
+        assert!(cpu.registers.status_register.negative);
+    }
+
+    #[test]
+    fn shx_ands_with_the_high_byte_plus_one() {
+        // LDX #$FF, LDY #$01, SHX $1200,Y
+        let mut cpu = cpu_with(&[0xA2, 0xFF, 0xA0, 0x01, 0x9E, 0x00, 0x12]);
+
+        assert_eq!(run(&mut cpu, 3), 5);
+        assert_eq!(cpu.bus.peek(0x1201), 0x13);
+    }
+
+    #[test]
+    fn unstable_stores_replace_the_high_byte_across_pages() {
+        // LDX #$05, LDY #$01, SHX $12FF,Y
+        let mut cpu = cpu_with(&[0xA2, 0x05, 0xA0, 0x01, 0x9E, 0xFF, 0x12]);
+
+        run(&mut cpu, 3);
+        // $1300 turns into $0100, as 0x05 AND 0x13 = 0x01.
+        assert_eq!(cpu.bus.peek(0x0100), 0x01);
+        assert_eq!(cpu.bus.peek(0x1300), 0x00);
+
+        // LDY #$07, LDX #$01, SHY $12FF,X
+        let mut cpu = cpu_with(&[0xA0, 0x07, 0xA2, 0x01, 0x9C, 0xFF, 0x12]);
+
+        assert_eq!(run(&mut cpu, 3), 5);
+        assert_eq!(cpu.bus.peek(0x0300), 0x03);
+    }
+
+    #[test]
+    fn ahx_stores_a_and_x() {
+        // LDA #$FF, LDX #$0F, LDY #$01, AHX ($10),Y, AHX $1300,Y
+        let mut cpu = cpu_with(&[
+            0xA9, 0xFF, 0xA2, 0x0F, 0xA0, 0x01, 0x93, 0x10, 0x9F, 0x00, 0x13,
+        ]);
+        cpu.bus.load(0x0010, &[0x00, 0x12]);
+
+        assert_eq!(run(&mut cpu, 4), 6);
+        assert_eq!(cpu.bus.peek(0x1201), 0x03);
+
+        assert_eq!(run(&mut cpu, 1), 5);
+        assert_eq!(cpu.bus.peek(0x1301), 0x04);
+    }
+
+    #[test]
+    fn tas_sets_the_stack_pointer() {
+        // LDA #$F0, LDX #$3F, LDY #$01, TAS $1200,Y
+        let mut cpu = cpu_with(&[0xA9, 0xF0, 0xA2, 0x3F, 0xA0, 0x01, 0x9B, 0x00, 0x12]);
+
+        assert_eq!(run(&mut cpu, 4), 5);
+        assert_eq!(cpu.registers.stack_pointer, 0x30);
+        assert_eq!(cpu.bus.peek(0x1201), 0x10);
+    }
+
+    #[test]
+    fn las_ands_with_the_stack_pointer() {
+        // LDY #$01, LAS $1200,Y, LAS $12FF,Y
+        let mut cpu = cpu_with(&[0xA0, 0x01, 0xBB, 0x00, 0x12, 0xBB, 0xFF, 0x12]);
+        cpu.bus.load(0x1201, &[0xF7]);
+        cpu.bus.load(0x1300, &[0x0F]);
+
+        assert_eq!(run(&mut cpu, 2), 4);
+        assert_eq!(cpu.registers.accumulator, 0xF5);
+        assert_eq!(cpu.registers.index_x, 0xF5);
+        assert_eq!(cpu.registers.stack_pointer, 0xF5);
+
+        assert_eq!(run(&mut cpu, 1), 5);
+        assert_eq!(cpu.registers.stack_pointer, 0x05);
+    }
+
+    #[test]
+    fn dcp_decrements_then_compares() {
+        // LDA #$40, DCP $1200, LDX #$01, DCP $1200,X
+        let mut cpu = cpu_with(&[0xA9, 0x40, 0xCF, 0x00, 0x12, 0xA2, 0x01, 0xDF, 0x00, 0x12]);
+        cpu.bus.load(0x1200, &[0x41, 0x80]);
+
+        assert_eq!(run(&mut cpu, 2), 6);
+        assert_eq!(cpu.bus.peek(0x1200), 0x40);
+        assert!(cpu.registers.status_register.zero);
+        assert!(cpu.registers.status_register.carry);
+
+        assert_eq!(run(&mut cpu, 2), 7);
+        assert_eq!(cpu.bus.peek(0x1201), 0x7F);
+        assert!(!cpu.registers.status_register.carry);
+    }
+
+    #[test]
+    fn isc_increments_then_subtracts() {
+        // SEC, LDA #$10, ISC $1200
+        let mut cpu = cpu_with(&[0x38, 0xA9, 0x10, 0xEF, 0x00, 0x12]);
+        cpu.bus.load(0x1200, &[0x0F]);
+
+        assert_eq!(run(&mut cpu, 3), 6);
+        assert_eq!(cpu.bus.peek(0x1200), 0x10);
+        assert_eq!(cpu.registers.accumulator, 0x00);
+        assert!(cpu.registers.status_register.zero);
+        assert!(cpu.registers.status_register.carry);
+    }
+
+    #[test]
+    fn slo_shifts_then_ors() {
+        // LDA #$01, SLO $1200
+        let mut cpu = cpu_with(&[0xA9, 0x01, 0x0F, 0x00, 0x12]);
+        cpu.bus.load(0x1200, &[0x81]);
+
+        assert_eq!(run(&mut cpu, 2), 6);
+        assert_eq!(cpu.bus.peek(0x1200), 0x02);
+        assert_eq!(cpu.registers.accumulator, 0x03);
+        assert!(cpu.registers.status_register.carry);
+    }
+
+    #[test]
+    fn arr_takes_carry_and_overflow_from_bits_6_and_5() {
+        // SEC, LDA #$FF, ARR #$FF, CLC, LDA #$FF, ARR #$80
+        let mut cpu = cpu_with(&[0x38, 0xA9, 0xFF, 0x6B, 0xFF, 0x18, 0xA9, 0xFF, 0x6B, 0x80]);
+
+        assert_eq!(run(&mut cpu, 3), 2);
+        assert_eq!(cpu.registers.accumulator, 0xFF);
+        assert!(cpu.registers.status_register.carry);
+        assert!(!cpu.registers.status_register.overflow);
+        assert!(cpu.registers.status_register.negative);
+
+        run(&mut cpu, 3);
+        assert_eq!(cpu.registers.accumulator, 0x40);
+        assert!(cpu.registers.status_register.carry);
+        assert!(cpu.registers.status_register.overflow);
+    }
+
+    #[test]
+    fn axs_subtracts_from_a_and_x() {
+        // LDA #$F0, LDX #$3C, AXS #$10
+        let mut cpu = cpu_with(&[0xA9, 0xF0, 0xA2, 0x3C, 0xCB, 0x10]);
+
+        assert_eq!(run(&mut cpu, 3), 2);
+        assert_eq!(cpu.registers.index_x, 0x20);
+        assert!(cpu.registers.status_register.carry);
+    }
+
+    #[test]
+    fn anc_copies_negative_into_carry() {
+        // LDA #$FF, ANC #$80
+        let mut cpu = cpu_with(&[0xA9, 0xFF, 0x0B, 0x80]);
+
+        assert_eq!(run(&mut cpu, 2), 2);
+        assert_eq!(cpu.registers.accumulator, 0x80);
+        assert!(cpu.registers.status_register.carry);
+    }
+
+    #[test]
+    fn sax_stores_without_touching_flags() {
+        // LDA #$F0, LDX #$3C, SAX $10
+        let mut cpu = cpu_with(&[0xA9, 0xF0, 0xA2, 0x3C, 0x87, 0x10]);
+
+        assert_eq!(run(&mut cpu, 3), 3);
+        assert_eq!(cpu.bus.peek(0x0010), 0x30);
+        assert!(!cpu.registers.status_register.negative);
+    }
+
+    #[test]
+    fn ign_reads_take_a_cycle_more_across_pages() {
+        // LDX #$01, IGN $1200,X, IGN $12FF,X
+        let mut cpu = cpu_with(&[0xA2, 0x01, 0x1C, 0x00, 0x12, 0x1C, 0xFF, 0x12]);
+
+        assert_eq!(run(&mut cpu, 2), 4);
+        assert_eq!(run(&mut cpu, 1), 5);
+        assert_eq!(cpu.registers.program_counter, 0x8008);
+    }
+
+    #[test]
+    fn stp_jams_until_reset() {
+        // STP, NOP
+        let mut cpu = cpu_with(&[0x02, 0xEA]);
+        cpu.bus.load(crate::cpu::NMI_VECTOR, &[0x00, 0x90]);
+
+        assert_eq!(run(&mut cpu, 1), 2);
+
+        // Neither instructions nor interrupts run, but time still passes.
+        cpu.request_nmi();
+        assert_eq!(run(&mut cpu, 10), 1);
+        assert_eq!(cpu.registers.program_counter, 0x8000);
+
+        cpu.reset();
+        assert_eq!(run(&mut cpu, 1), 7);
+        assert_eq!(cpu.registers.program_counter, 0x9000);
+    }
 }
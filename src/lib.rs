@@ -0,0 +1,10 @@
+pub mod apu;
+pub mod bus;
+pub mod controller;
+pub mod cpu;
+pub mod mapper;
+pub mod memory;
+pub mod ppu;
+pub mod rom;
+pub mod save;
+pub mod utils;
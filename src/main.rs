@@ -1,13 +1,74 @@
-mod cpu;
-mod memory;
-mod rom;
-mod utils;
+use std::{
+    env,
+    fs::File,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
 
-use std::fs::File;
+use fenes::{cpu, mapper, memory, ppu::palette::Palette, rom, save};
 
-fn main() -> std::io::Result<()> {
-    let rom = rom::ROM::new(File::open("rom")?).load()?;
-    let mut CPU = cpu::CPU::new();
+/// How often battery-backed memory gets saved, in CPU cycles. About 5 seconds.
+const SAVE_INTERVAL: usize = 5 * 1_789_773;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+    let rom_path = args.next().unwrap_or_else(|| "rom".to_string());
+    let palette_path = args.next();
+
+    let rom = rom::ROM::load(File::open(&rom_path)?)?;
+    let battery = rom.info.nonvolatile_memory;
+    let vs_ppu_type = rom.info.vs_ppu_type;
+
+    let mut memory = memory::Memory::new();
+    memory.insert_cartridge(mapper::create(rom)?);
+
+    // A palette file overrides whatever the PPU would look like.
+    if let Some(palette_path) = palette_path {
+        memory
+            .ppu
+            .set_palette(Palette::load(File::open(palette_path)?)?);
+    } else if let Some(vs_ppu_type) = vs_ppu_type {
+        memory.ppu.set_palette(Palette::for_vs_ppu(vs_ppu_type));
+    }
+
+    if battery {
+        memory.attach_save_file(save::SaveFile::beside(&rom_path))?;
+    }
+
+    // There's no window to close yet, so pressing Enter (or closing stdin) is how to quit.
+    let running = Arc::new(AtomicBool::new(true));
+
+    {
+        let running = Arc::clone(&running);
+
+        thread::spawn(move || {
+            let _ = io::stdin().read_line(&mut String::new());
+            running.store(false, Ordering::Relaxed);
+        });
+    }
+
+    let mut cpu = cpu::CPU::new(memory);
+    cpu.power_on();
+
+    let mut last_save = cpu.cycles;
+
+    while running.load(Ordering::Relaxed) {
+        cpu.step();
+
+        if cpu.cycles - last_save >= SAVE_INTERVAL {
+            last_save = cpu.cycles;
+
+            if let Err(error) = cpu.bus.save() {
+                eprintln!("Couldn't write the save file: {error}");
+            }
+        }
+    }
+
+    cpu.bus.save()?;
 
     Ok(())
 }
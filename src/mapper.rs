@@ -0,0 +1,372 @@
+use crate::rom::{ines::iNESInfo, RomError, ROM};
+
+pub mod axrom;
+pub mod cnrom;
+pub mod color_dreams;
+pub mod fme7;
+pub mod gxrom;
+pub mod mmc1;
+pub mod mmc3;
+pub mod mmc5;
+pub mod namco163;
+pub mod nrom;
+pub mod uxrom;
+pub mod vrc4;
+pub mod vrc6;
+pub mod vrc7;
+mod vrc_irq;
+
+/// How the PPU's two 1 KB pages of CIRAM are arranged into the four nametables.
+/// Note that mirroring is named after the direction the nametables repeat in, not how they're arranged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    /// $2000 = $2400, $2800 = $2C00 (vertical arrangement)
+    Horizontal,
+    /// $2000 = $2800, $2400 = $2C00 (horizontal arrangement)
+    Vertical,
+    /// All four nametables point to the first page of CIRAM.
+    SingleScreenLower,
+    /// All four nametables point to the second page of CIRAM.
+    SingleScreenUpper,
+    /// The cartridge provides an extra 2 KB of VRAM, so every nametable is unique.
+    FourScreen,
+    /// Each nametable ($2000, $2400, $2800, $2C00) picks its own CIRAM page (0 or 1).
+    Custom([u8; 4]),
+}
+
+impl Mirroring {
+    /// Mirroring hard-wired on the board, as described by the header.
+    pub fn from_header(info: &iNESInfo) -> Mirroring {
+        if info.hardwired_fourscreen_mode {
+            Mirroring::FourScreen
+        } else if info.hardwired_nametable_mirroring {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+
+    /// Which 1 KB page of VRAM a nametable ($2000, $2400, $2800, $2C00) is in.
+    /// Pages 0 and 1 are CIRAM, 2 and 3 only exist on four-screen cartridges.
+    pub fn nametable_page(self, nametable: usize) -> usize {
+        match self {
+            Mirroring::Horizontal => nametable >> 1,
+            Mirroring::Vertical => nametable & 1,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::FourScreen => nametable,
+            Mirroring::Custom(pages) => (pages[nametable] & 1) as usize,
+        }
+    }
+
+    /// Where a nametable address ($2000-$2FFF, or its mirror up to $3EFF) lands in VRAM.
+    pub fn vram_index(self, addr: u16) -> usize {
+        let nametable = ((addr >> 10) & 0b11) as usize;
+
+        (self.nametable_page(nametable) << 10) | (addr & 0x03FF) as usize
+    }
+}
+
+/// What happens when the CPU writes to a register that shares its address with PRG-ROM.
+/// Discrete-logic boards often don't disable the ROM during writes, so both chips drive the bus at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusConflicts {
+    None,
+    /// The ROM wins wherever it drives a 0, so the register sees the written value ANDed with the ROM byte.
+    And,
+}
+
+impl BusConflicts {
+    /// For the discrete-logic mappers, NES 2.0 submapper 1 means no bus conflicts, and 2 means AND-type conflicts.
+    /// Older headers can't tell, and most games avoid conflicts anyway, so they're off by default.
+    pub fn from_header(info: &iNESInfo) -> BusConflicts {
+        match info.submapper {
+            2 => BusConflicts::And,
+            _ => BusConflicts::None,
+        }
+    }
+
+    /// The value that actually reaches the register, given the ROM byte at the written address.
+    pub fn apply(self, rom_value: u8, value: u8) -> u8 {
+        match self {
+            BusConflicts::None => value,
+            BusConflicts::And => rom_value & value,
+        }
+    }
+}
+
+/// Everything on the cartridge side of the edge connector.
+///
+/// On the CPU side, mappers see every access to $4020-$FFFF.
+/// On the PPU side, they see accesses to the pattern tables ($0000-$1FFF) and decide how nametables are mirrored.
+pub trait Mapper {
+    /// The memory chips on the cartridge.
+    fn memory(&self) -> &CartridgeMemory;
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory;
+
+    /// Reads from the CPU address space without side effects. None means nothing drives the bus (open bus).
+    fn cpu_peek(&self, addr: u16) -> Option<u8>;
+
+    /// Reads from the CPU address space. Only needs to be overridden by mappers that react to reads.
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek(addr)
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8);
+
+    /// Reads from the pattern tables without side effects.
+    fn ppu_peek(&self, addr: u16) -> u8;
+
+    /// Reads from the pattern tables. Only needs to be overridden by mappers that watch the PPU address bus.
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.ppu_peek(addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8);
+
+    fn mirroring(&self) -> Mirroring;
+
+    /// Reads from the nametables ($2000-$2FFF) without side effects.
+    /// None means the mapper leaves it up to CIRAM, arranged according to `mirroring`.
+    fn nametable_peek(&self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    /// Reads from the nametables. Only needs to be overridden by mappers that watch nametable fetches.
+    fn nametable_read(&mut self, addr: u16) -> Option<u8> {
+        self.nametable_peek(addr)
+    }
+
+    /// Writes to the nametables. Returns whether the mapper took care of the write, instead of CIRAM.
+    fn nametable_write(&mut self, _addr: u16, _value: u8) -> bool {
+        false
+    }
+
+    /// Called for CPU writes outside of cartridge space, which the cartridge can see on the bus as well.
+    /// Some mappers listen in on the PPU registers this way.
+    fn cpu_snoop(&mut self, _addr: u16, _value: u8) {}
+
+    /// Called once per CPU cycle (M2), for mappers with cycle counters or timing-dependent behavior.
+    fn cpu_clock(&mut self) {}
+
+    /// Whether the mapper is currently holding the IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Contents of battery-backed memory, to be kept between sessions. None if the cartridge has no battery.
+    /// Only needs to be overridden by mappers with battery-backed memory of their own.
+    fn battery_ram(&self) -> Option<Vec<u8>> {
+        self.memory().battery_ram()
+    }
+
+    /// Restores battery-backed memory, in the same layout `battery_ram` returns it in.
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        self.memory_mut().load_battery_ram(data);
+    }
+
+    /// Output of the cartridge's expansion audio, if it has any. Scaled so that 1.0 is about as loud as the APU at full volume.
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+}
+
+/// Builds the right mapper for the ROM, based on the mapper number in its header.
+pub fn create(rom: ROM) -> Result<Box<dyn Mapper>, RomError> {
+    match rom.info.mapper {
+        0 => Ok(Box::new(nrom::NROM::new(rom))),
+        1 | 155 => Ok(Box::new(mmc1::MMC1::new(rom))),
+        2 | 94 | 180 => Ok(Box::new(uxrom::UxROM::new(rom))),
+        3 | 87 => Ok(Box::new(cnrom::CNROM::new(rom))),
+        4 => Ok(Box::new(mmc3::MMC3::new(rom))),
+        5 => Ok(Box::new(mmc5::MMC5::new(rom))),
+        7 => Ok(Box::new(axrom::AxROM::new(rom))),
+        11 => Ok(Box::new(color_dreams::ColorDreams::new(rom))),
+        19 => Ok(Box::new(namco163::Namco163::new(rom))),
+        21 | 22 | 23 | 25 => Ok(Box::new(vrc4::VRC4::new(rom))),
+        24 | 26 => Ok(Box::new(vrc6::VRC6::new(rom))),
+        66 | 140 => Ok(Box::new(gxrom::GxROM::new(rom))),
+        69 => Ok(Box::new(fme7::FME7::new(rom))),
+        85 => Ok(Box::new(vrc7::VRC7::new(rom))),
+        mapper => Err(RomError::UnsupportedMapper {
+            mapper,
+            submapper: rom.info.submapper,
+        }),
+    }
+}
+
+/// PRG-ROM, PRG-RAM and CHR memory, which pretty much every board has.
+/// Takes care of banking arithmetic, so mappers only have to deal with bank numbers.
+pub struct CartridgeMemory {
+    pub prg_rom: Vec<u8>,
+    pub prg_ram: Vec<u8>,
+    /// Either CHR-ROM or CHR-RAM, depending on `chr_is_ram`.
+    pub chr: Vec<u8>,
+    pub chr_is_ram: bool,
+    /// How much of the start of PRG-RAM is battery-backed.
+    pub prg_nvram_size: usize,
+    /// How much of the start of CHR-RAM is battery-backed.
+    pub chr_nvram_size: usize,
+    /// Set whenever battery-backed memory changes, and cleared once it's been saved.
+    pub battery_ram_dirty: bool,
+}
+
+/// Finds where `addr` lands in memory of `len` bytes, when `bank` of `bank_size` bytes is mapped in.
+/// Bank numbers wrap around the memory size, the same way unconnected address lines would.
+#[inline]
+fn banked_index(len: usize, bank: usize, bank_size: usize, addr: u16) -> usize {
+    (bank * bank_size + (addr as usize % bank_size)) % len
+}
+
+impl CartridgeMemory {
+    pub fn new(rom: ROM) -> CartridgeMemory {
+        let info = &rom.info;
+
+        let prg_nvram_size = info.prg_nvram_size.map_or(0, |size| size.get());
+        let prg_ram_size = info.prg_ram_size.map_or(0, |size| size.get()) + prg_nvram_size;
+
+        let mut prg_ram = vec![0u8; prg_ram_size as usize];
+
+        // The trainer is meant to be loaded at $7000.
+        if let Some(trainer) = &rom.trainer {
+            if prg_ram.len() >= 0x2000 {
+                prg_ram[0x1000..0x1000 + trainer.len()].copy_from_slice(trainer);
+            }
+        }
+
+        let chr_is_ram = rom.chr_rom.is_empty();
+
+        let chr_nvram_size = if chr_is_ram {
+            info.chr_nvram_size.map_or(0, |size| size.get())
+        } else {
+            0
+        };
+
+        let chr = if chr_is_ram {
+            let chr_ram_size = info.chr_ram_size.map_or(0, |size| size.get()) + chr_nvram_size;
+
+            // Some NES 2.0 headers don't bother with CHR-RAM sizes, assume the usual 8 KB.
+            vec![0u8; (chr_ram_size as usize).max(0x2000)]
+        } else {
+            rom.chr_rom
+        };
+
+        CartridgeMemory {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            chr,
+            chr_is_ram,
+            prg_nvram_size: prg_nvram_size as usize,
+            chr_nvram_size: chr_nvram_size as usize,
+            battery_ram_dirty: false,
+        }
+    }
+
+    /// Battery-backed PRG-RAM, followed by battery-backed CHR-RAM. None if neither is battery-backed.
+    pub fn battery_ram(&self) -> Option<Vec<u8>> {
+        if self.prg_nvram_size == 0 && self.chr_nvram_size == 0 {
+            return None;
+        }
+
+        Some(
+            [
+                &self.prg_ram[..self.prg_nvram_size],
+                &self.chr[..self.chr_nvram_size],
+            ]
+            .concat(),
+        )
+    }
+
+    /// Restores battery-backed memory, in the layout `battery_ram` returns it in.
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        let (prg_nvram, chr_nvram) = data.split_at(self.prg_nvram_size.min(data.len()));
+
+        self.prg_ram[..prg_nvram.len()].copy_from_slice(prg_nvram);
+
+        let chr_nvram = &chr_nvram[..self.chr_nvram_size.min(chr_nvram.len())];
+        self.chr[..chr_nvram.len()].copy_from_slice(chr_nvram);
+    }
+
+    pub fn prg_rom(&self, bank: usize, bank_size: usize, addr: u16) -> u8 {
+        self.prg_rom[banked_index(self.prg_rom.len(), bank, bank_size, addr)]
+    }
+
+    /// Amount of `bank_size` banks in PRG-ROM.
+    pub fn prg_rom_banks(&self, bank_size: usize) -> usize {
+        (self.prg_rom.len() / bank_size).max(1)
+    }
+
+    /// None if the cartridge has no PRG-RAM.
+    pub fn prg_ram(&self, bank: usize, bank_size: usize, addr: u16) -> Option<u8> {
+        if self.prg_ram.is_empty() {
+            return None;
+        }
+
+        Some(self.prg_ram[banked_index(self.prg_ram.len(), bank, bank_size, addr)])
+    }
+
+    pub fn write_prg_ram(&mut self, bank: usize, bank_size: usize, addr: u16, value: u8) {
+        if !self.prg_ram.is_empty() {
+            let index = banked_index(self.prg_ram.len(), bank, bank_size, addr);
+
+            if index < self.prg_nvram_size && self.prg_ram[index] != value {
+                self.battery_ram_dirty = true;
+            }
+
+            self.prg_ram[index] = value;
+        }
+    }
+
+    pub fn chr(&self, bank: usize, bank_size: usize, addr: u16) -> u8 {
+        self.chr[banked_index(self.chr.len(), bank, bank_size, addr)]
+    }
+
+    /// Writes are ignored when the board has CHR-ROM.
+    pub fn write_chr(&mut self, bank: usize, bank_size: usize, addr: u16, value: u8) {
+        if self.chr_is_ram {
+            let index = banked_index(self.chr.len(), bank, bank_size, addr);
+
+            if index < self.chr_nvram_size && self.chr[index] != value {
+                self.battery_ram_dirty = true;
+            }
+
+            self.chr[index] = value;
+        }
+    }
+}
+
+/// A NES 2.0 image for `mapper` and `submapper`, with 128 KB of PRG-ROM, 256 KB of CHR-ROM and 8 KB of PRG-RAM.
+/// Every 8 KB of PRG-ROM and 1 KB of CHR-ROM starts with its own bank number.
+#[cfg(test)]
+pub(crate) fn test_rom(mapper: u16, submapper: u8) -> ROM {
+    let mut data = vec![
+        b'N',
+        b'E',
+        b'S',
+        0x1A,
+        8,
+        32,
+        ((mapper & 0x0F) << 4) as u8,
+        (mapper & 0xF0) as u8 | 0x08,
+        (submapper << 4) | (mapper >> 8) as u8,
+        0,
+        0x07,
+    ];
+    data.resize(16, 0);
+
+    let mut prg_rom = vec![0; 0x20000];
+    for bank in 0..prg_rom.len() / 0x2000 {
+        prg_rom[bank * 0x2000] = bank as u8;
+    }
+
+    let mut chr_rom = vec![0; 0x40000];
+    for bank in 0..chr_rom.len() / 0x0400 {
+        chr_rom[bank * 0x0400] = bank as u8;
+    }
+
+    data.extend(prg_rom);
+    data.extend(chr_rom);
+
+    ROM::from_bytes(&data).expect("Test ROMs should always parse.")
+}
@@ -0,0 +1,103 @@
+use super::{BusConflicts, CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+/// Mapper 7, switching 32 KB of PRG-ROM along with single-screen mirroring.
+///
+/// 7654 3210
+///
+/// ...M PPPP
+///
+/// Mirroring (which CIRAM page is used), PRG bank
+///
+/// ANROM (submapper 1) avoids bus conflicts, AMROM and AOROM (submapper 2) have them.
+/// Refer to: https://www.nesdev.org/wiki/AxROM
+#[allow(clippy::upper_case_acronyms)]
+pub struct AxROM {
+    memory: CartridgeMemory,
+    bus_conflicts: BusConflicts,
+    register: u8,
+}
+
+impl AxROM {
+    pub fn new(rom: ROM) -> AxROM {
+        AxROM {
+            bus_conflicts: BusConflicts::from_header(&rom.info),
+            register: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    fn prg_bank(&self) -> usize {
+        (self.register & 0b1111) as usize
+    }
+}
+
+impl Mapper for AxROM {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(), 0x8000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        if let 0x8000..=0xFFFF = addr {
+            let rom_value = self.memory.prg_rom(self.prg_bank(), 0x8000, addr);
+
+            self.register = self.bus_conflicts.apply(rom_value, value);
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(0, 0x2000, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory.write_chr(0, 0x2000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if get_bit(self.register, 4) {
+            Mirroring::SingleScreenUpper
+        } else {
+            Mirroring::SingleScreenLower
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn bank_switching_and_mirroring() {
+        // Every 32 KB bank starts with 4 times its own number.
+        let mut axrom = AxROM::new(test_rom(7, 0));
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+
+        axrom.cpu_write(0x8000, 0x12);
+        assert_eq!(axrom.cpu_peek(0x8000), Some(8));
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenUpper);
+    }
+
+    #[test]
+    fn bus_conflicts_from_submapper() {
+        let mut rom = test_rom(7, 2);
+        rom.prg_rom[0x7FFF] = 0x01;
+
+        let mut axrom = AxROM::new(rom);
+        axrom.cpu_write(0xFFFF, 0x13);
+
+        assert_eq!(axrom.cpu_peek(0x8000), Some(4));
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+    }
+}
@@ -0,0 +1,109 @@
+use super::{BusConflicts, CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+/// Mappers 3 and 87, switching 8 KB of CHR-ROM with fixed PRG-ROM.
+///
+/// CNROM (mapper 3) selects the CHR bank by writing anywhere in $8000-$FFFF.
+/// Jaleco's J87 boards (mapper 87) put the register at $6000-$7FFF instead, with the two lowest bits swapped.
+/// Refer to: https://www.nesdev.org/wiki/CNROM
+#[allow(clippy::upper_case_acronyms)]
+pub struct CNROM {
+    memory: CartridgeMemory,
+    mirroring: Mirroring,
+    bus_conflicts: BusConflicts,
+    jaleco: bool,
+    chr_bank: u8,
+}
+
+impl CNROM {
+    pub fn new(rom: ROM) -> CNROM {
+        CNROM {
+            mirroring: Mirroring::from_header(&rom.info),
+            bus_conflicts: BusConflicts::from_header(&rom.info),
+            jaleco: rom.info.mapper == 87,
+            chr_bank: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+}
+
+impl Mapper for CNROM {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if !self.jaleco => self.memory.prg_ram(0, 0x2000, addr),
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(0, 0x8000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.jaleco => {
+                self.chr_bank = ((value & 1) << 1) | get_bit(value, 1) as u8;
+            }
+            0x6000..=0x7FFF => self.memory.write_prg_ram(0, 0x2000, addr, value),
+            0x8000..=0xFFFF if !self.jaleco => {
+                let rom_value = self.memory.prg_rom(0, 0x8000, addr);
+
+                self.chr_bank = self.bus_conflicts.apply(rom_value, value);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank as usize, 0x2000, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory
+            .write_chr(self.chr_bank as usize, 0x2000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn bank_switching() {
+        // Every 8 KB CHR bank starts with 8 times its own number.
+        let mut cnrom = CNROM::new(test_rom(3, 0));
+        cnrom.cpu_write(0x8000, 2);
+        assert_eq!(cnrom.ppu_peek(0x0000), 16);
+        assert_eq!(cnrom.ppu_peek(0x1C00), 23);
+
+        // J87 swaps the two bits, and has the register at $6000.
+        let mut j87 = CNROM::new(test_rom(87, 0));
+        j87.cpu_write(0x8000, 2);
+        assert_eq!(j87.ppu_peek(0x0000), 0);
+        j87.cpu_write(0x6000, 1);
+        assert_eq!(j87.ppu_peek(0x0000), 16);
+    }
+
+    #[test]
+    fn bus_conflicts_from_submapper() {
+        for (submapper, bank) in [(0, 3), (2, 1)] {
+            let mut rom = test_rom(3, submapper);
+            rom.prg_rom[0x7FFF] = 0x01;
+
+            let mut cnrom = CNROM::new(rom);
+            cnrom.cpu_write(0xFFFF, 0x03);
+
+            assert_eq!(cnrom.ppu_peek(0x0000), bank * 8, "{submapper}");
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use super::{BusConflicts, CartridgeMemory, Mapper, Mirroring};
+use crate::rom::ROM;
+
+/// Mapper 11, used by Color Dreams and Wisdom Tree's unlicensed games.
+/// It's GxROM with the register bits rearranged.
+///
+/// 7654 3210
+///
+/// CCCC ..PP
+///
+/// CHR bank, PRG bank
+///
+/// Refer to: https://www.nesdev.org/wiki/Color_Dreams
+pub struct ColorDreams {
+    memory: CartridgeMemory,
+    mirroring: Mirroring,
+    bus_conflicts: BusConflicts,
+    register: u8,
+}
+
+impl ColorDreams {
+    pub fn new(rom: ROM) -> ColorDreams {
+        ColorDreams {
+            mirroring: Mirroring::from_header(&rom.info),
+            bus_conflicts: BusConflicts::from_header(&rom.info),
+            register: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    fn prg_bank(&self) -> usize {
+        (self.register & 0b11) as usize
+    }
+
+    fn chr_bank(&self) -> usize {
+        (self.register >> 4) as usize
+    }
+}
+
+impl Mapper for ColorDreams {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(), 0x8000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        if let 0x8000..=0xFFFF = addr {
+            let rom_value = self.memory.prg_rom(self.prg_bank(), 0x8000, addr);
+
+            self.register = self.bus_conflicts.apply(rom_value, value);
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank(), 0x2000, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory.write_chr(self.chr_bank(), 0x2000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn bank_switching() {
+        // Every 32 KB PRG bank starts with 4 times its own number, every 8 KB CHR bank with 8 times.
+        let mut color_dreams = ColorDreams::new(test_rom(11, 0));
+        color_dreams.cpu_write(0x8000, 0x21);
+
+        assert_eq!(color_dreams.cpu_peek(0x8000), Some(4));
+        assert_eq!(color_dreams.ppu_peek(0x0000), 16);
+    }
+
+    #[test]
+    fn bus_conflicts_from_submapper() {
+        let mut rom = test_rom(11, 2);
+        rom.prg_rom[0x7FFF] = 0x12;
+
+        let mut color_dreams = ColorDreams::new(rom);
+        color_dreams.cpu_write(0xFFFF, 0x33);
+
+        assert_eq!(color_dreams.cpu_peek(0x8000), Some(8));
+        assert_eq!(color_dreams.ppu_peek(0x0000), 8);
+    }
+}
@@ -0,0 +1,266 @@
+use super::{CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+mod audio;
+
+/// Mapper 69: Sunsoft FME-7, and the 5B which adds audio to it.
+///
+/// Registers are written through a command port at $8000-$9FFF and a parameter port at $A000-$BFFF.
+/// PRG-ROM is switched in four 8 KB banks (including $6000-$7FFF, which can also hold PRG-RAM),
+/// with the last one fixed. CHR is switched in eight 1 KB banks.
+/// Refer to: https://www.nesdev.org/wiki/Sunsoft_FME-7
+#[allow(clippy::upper_case_acronyms)]
+pub struct FME7 {
+    memory: CartridgeMemory,
+    command: u8,
+    chr_banks: [u8; 8],
+    /// 7654 3210
+    ///
+    /// ERBB BBBB
+    ///
+    /// RAM Enable, RAM select (instead of ROM), ROM Bank
+    prg_bank_6000: u8,
+    prg_banks: [u8; 3],
+    mirroring: u8,
+    irq_enabled: bool,
+    irq_counter_enabled: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: audio::Audio,
+}
+
+impl FME7 {
+    pub fn new(rom: ROM) -> FME7 {
+        FME7 {
+            command: 0,
+            chr_banks: [0; 8],
+            prg_bank_6000: 0,
+            prg_banks: [0; 3],
+            mirroring: 0,
+            irq_enabled: false,
+            irq_counter_enabled: false,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: audio::Audio::default(),
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    fn ram_selected(&self) -> bool {
+        get_bit(self.prg_bank_6000, 6)
+    }
+
+    fn ram_enabled(&self) -> bool {
+        get_bit(self.prg_bank_6000, 7)
+    }
+
+    fn write_parameter(&mut self, value: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[self.command as usize] = value,
+            0x8 => self.prg_bank_6000 = value,
+            0x9..=0xB => self.prg_banks[(self.command - 0x9) as usize] = value & 0b0011_1111,
+            0xC => self.mirroring = value & 0b11,
+            // Writing the control register acknowledges the IRQ.
+            0xD => {
+                self.irq_enabled = get_bit(value, 0);
+                self.irq_counter_enabled = get_bit(value, 7);
+                self.irq_pending = false;
+            }
+            0xE => self.irq_counter = (self.irq_counter & 0xFF00) | value as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | ((value as u16) << 8),
+        }
+    }
+}
+
+impl Mapper for FME7 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.ram_selected() => {
+                if self.ram_enabled() {
+                    self.memory.prg_ram(0, 0x2000, addr)
+                } else {
+                    None
+                }
+            }
+            0x6000..=0x7FFF => {
+                let bank = (self.prg_bank_6000 & 0b0011_1111) as usize;
+
+                Some(self.memory.prg_rom(bank, 0x2000, addr))
+            }
+            0x8000..=0xDFFF => {
+                let bank = self.prg_banks[((addr - 0x8000) >> 13) as usize] as usize;
+
+                Some(self.memory.prg_rom(bank, 0x2000, addr))
+            }
+            0xE000..=0xFFFF => {
+                let last_bank = self.memory.prg_rom_banks(0x2000) - 1;
+
+                Some(self.memory.prg_rom(last_bank, 0x2000, addr))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.ram_selected() && self.ram_enabled() => {
+                self.memory.write_prg_ram(0, 0x2000, addr, value)
+            }
+            0x8000..=0x9FFF => self.command = value & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(value),
+            0xC000..=0xDFFF => self.audio.select(value),
+            0xE000..=0xFFFF => self.audio.write(value),
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        let bank = self.chr_banks[(addr >> 10) as usize & 0b111] as usize;
+
+        self.memory.chr(bank, 0x0400, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        let bank = self.chr_banks[(addr >> 10) as usize & 0b111] as usize;
+
+        self.memory.write_chr(bank, 0x0400, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    /// The IRQ counter counts down every CPU cycle, and raises an IRQ when it wraps around from 0.
+    fn cpu_clock(&mut self) {
+        if self.irq_counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+
+            if self.irq_counter == 0xFFFF && self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    fn write_register(fme7: &mut FME7, command: u8, value: u8) {
+        fme7.cpu_write(0x8000, command);
+        fme7.cpu_write(0xA000, value);
+    }
+
+    #[test]
+    fn command_and_parameter_registers() {
+        let mut fme7 = FME7::new(test_rom(69, 0));
+
+        for command in 0..8 {
+            write_register(&mut fme7, command, 20 + command);
+        }
+
+        let chr_banks = (0..8)
+            .map(|slot| fme7.ppu_peek(slot * 0x0400))
+            .collect::<Vec<_>>();
+        assert_eq!(chr_banks, [20, 21, 22, 23, 24, 25, 26, 27]);
+
+        // The command port only looks at the lower 4 bits, and stays selected between parameter writes.
+        fme7.cpu_write(0x9FFF, 0xF9);
+        fme7.cpu_write(0xA000, 3);
+        fme7.cpu_write(0xBFFF, 4);
+        write_register(&mut fme7, 0xA, 5);
+        write_register(&mut fme7, 0xB, 6);
+
+        let prg_banks = [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| fme7.cpu_peek(addr));
+        assert_eq!(prg_banks, [Some(4), Some(5), Some(6), Some(15)]);
+
+        for (value, mirroring) in [
+            (0, Mirroring::Vertical),
+            (1, Mirroring::Horizontal),
+            (2, Mirroring::SingleScreenLower),
+            (3, Mirroring::SingleScreenUpper),
+        ] {
+            write_register(&mut fme7, 0xC, value);
+            assert_eq!(fme7.mirroring(), mirroring);
+        }
+    }
+
+    #[test]
+    fn prg_rom_or_ram_at_6000() {
+        let mut fme7 = FME7::new(test_rom(69, 0));
+
+        write_register(&mut fme7, 0x8, 7);
+        assert_eq!(fme7.cpu_peek(0x6000), Some(7));
+
+        // RAM selected, but not enabled: open bus.
+        write_register(&mut fme7, 0x8, 0b0100_0000);
+        fme7.cpu_write(0x6000, 0xAA);
+        assert_eq!(fme7.cpu_peek(0x6000), None);
+
+        write_register(&mut fme7, 0x8, 0b1100_0000);
+        assert_eq!(fme7.cpu_peek(0x6000), Some(0));
+        fme7.cpu_write(0x6000, 0xAA);
+        assert_eq!(fme7.cpu_peek(0x6000), Some(0xAA));
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut fme7 = FME7::new(test_rom(69, 0));
+
+        write_register(&mut fme7, 0xE, 0x02);
+        write_register(&mut fme7, 0xF, 0x01);
+
+        // Doesn't count until enabled.
+        fme7.cpu_clock();
+        write_register(&mut fme7, 0xD, 0b1000_0001);
+
+        // Fires when it wraps around from 0, after counter + 1 cycles.
+        for _ in 0..0x0102 {
+            fme7.cpu_clock();
+        }
+        assert!(!fme7.irq());
+
+        fme7.cpu_clock();
+        assert!(fme7.irq());
+
+        // Writing the control register acknowledges it.
+        write_register(&mut fme7, 0xD, 0b1000_0000);
+        assert!(!fme7.irq());
+
+        // With IRQs disabled, the counter keeps going around without firing.
+        for _ in 0..0x10000 {
+            fme7.cpu_clock();
+        }
+        assert!(!fme7.irq());
+
+        write_register(&mut fme7, 0xD, 0b1000_0001);
+        for _ in 0..0x10000 {
+            fme7.cpu_clock();
+        }
+        assert!(fme7.irq());
+    }
+}
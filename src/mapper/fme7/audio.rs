@@ -0,0 +1,228 @@
+use crate::utils::bits::get_bit;
+
+/// Each channel at full volume is about as loud as an APU pulse channel at full volume.
+const LEVEL: f32 = 0.15;
+
+/// The tone, noise and envelope generators all run on the CPU clock divided by 16.
+const CLOCK_DIVIDER: u8 = 16;
+
+/// 5B's envelope has 32 steps, and channel volumes map onto every other one of them.
+/// Every step is 1.5 dB.
+fn amplitude(level: u8) -> f32 {
+    if level == 0 {
+        0.0
+    } else {
+        10f32.powf((level as f32 - 31.0) * 1.5 / 20.0)
+    }
+}
+
+#[derive(Default)]
+struct Tone {
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl Tone {
+    fn clock(&mut self) {
+        self.counter += 1;
+
+        if self.counter >= self.period {
+            self.counter = 0;
+            self.output = !self.output;
+        }
+    }
+}
+
+/// A 17-bit LFSR.
+struct Noise {
+    period: u8,
+    counter: u8,
+    shift_register: u32,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            period: 0,
+            counter: 0,
+            shift_register: 1,
+        }
+    }
+}
+
+impl Noise {
+    fn clock(&mut self) {
+        self.counter += 1;
+
+        if self.counter >= self.period {
+            self.counter = 0;
+
+            let feedback = (self.shift_register ^ (self.shift_register >> 3)) & 1;
+            self.shift_register = (self.shift_register >> 1) | (feedback << 16);
+        }
+    }
+
+    fn output(&self) -> bool {
+        self.shift_register & 1 == 1
+    }
+}
+
+#[derive(Default)]
+struct Envelope {
+    period: u16,
+    counter: u16,
+    /// 3210
+    ///
+    /// CAaH
+    ///
+    /// Continue, Attack, Alternate, Hold
+    shape: u8,
+    step: u8,
+    attack: bool,
+    holding: bool,
+}
+
+impl Envelope {
+    fn write_shape(&mut self, value: u8) {
+        self.shape = value & 0x0F;
+        self.attack = get_bit(self.shape, 2);
+        self.step = 0;
+        self.counter = 0;
+        self.holding = false;
+    }
+
+    fn clock(&mut self) {
+        if self.holding {
+            return;
+        }
+
+        self.counter += 1;
+
+        if self.counter < self.period {
+            return;
+        }
+
+        self.counter = 0;
+        self.step += 1;
+
+        if self.step < 32 {
+            return;
+        }
+
+        let continues = get_bit(self.shape, 3);
+        let alternate = get_bit(self.shape, 1);
+        let hold = get_bit(self.shape, 0);
+
+        if !continues {
+            // Shapes without continue end up silent.
+            self.holding = true;
+            self.attack = false;
+            self.step = 31;
+        } else if hold {
+            // Holds at the level the ramp ended on, or the opposite one when alternating.
+            self.holding = true;
+            self.attack ^= alternate;
+            self.step = 31;
+        } else {
+            self.step = 0;
+            self.attack ^= alternate;
+        }
+    }
+
+    /// 0-31
+    fn level(&self) -> u8 {
+        if self.attack {
+            self.step
+        } else {
+            31 - self.step
+        }
+    }
+}
+
+/// Sunsoft 5B's audio, a YM2149F (a licensed AY-3-8910) with three square channels, noise and an envelope.
+/// Refer to: https://www.nesdev.org/wiki/Sunsoft_5B_audio
+#[derive(Default)]
+pub struct Audio {
+    address: u8,
+    tones: [Tone; 3],
+    noise: Noise,
+    envelope: Envelope,
+    /// 7654 3210
+    ///
+    /// ..NN NTTT
+    ///
+    /// Noise disable, Tone disable, for channels C, B and A
+    mixer: u8,
+    /// Bit 4 selects the envelope instead of a fixed volume.
+    volumes: [u8; 3],
+    divider: u8,
+}
+
+impl Audio {
+    pub fn select(&mut self, address: u8) {
+        self.address = address;
+    }
+
+    pub fn write(&mut self, value: u8) {
+        match self.address {
+            0x00..=0x05 => {
+                let tone = &mut self.tones[(self.address / 2) as usize];
+
+                tone.period = if self.address.is_multiple_of(2) {
+                    (tone.period & 0x0F00) | value as u16
+                } else {
+                    (tone.period & 0x00FF) | (((value & 0x0F) as u16) << 8)
+                };
+            }
+            0x06 => self.noise.period = value & 0b1_1111,
+            0x07 => self.mixer = value,
+            0x08..=0x0A => self.volumes[(self.address - 0x08) as usize] = value & 0b1_1111,
+            0x0B => self.envelope.period = (self.envelope.period & 0xFF00) | value as u16,
+            0x0C => self.envelope.period = (self.envelope.period & 0x00FF) | ((value as u16) << 8),
+            0x0D => self.envelope.write_shape(value),
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.divider += 1;
+
+        if self.divider < CLOCK_DIVIDER {
+            return;
+        }
+
+        self.divider = 0;
+
+        self.tones.iter_mut().for_each(Tone::clock);
+        self.noise.clock();
+        self.envelope.clock();
+    }
+
+    pub fn output(&self) -> f32 {
+        let mut output = 0.0;
+
+        for (channel, tone) in self.tones.iter().enumerate() {
+            let tone_on = tone.output || get_bit(self.mixer, channel as u8);
+            let noise_on = self.noise.output() || get_bit(self.mixer, channel as u8 + 3);
+
+            if !(tone_on && noise_on) {
+                continue;
+            }
+
+            let volume = self.volumes[channel];
+
+            let level = if get_bit(volume, 4) {
+                self.envelope.level()
+            } else if volume == 0 {
+                0
+            } else {
+                (volume & 0x0F) * 2 + 1
+            };
+
+            output += amplitude(level);
+        }
+
+        output * LEVEL
+    }
+}
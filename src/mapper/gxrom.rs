@@ -0,0 +1,118 @@
+use super::{BusConflicts, CartridgeMemory, Mapper, Mirroring};
+use crate::rom::ROM;
+
+/// Mappers 66 and 140, switching 32 KB of PRG-ROM and 8 KB of CHR-ROM from a single register.
+///
+/// 7654 3210
+///
+/// ..PP ..CC
+///
+/// PRG bank, CHR bank
+///
+/// GNROM and MHROM (mapper 66) have the register at $8000-$FFFF.
+/// Jaleco's JF-11 and JF-14 (mapper 140) have it at $6000-$7FFF, which avoids bus conflicts entirely.
+/// Refer to: https://www.nesdev.org/wiki/GxROM
+#[allow(clippy::upper_case_acronyms)]
+pub struct GxROM {
+    memory: CartridgeMemory,
+    mirroring: Mirroring,
+    bus_conflicts: BusConflicts,
+    jaleco: bool,
+    register: u8,
+}
+
+impl GxROM {
+    pub fn new(rom: ROM) -> GxROM {
+        GxROM {
+            mirroring: Mirroring::from_header(&rom.info),
+            bus_conflicts: BusConflicts::from_header(&rom.info),
+            jaleco: rom.info.mapper == 140,
+            register: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    fn prg_bank(&self) -> usize {
+        ((self.register >> 4) & 0b11) as usize
+    }
+
+    fn chr_bank(&self) -> usize {
+        (self.register & 0b11) as usize
+    }
+}
+
+impl Mapper for GxROM {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(), 0x8000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.jaleco => self.register = value,
+            0x8000..=0xFFFF if !self.jaleco => {
+                let rom_value = self.memory.prg_rom(self.prg_bank(), 0x8000, addr);
+
+                self.register = self.bus_conflicts.apply(rom_value, value);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank(), 0x2000, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory.write_chr(self.chr_bank(), 0x2000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn bank_switching() {
+        // Every 32 KB PRG bank starts with 4 times its own number, every 8 KB CHR bank with 8 times.
+        let mut gxrom = GxROM::new(test_rom(66, 0));
+        gxrom.cpu_write(0x8000, 0x21);
+        assert_eq!(gxrom.cpu_peek(0x8000), Some(8));
+        assert_eq!(gxrom.ppu_peek(0x0000), 8);
+
+        // Jaleco's version has the register at $6000.
+        let mut jaleco = GxROM::new(test_rom(140, 0));
+        jaleco.cpu_write(0x8000, 0x21);
+        assert_eq!(jaleco.cpu_peek(0x8000), Some(0));
+        jaleco.cpu_write(0x6000, 0x21);
+        assert_eq!(jaleco.cpu_peek(0x8000), Some(8));
+        assert_eq!(jaleco.ppu_peek(0x0000), 8);
+    }
+
+    #[test]
+    fn bus_conflicts_from_submapper() {
+        let mut rom = test_rom(66, 2);
+        rom.prg_rom[0x7FFF] = 0x11;
+
+        let mut gxrom = GxROM::new(rom);
+        gxrom.cpu_write(0xFFFF, 0x33);
+
+        assert_eq!(gxrom.cpu_peek(0x8000), Some(4));
+        assert_eq!(gxrom.ppu_peek(0x0000), 8);
+    }
+}
@@ -0,0 +1,435 @@
+use super::{CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+/// SxROM boards wire the unused upper CHR bank bits to different things.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Board {
+    /// SAROM, SBROM, SKROM etc. Nothing special about them.
+    Generic,
+    /// CHR bit 4 disables PRG-RAM.
+    SNROM,
+    /// CHR bit 3 selects one of two 8 KB PRG-RAM banks.
+    SOROM,
+    /// CHR bit 4 selects the 256 KB PRG-ROM half.
+    SUROM,
+    /// CHR bit 4 selects the 256 KB PRG-ROM half, bits 2-3 select one of four 8 KB PRG-RAM banks.
+    SXROM,
+    /// SEROM, SHROM and SH1ROM have 32 KB of PRG-ROM with no PRG banking connected. (Submapper 5)
+    SEROM,
+}
+
+impl Board {
+    fn detect(submapper: u8, memory: &CartridgeMemory) -> Board {
+        if submapper == 5 {
+            return Board::SEROM;
+        }
+
+        let large_prg_rom = memory.prg_rom.len() > 0x40000;
+
+        match memory.prg_ram.len() {
+            0x8000 => Board::SXROM,
+            0x4000 => Board::SOROM,
+            _ if large_prg_rom => Board::SUROM,
+            0x2000 if memory.chr_is_ram => Board::SNROM,
+            _ => Board::Generic,
+        }
+    }
+}
+
+/// Mapper 1 (and 155, which is the same thing with the original MMC1A chip)
+///
+/// Registers are written serially through a 5-bit shift register, one bit per write to $8000-$FFFF.
+/// The fifth write copies the value into one of four internal registers, picked by address bits 13 and 14.
+/// Refer to: https://www.nesdev.org/wiki/MMC1
+#[allow(clippy::upper_case_acronyms)]
+pub struct MMC1 {
+    memory: CartridgeMemory,
+    board: Board,
+    /// MMC1A can't disable PRG-RAM through the PRG bank register.
+    mmc1a: bool,
+    shift_register: u8,
+    shift_count: u8,
+    /// 43210
+    ///
+    /// CPPMM
+    ///
+    /// CHR mode, PRG mode, Mirroring
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    /// In 4 KB CHR mode, the upper CHR bits come from whichever register the PPU last used.
+    ppu_a12: bool,
+    cycle: u64,
+    last_write_cycle: Option<u64>,
+}
+
+impl MMC1 {
+    pub fn new(rom: ROM) -> MMC1 {
+        let mmc1a = rom.info.mapper == 155;
+        let submapper = rom.info.submapper;
+        let memory = CartridgeMemory::new(rom);
+
+        MMC1 {
+            board: Board::detect(submapper, &memory),
+            memory,
+            mmc1a,
+            shift_register: 0,
+            shift_count: 0,
+            // Most games rely on the last bank being fixed at $C000 at power-on.
+            control: 0b0_1100,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            ppu_a12: false,
+            cycle: 0,
+            last_write_cycle: None,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        // Only bits 13 and 14 of the address matter.
+        match addr & 0xE000 {
+            0x8000 => self.control = value,
+            0xA000 => self.chr_bank_0 = value,
+            0xC000 => self.chr_bank_1 = value,
+            _ => self.prg_bank = value,
+        }
+    }
+
+    /// A write with bit 7 set resets the shift register, and sets PRG mode 3.
+    /// Otherwise, bit 0 gets shifted in, LSB first.
+    fn write_serial(&mut self, addr: u16, value: u8) {
+        // Writes on consecutive cycles (e.g. both writes of a read-modify-write instruction) are ignored.
+        if self.last_write_cycle == Some(self.cycle) {
+            return;
+        }
+        self.last_write_cycle = Some(self.cycle);
+
+        if get_bit(value, 7) {
+            self.shift_register = 0;
+            self.shift_count = 0;
+            self.control |= 0b0_1100;
+
+            return;
+        }
+
+        self.shift_register |= (value & 1) << self.shift_count;
+        self.shift_count += 1;
+
+        if self.shift_count == 5 {
+            self.write_register(addr, self.shift_register);
+
+            self.shift_register = 0;
+            self.shift_count = 0;
+        }
+    }
+
+    fn chr_mode_4k(&self) -> bool {
+        get_bit(self.control, 4)
+    }
+
+    /// The CHR bank register currently driving the upper CHR lines, which some boards use for other things.
+    fn chr_high_bits(&self) -> u8 {
+        if self.chr_mode_4k() && self.ppu_a12 {
+            self.chr_bank_1
+        } else {
+            self.chr_bank_0
+        }
+    }
+
+    /// Picks the 16 KB PRG-ROM bank for the given address.
+    fn prg_rom_bank(&self, addr: u16) -> usize {
+        if self.board == Board::SEROM {
+            return (addr >= 0xC000) as usize;
+        }
+
+        let outer_bank = match self.board {
+            Board::SUROM | Board::SXROM => (self.chr_high_bits() & 0b1_0000) as usize,
+            _ => 0,
+        };
+
+        let bank = (self.prg_bank & 0b1111) as usize;
+        let upper_half = addr >= 0xC000;
+
+        let inner_bank = match (self.control >> 2) & 0b11 {
+            // 32 KB mode ignores the lowest bit of the bank number.
+            0 | 1 => (bank & !1) | upper_half as usize,
+            // First bank fixed at $8000, $C000 switchable.
+            2 => {
+                if upper_half {
+                    bank
+                } else {
+                    0
+                }
+            }
+            // Last bank fixed at $C000, $8000 switchable.
+            _ => {
+                if upper_half {
+                    0b1111
+                } else {
+                    bank
+                }
+            }
+        };
+
+        outer_bank | inner_bank
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        let disabled_by_prg_bank = !self.mmc1a && get_bit(self.prg_bank, 4);
+        let disabled_by_chr_bank = self.board == Board::SNROM && get_bit(self.chr_high_bits(), 4);
+
+        !disabled_by_prg_bank && !disabled_by_chr_bank
+    }
+
+    fn prg_ram_bank(&self) -> usize {
+        match self.board {
+            Board::SOROM => ((self.chr_high_bits() >> 3) & 1) as usize,
+            Board::SXROM => ((self.chr_high_bits() >> 2) & 0b11) as usize,
+            _ => 0,
+        }
+    }
+
+    /// Picks the 4 KB CHR bank for the given address.
+    fn chr_bank(&self, addr: u16) -> usize {
+        let upper_half = addr >= 0x1000;
+
+        if self.chr_mode_4k() {
+            if upper_half {
+                self.chr_bank_1 as usize
+            } else {
+                self.chr_bank_0 as usize
+            }
+        } else {
+            // 8 KB mode ignores the lowest bit of the bank number.
+            (self.chr_bank_0 & !1) as usize | upper_half as usize
+        }
+    }
+}
+
+impl Mapper for MMC1 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.memory.prg_ram(self.prg_ram_bank(), 0x2000, addr)
+            }
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_rom_bank(addr), 0x4000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.memory
+                    .write_prg_ram(self.prg_ram_bank(), 0x2000, addr, value);
+            }
+            0x8000..=0xFFFF => self.write_serial(addr, value),
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank(addr), 0x1000, addr)
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.ppu_a12 = get_bit((addr >> 8) as u8, 4);
+
+        self.ppu_peek(addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.ppu_a12 = get_bit((addr >> 8) as u8, 4);
+
+        self.memory
+            .write_chr(self.chr_bank(addr), 0x1000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.cycle += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::mapper::test_rom;
+
+    /// `prg_rom_banks` of 16 KB PRG-ROM, with every bank starting with its own number.
+    fn rom(prg_rom_banks: usize, prg_ram_size: u32) -> ROM {
+        let mut rom = test_rom(1, 0);
+        rom.prg_rom = vec![0; prg_rom_banks * 0x4000];
+        rom.info.prg_ram_size = NonZeroU32::new(prg_ram_size);
+
+        for bank in 0..prg_rom_banks {
+            rom.prg_rom[bank * 0x4000] = bank as u8;
+        }
+
+        rom
+    }
+
+    /// Writes a register the way games do: five writes, LSB first, a few cycles apart.
+    fn write(mmc1: &mut MMC1, addr: u16, value: u8) {
+        for bit in 0..5 {
+            mmc1.cpu_write(addr, value >> bit);
+            mmc1.cpu_clock();
+            mmc1.cpu_clock();
+        }
+    }
+
+    fn prg_banks(mmc1: &MMC1) -> [Option<u8>; 2] {
+        [0x8000, 0xC000].map(|addr| mmc1.cpu_peek(addr))
+    }
+
+    #[test]
+    fn shift_register_takes_five_writes() {
+        let mut mmc1 = MMC1::new(rom(8, 0x2000));
+
+        for bit in [1, 1, 0, 0] {
+            mmc1.cpu_write(0xE000, bit);
+            mmc1.cpu_clock();
+        }
+
+        assert_eq!(prg_banks(&mmc1), [Some(0), Some(7)]);
+
+        // Only the fifth write's address picks the register.
+        mmc1.cpu_write(0xFFFF, 0);
+        assert_eq!(prg_banks(&mmc1), [Some(3), Some(7)]);
+    }
+
+    #[test]
+    fn bit_7_resets_the_shift_register_and_prg_mode() {
+        let mut mmc1 = MMC1::new(rom(8, 0x2000));
+        write(&mut mmc1, 0x8000, 0b0_1000);
+        write(&mut mmc1, 0xE000, 2);
+        assert_eq!(prg_banks(&mmc1), [Some(0), Some(2)]);
+
+        mmc1.cpu_write(0xE000, 1);
+        mmc1.cpu_clock();
+        mmc1.cpu_write(0xE000, 1);
+        mmc1.cpu_clock();
+        mmc1.cpu_write(0x8000, 0x80);
+        mmc1.cpu_clock();
+
+        // Back to the last bank fixed at $C000, and the bits written before the reset are gone.
+        assert_eq!(prg_banks(&mmc1), [Some(2), Some(7)]);
+
+        write(&mut mmc1, 0xE000, 5);
+        assert_eq!(prg_banks(&mmc1), [Some(5), Some(7)]);
+    }
+
+    #[test]
+    fn ignores_writes_on_consecutive_cycles() {
+        let mut mmc1 = MMC1::new(rom(8, 0x2000));
+
+        // Like INC $FFFF: the second write of the pair gets ignored.
+        for _ in 0..5 {
+            mmc1.cpu_write(0xFFFF, 1);
+            mmc1.cpu_write(0xFFFF, 0);
+            mmc1.cpu_clock();
+        }
+
+        // %11111, and bank 15 wraps around to 7.
+        assert_eq!(prg_banks(&mmc1), [Some(7), Some(7)]);
+        assert_eq!(mmc1.prg_bank, 0b1_1111);
+    }
+
+    #[test]
+    fn prg_modes() {
+        let mut mmc1 = MMC1::new(rom(8, 0x2000));
+        write(&mut mmc1, 0xE000, 5);
+
+        // 32 KB, ignoring the lowest bit.
+        write(&mut mmc1, 0x8000, 0b0_0000);
+        assert_eq!(prg_banks(&mmc1), [Some(4), Some(5)]);
+        write(&mut mmc1, 0x8000, 0b0_0100);
+        assert_eq!(prg_banks(&mmc1), [Some(4), Some(5)]);
+
+        // First bank fixed at $8000.
+        write(&mut mmc1, 0x8000, 0b0_1000);
+        assert_eq!(prg_banks(&mmc1), [Some(0), Some(5)]);
+
+        // Last bank fixed at $C000.
+        write(&mut mmc1, 0x8000, 0b0_1100);
+        assert_eq!(prg_banks(&mmc1), [Some(5), Some(7)]);
+    }
+
+    #[test]
+    fn prg_ram_disable() {
+        let mut mmc1 = MMC1::new(rom(8, 0x2000));
+        mmc1.cpu_write(0x6000, 0x42);
+        assert_eq!(mmc1.cpu_peek(0x6000), Some(0x42));
+
+        write(&mut mmc1, 0xE000, 0b1_0000);
+        assert_eq!(mmc1.cpu_peek(0x6000), None);
+    }
+
+    #[test]
+    fn surom_outer_prg_bank() {
+        let mut mmc1 = MMC1::new(rom(32, 0x2000));
+        assert_eq!(mmc1.board, Board::SUROM);
+
+        write(&mut mmc1, 0xE000, 3);
+        assert_eq!(prg_banks(&mmc1), [Some(3), Some(15)]);
+
+        // CHR bit 4 picks the second 256 KB, including the fixed bank.
+        write(&mut mmc1, 0xA000, 0b1_0000);
+        assert_eq!(prg_banks(&mmc1), [Some(19), Some(31)]);
+    }
+
+    #[test]
+    fn sorom_prg_ram_banks() {
+        let mut mmc1 = MMC1::new(rom(8, 0x4000));
+        assert_eq!(mmc1.board, Board::SOROM);
+
+        mmc1.cpu_write(0x6000, 0x11);
+
+        // CHR bit 3 picks the 8 KB bank.
+        write(&mut mmc1, 0xA000, 0b0_1000);
+        assert_eq!(mmc1.cpu_peek(0x6000), Some(0));
+        mmc1.cpu_write(0x6000, 0x22);
+
+        write(&mut mmc1, 0xA000, 0);
+        assert_eq!(mmc1.cpu_peek(0x6000), Some(0x11));
+    }
+
+    #[test]
+    fn sxrom_prg_ram_banks() {
+        let mut mmc1 = MMC1::new(rom(32, 0x8000));
+        assert_eq!(mmc1.board, Board::SXROM);
+
+        // CHR bits 2-3 pick the 8 KB bank, bit 4 still picks the PRG-ROM half.
+        for bank in 0..4 {
+            write(&mut mmc1, 0xA000, bank << 2);
+            mmc1.cpu_write(0x6000, bank);
+        }
+
+        for bank in 0..4 {
+            write(&mut mmc1, 0xA000, 0b1_0000 | bank << 2);
+            assert_eq!(mmc1.cpu_peek(0x6000), Some(bank));
+            assert_eq!(mmc1.cpu_peek(0xC000), Some(31));
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::num::NonZeroU32;
+
+use super::{CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+/// The two MMC3 revisions disagree on when the scanline counter raises an IRQ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrqRevision {
+    /// MMC3A (and some MMC3B): the IRQ is only raised when the counter goes from non-zero to zero,
+    /// or when it was reloaded through $C001. A latch of 0 raises a single IRQ.
+    RevA,
+    /// MMC3B and MMC3C: the IRQ is raised every time the counter is clocked and ends up zero.
+    /// A latch of 0 raises an IRQ on every scanline.
+    RevB,
+}
+
+/// The counter only gets clocked by A12 rises after A12 has stayed low for a bit.
+/// Without this, the rapid A12 toggling while fetching 8x16 sprites would clock it many times per scanline.
+/// The real filter is analog, but a few M2 cycles is what it ends up as.
+const A12_FILTER_CYCLES: u64 = 3;
+
+/// Mapper 4: MMC3, and the MMC6 it got cut down into.
+///
+/// PRG-ROM is switched in 8 KB banks, and CHR in two 2 KB banks and four 1 KB banks.
+/// The IRQ counter is clocked by rising edges on PPU A12, which normally happens once per scanline,
+/// when the PPU moves from background to sprite pattern fetches (or vice versa).
+/// Refer to: https://www.nesdev.org/wiki/MMC3
+#[allow(clippy::upper_case_acronyms)]
+pub struct MMC3 {
+    memory: CartridgeMemory,
+    /// MMC6 has 1 KB of its own PRG-RAM, with different protection bits.
+    mmc6: bool,
+    /// Plenty of MMC3 boards don't wire up the PRG-RAM protection bits,
+    /// and MMC6 games dumped without a submapper write MMC6 values there.
+    /// So without a submapper, PRG-RAM is always enabled.
+    ram_protect_wired: bool,
+    fourscreen: bool,
+    irq_revision: IrqRevision,
+    /// 7654 3210
+    ///
+    /// CPM. .RRR
+    ///
+    /// CHR A12 inversion, PRG-ROM bank mode, MMC6 PRG-RAM enable, bank register to update
+    bank_select: u8,
+    /// R0 to R7.
+    bank_registers: [u8; 8],
+    horizontal_mirroring: bool,
+    /// 7654 3210
+    ///
+    /// EW.. ....
+    ///
+    /// PRG-RAM chip enable, write protection (MMC3)
+    ///
+    /// 7654 3210
+    ///
+    /// HhLl ....
+    ///
+    /// Read and write enable for the upper and lower 512 bytes of PRG-RAM (MMC6)
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_line: bool,
+    ppu_a12: bool,
+    /// The CPU cycle A12 last went low on.
+    a12_fall_cycle: u64,
+    cycle: u64,
+}
+
+impl MMC3 {
+    pub fn new(rom: ROM) -> MMC3 {
+        // NES 2.0 submapper 1 is MMC6, submapper 4 is MMC3A.
+        let irq_revision = match rom.info.submapper {
+            4 => IrqRevision::RevA,
+            _ => IrqRevision::RevB,
+        };
+
+        MMC3::with_irq_revision(rom, irq_revision)
+    }
+
+    pub fn with_irq_revision(rom: ROM, irq_revision: IrqRevision) -> MMC3 {
+        // Only MMC6 has 1 KB of PRG-NVRAM, which gives it away in NES 2.0 headers without a submapper.
+        let mmc6 = rom.info.submapper == 1 || rom.info.prg_nvram_size == NonZeroU32::new(0x0400);
+        let ram_protect_wired = rom.info.submapper != 0;
+        let fourscreen = rom.info.hardwired_fourscreen_mode;
+        // The mirroring register isn't reset at power-on, go with what the header says until it gets written.
+        let horizontal_mirroring = Mirroring::from_header(&rom.info) == Mirroring::Horizontal;
+
+        let mut memory = CartridgeMemory::new(rom);
+
+        if mmc6 {
+            memory.prg_ram = vec![0u8; 0x0400];
+            memory.prg_nvram_size = memory.prg_nvram_size.min(0x0400);
+        }
+
+        MMC3 {
+            memory,
+            mmc6,
+            ram_protect_wired,
+            fourscreen,
+            irq_revision,
+            bank_select: 0,
+            bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            horizontal_mirroring,
+            prg_ram_protect: 0,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_line: false,
+            ppu_a12: false,
+            a12_fall_cycle: 0,
+            cycle: 0,
+        }
+    }
+
+    /// Registers are picked by the address range and whether the address is even or odd.
+    fn write_register(&mut self, addr: u16, value: u8) {
+        let odd = addr & 1 == 1;
+
+        match (addr, odd) {
+            (0x8000..=0x9FFF, false) => self.bank_select = value,
+            (0x8000..=0x9FFF, true) => {
+                self.bank_registers[(self.bank_select & 0b111) as usize] = value
+            }
+            (0xA000..=0xBFFF, false) => self.horizontal_mirroring = get_bit(value, 0),
+            (0xA000..=0xBFFF, true) => self.prg_ram_protect = value,
+            (0xC000..=0xDFFF, false) => self.irq_latch = value,
+            // The counter gets reloaded on the next clock.
+            (0xC000..=0xDFFF, true) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            // Disabling also acknowledges any pending IRQ.
+            (0xE000..=0xFFFF, false) => {
+                self.irq_enabled = false;
+                self.irq_line = false;
+            }
+            (0xE000..=0xFFFF, true) => self.irq_enabled = true,
+            _ => {}
+        }
+    }
+
+    /// Picks the 8 KB PRG-ROM bank for the given address.
+    fn prg_bank(&self, addr: u16) -> usize {
+        let second_last = self.memory.prg_rom_banks(0x2000).saturating_sub(2);
+        let swapped = get_bit(self.bank_select, 6);
+
+        // R6 and the second-to-last bank trade places in mode 1.
+        match (addr, swapped) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => {
+                (self.bank_registers[6] & 0b0011_1111) as usize
+            }
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => (self.bank_registers[7] & 0b0011_1111) as usize,
+            _ => second_last + 1,
+        }
+    }
+
+    /// Picks the 1 KB CHR bank for the given address.
+    fn chr_bank(&self, addr: u16) -> usize {
+        // With A12 inversion, the 2 KB banks move to $1000-$1FFF.
+        let addr = if get_bit(self.bank_select, 7) {
+            addr ^ 0x1000
+        } else {
+            addr
+        };
+
+        let slot = ((addr >> 10) & 0b111) as usize;
+
+        match slot {
+            // 2 KB banks ignore the lowest bit.
+            0 | 1 => (self.bank_registers[0] & !1) as usize | slot,
+            2 | 3 => (self.bank_registers[1] & !1) as usize | (slot & 1),
+            _ => self.bank_registers[slot - 2] as usize,
+        }
+    }
+
+    fn prg_ram_readable(&self, addr: u16) -> bool {
+        if self.mmc6 {
+            get_bit(self.bank_select, 5)
+                && get_bit(self.prg_ram_protect, self.mmc6_ram_half(addr) + 1)
+        } else {
+            !self.ram_protect_wired || get_bit(self.prg_ram_protect, 7)
+        }
+    }
+
+    fn prg_ram_writable(&self, addr: u16) -> bool {
+        if self.mmc6 {
+            self.prg_ram_readable(addr) && get_bit(self.prg_ram_protect, self.mmc6_ram_half(addr))
+        } else {
+            !self.ram_protect_wired
+                || (get_bit(self.prg_ram_protect, 7) && !get_bit(self.prg_ram_protect, 6))
+        }
+    }
+
+    /// The bit offset of the protection bits for the half of MMC6 PRG-RAM the address falls in.
+    fn mmc6_ram_half(&self, addr: u16) -> u8 {
+        if get_bit((addr >> 8) as u8, 1) {
+            6
+        } else {
+            4
+        }
+    }
+
+    fn observe_ppu_address(&mut self, addr: u16) {
+        let a12 = get_bit((addr >> 8) as u8, 4);
+
+        if a12 && !self.ppu_a12 && self.cycle - self.a12_fall_cycle >= A12_FILTER_CYCLES {
+            self.clock_irq_counter();
+        } else if !a12 && self.ppu_a12 {
+            self.a12_fall_cycle = self.cycle;
+        }
+
+        self.ppu_a12 = a12;
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let previous = self.irq_counter;
+        let reloading = self.irq_counter == 0 || self.irq_reload;
+
+        if reloading {
+            self.irq_counter = self.irq_latch;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        let raise = match self.irq_revision {
+            IrqRevision::RevA => self.irq_counter == 0 && (previous != 0 || self.irq_reload),
+            IrqRevision::RevB => self.irq_counter == 0,
+        };
+
+        if raise && self.irq_enabled {
+            self.irq_line = true;
+        }
+
+        self.irq_reload = false;
+    }
+}
+
+impl Mapper for MMC3 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            // MMC6 PRG-RAM is mirrored across $7000-$7FFF. If only one half is readable, the other one reads as 0.
+            // With both halves unreadable, nothing drives the bus.
+            0x7000..=0x7FFF if self.mmc6 => {
+                let disabled = !get_bit(self.bank_select, 5)
+                    || (!get_bit(self.prg_ram_protect, 5) && !get_bit(self.prg_ram_protect, 7));
+
+                if disabled {
+                    None
+                } else if self.prg_ram_readable(addr) {
+                    self.memory.prg_ram(0, 0x0400, addr)
+                } else {
+                    Some(0)
+                }
+            }
+            0x6000..=0x7FFF if !self.mmc6 && self.prg_ram_readable(addr) => {
+                self.memory.prg_ram(0, 0x2000, addr)
+            }
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(addr), 0x2000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x7000..=0x7FFF if self.mmc6 && self.prg_ram_writable(addr) => {
+                self.memory.write_prg_ram(0, 0x0400, addr, value)
+            }
+            0x6000..=0x7FFF if !self.mmc6 && self.prg_ram_writable(addr) => {
+                self.memory.write_prg_ram(0, 0x2000, addr, value)
+            }
+            // MMC6 can only write the protection register while PRG-RAM is enabled.
+            0xA001..=0xBFFF if self.mmc6 && addr & 1 == 1 && !get_bit(self.bank_select, 5) => {}
+            0x8000..=0xFFFF => self.write_register(addr, value),
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank(addr), 0x0400, addr)
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.observe_ppu_address(addr);
+
+        self.ppu_peek(addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.observe_ppu_address(addr);
+
+        self.memory
+            .write_chr(self.chr_bank(addr), 0x0400, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.fourscreen {
+            Mirroring::FourScreen
+        } else if self.horizontal_mirroring {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.cycle += 1;
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    /// A12 going low, staying there long enough to get past the filter, then rising.
+    fn scanline(mmc3: &mut MMC3) {
+        mmc3.ppu_read(0x0000);
+
+        for _ in 0..A12_FILTER_CYCLES {
+            mmc3.cpu_clock();
+        }
+
+        mmc3.ppu_read(0x1000);
+    }
+
+    /// Sets the latch, reloads the counter and enables IRQs.
+    fn set_up_irq(mmc3: &mut MMC3, latch: u8) {
+        mmc3.cpu_write(0xC000, latch);
+        mmc3.cpu_write(0xC001, 0);
+        mmc3.cpu_write(0xE001, 0);
+    }
+
+    #[test]
+    fn prg_banking_modes() {
+        let mut mmc3 = MMC3::new(test_rom(4, 0));
+
+        mmc3.cpu_write(0x8000, 6);
+        mmc3.cpu_write(0x8001, 2);
+        mmc3.cpu_write(0x8000, 7);
+        mmc3.cpu_write(0x8001, 1);
+
+        let banks = |mmc3: &MMC3| [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| mmc3.cpu_peek(addr));
+
+        assert_eq!(banks(&mmc3), [Some(2), Some(1), Some(14), Some(15)]);
+
+        // R6 and the second-to-last bank swap places.
+        mmc3.cpu_write(0x8000, 0b0100_0111);
+        assert_eq!(banks(&mmc3), [Some(14), Some(1), Some(2), Some(15)]);
+    }
+
+    #[test]
+    fn chr_banking_and_a12_inversion() {
+        let mut mmc3 = MMC3::new(test_rom(4, 0));
+
+        for (register, bank) in [3, 5, 1, 2, 3, 4].into_iter().enumerate() {
+            mmc3.cpu_write(0x8000, register as u8);
+            mmc3.cpu_write(0x8001, bank);
+        }
+
+        let banks = |mmc3: &MMC3| {
+            (0..8)
+                .map(|slot| mmc3.ppu_peek(slot * 0x0400))
+                .collect::<Vec<_>>()
+        };
+
+        // The 2 KB banks ignore the lowest bit of their register.
+        assert_eq!(banks(&mmc3), [2, 3, 4, 5, 1, 2, 3, 4]);
+
+        mmc3.cpu_write(0x8000, 0x80);
+        assert_eq!(banks(&mmc3), [1, 2, 3, 4, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn irq_after_latch_plus_one_scanlines() {
+        for revision in [IrqRevision::RevA, IrqRevision::RevB] {
+            let mut mmc3 = MMC3::with_irq_revision(test_rom(4, 0), revision);
+            set_up_irq(&mut mmc3, 2);
+
+            // The first clock reloads the counter, then it counts down to 0.
+            scanline(&mut mmc3);
+            scanline(&mut mmc3);
+            assert!(!mmc3.irq(), "{revision:?}");
+
+            scanline(&mut mmc3);
+            assert!(mmc3.irq(), "{revision:?}");
+
+            // Writing $E000 acknowledges it.
+            mmc3.cpu_write(0xE000, 0);
+            assert!(!mmc3.irq(), "{revision:?}");
+        }
+    }
+
+    #[test]
+    fn rev_a_raises_a_latch_of_0_once() {
+        let mut mmc3 = MMC3::with_irq_revision(test_rom(4, 0), IrqRevision::RevA);
+        set_up_irq(&mut mmc3, 0);
+
+        scanline(&mut mmc3);
+        assert!(mmc3.irq());
+
+        mmc3.cpu_write(0xE000, 0);
+        mmc3.cpu_write(0xE001, 0);
+
+        scanline(&mut mmc3);
+        assert!(!mmc3.irq());
+    }
+
+    #[test]
+    fn rev_b_raises_a_latch_of_0_every_scanline() {
+        let mut mmc3 = MMC3::with_irq_revision(test_rom(4, 0), IrqRevision::RevB);
+        set_up_irq(&mut mmc3, 0);
+
+        scanline(&mut mmc3);
+        assert!(mmc3.irq());
+
+        mmc3.cpu_write(0xE000, 0);
+        mmc3.cpu_write(0xE001, 0);
+
+        scanline(&mut mmc3);
+        assert!(mmc3.irq());
+    }
+
+    #[test]
+    fn a12_filter_ignores_quick_toggles() {
+        let mut mmc3 = MMC3::with_irq_revision(test_rom(4, 0), IrqRevision::RevB);
+        set_up_irq(&mut mmc3, 0);
+
+        scanline(&mut mmc3);
+        mmc3.cpu_write(0xE000, 0);
+        mmc3.cpu_write(0xE001, 0);
+
+        // Like 8x16 sprites fetching from both pattern tables.
+        mmc3.ppu_read(0x0000);
+        mmc3.ppu_read(0x1000);
+        assert!(!mmc3.irq());
+    }
+
+    #[test]
+    fn submapper_4_is_rev_a() {
+        let mmc3 = MMC3::new(test_rom(4, 4));
+
+        assert_eq!(mmc3.irq_revision, IrqRevision::RevA);
+    }
+
+    #[test]
+    fn prg_ram_protection_needs_a_submapper() {
+        // Without one, PRG-RAM works no matter what gets written to $A001.
+        let mut mmc3 = MMC3::new(test_rom(4, 0));
+        mmc3.cpu_write(0xA001, 0x30);
+        mmc3.cpu_write(0x6000, 0x42);
+        assert_eq!(mmc3.cpu_peek(0x6000), Some(0x42));
+
+        let mut mmc3 = MMC3::new(test_rom(4, 4));
+        mmc3.cpu_write(0x6000, 0x42);
+        assert_eq!(mmc3.cpu_peek(0x6000), None);
+
+        mmc3.cpu_write(0xA001, 0x80);
+        mmc3.cpu_write(0x6000, 0x42);
+        assert_eq!(mmc3.cpu_peek(0x6000), Some(0x42));
+
+        // Write protected
+        mmc3.cpu_write(0xA001, 0xC0);
+        mmc3.cpu_write(0x6000, 0x24);
+        assert_eq!(mmc3.cpu_peek(0x6000), Some(0x42));
+    }
+
+    #[test]
+    fn mmc6_from_prg_nvram_size() {
+        let mut rom = test_rom(4, 0);
+        rom.info.prg_ram_size = None;
+        rom.info.prg_nvram_size = NonZeroU32::new(0x0400);
+
+        let mut mmc6 = MMC3::new(rom);
+        assert!(mmc6.mmc6);
+        assert_eq!(mmc6.cpu_peek(0x7000), None);
+
+        // Enable PRG-RAM, then both halves for reading and writing.
+        mmc6.cpu_write(0x8000, 0x20);
+        mmc6.cpu_write(0xA001, 0xF0);
+        mmc6.cpu_write(0x7000, 0x42);
+        mmc6.cpu_write(0x7200, 0x24);
+
+        // 1 KB, mirrored across $7000-$7FFF.
+        assert_eq!(mmc6.cpu_peek(0x7C00), Some(0x42));
+        assert_eq!(mmc6.cpu_peek(0x7E00), Some(0x24));
+        assert_eq!(mmc6.cpu_peek(0x6000), None);
+
+        // Only the lower half readable, the upper one reads as 0.
+        mmc6.cpu_write(0xA001, 0x30);
+        assert_eq!(mmc6.cpu_peek(0x7000), Some(0x42));
+        assert_eq!(mmc6.cpu_peek(0x7200), Some(0));
+    }
+}
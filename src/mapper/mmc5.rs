@@ -0,0 +1,892 @@
+use super::{CartridgeMemory, Mapper, Mirroring};
+use crate::{
+    rom::{ines::iNESVersion, ROM},
+    utils::bits::get_bit,
+};
+
+mod audio;
+
+/// MMC5 has two sets of CHR bank registers, for 8x16 sprites to have their own patterns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChrSet {
+    /// $5120-$5127
+    Sprites,
+    /// $5128-$512B
+    Background,
+}
+
+/// PPU reads on a rendered scanline, counted from the first background nametable fetch:
+/// 32 background tiles, 8 sprites and 2 tiles for the next scanline, 4 reads each.
+const BACKGROUND_FETCHES_END: u16 = 32 * 4;
+const SPRITE_FETCHES_END: u16 = BACKGROUND_FETCHES_END + 8 * 4;
+const PREFETCH_END: u16 = SPRITE_FETCHES_END + 2 * 4;
+
+/// Mapper 5: MMC5, Nintendo's biggest mapper.
+///
+/// On top of flexible PRG and CHR banking, it has 1 KB of ExRAM that can act as an extra nametable,
+/// or hold per-tile attributes and CHR banks. It can also fill a nametable with a single tile,
+/// render a vertical split screen, multiply numbers and play two extra pulse channels and PCM.
+///
+/// MMC5 can't see the PPU's internal state, so it follows along by watching PPU reads.
+/// Three reads in a row from the same nametable address mark the start of a scanline,
+/// after which the fetch pattern is predictable enough to tell background from sprite fetches.
+/// Refer to: https://www.nesdev.org/wiki/MMC5
+#[allow(clippy::upper_case_acronyms)]
+pub struct MMC5 {
+    memory: CartridgeMemory,
+    audio: audio::Audio,
+    exram: Box<[u8; 0x0400]>,
+    prg_mode: u8,
+    chr_mode: u8,
+    /// Both have to be set to the magic values (0b10 and 0b01) for PRG-RAM to be writable.
+    prg_ram_protect: [u8; 2],
+    /// 0: extra nametable, 1: extended attributes, 2: CPU RAM, 3: CPU ROM
+    exram_mode: u8,
+    /// 76 54 32 10
+    ///
+    /// DD CC BB AA
+    ///
+    /// Source of each nametable: 0 for CIRAM page 0, 1 for CIRAM page 1, 2 for ExRAM, 3 for fill mode
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    /// $5113-$5117. Bit 7 selects ROM instead of RAM, except for $5113 (always RAM) and $5117 (always ROM).
+    prg_banks: [u8; 5],
+    /// $5120-$512B, with the upper bits from $5130 applied at the time of writing.
+    chr_banks: [u16; 12],
+    chr_upper_bits: u8,
+    last_chr_set: ChrSet,
+    /// 7654 3210
+    ///
+    /// ER.T TTTT
+    ///
+    /// Enable, Right side, Tile count
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    multiplicand: u8,
+    multiplier: u8,
+    /// Snooped from PPUCTRL.
+    large_sprites: bool,
+    /// Snooped from PPUMASK.
+    rendering_enabled: bool,
+    in_frame: bool,
+    scanline: u8,
+    last_ppu_addr: u16,
+    matching_reads: u8,
+    /// Reads since the current scanline started.
+    line_fetches: u16,
+    /// CPU cycles since the last PPU read. The PPU stops reading when it's done rendering.
+    idle_cycles: u8,
+    /// ExRAM byte of the background tile being fetched, in extended attribute mode.
+    extended_attribute: u8,
+}
+
+impl MMC5 {
+    pub fn new(rom: ROM) -> MMC5 {
+        let old_header = rom.info.version == iNESVersion::Ver1;
+
+        let mut memory = CartridgeMemory::new(rom);
+
+        // iNES headers can't tell how much PRG-RAM there is.
+        // The biggest configuration works with every game, as long as the banks wrap around.
+        if old_header {
+            memory.prg_ram.resize(0x10000, 0);
+        }
+
+        MMC5 {
+            memory,
+            audio: audio::Audio::default(),
+            exram: Box::new([0u8; 0x0400]),
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: [0, 0],
+            exram_mode: 0,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0, 0, 0, 0xFF],
+            chr_banks: [0; 12],
+            chr_upper_bits: 0,
+            last_chr_set: ChrSet::Sprites,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            large_sprites: false,
+            rendering_enabled: false,
+            in_frame: false,
+            scanline: 0,
+            last_ppu_addr: 0,
+            matching_reads: 0,
+            line_fetches: 0,
+            idle_cycles: 0,
+            extended_attribute: 0,
+        }
+    }
+
+    fn product(&self) -> u16 {
+        self.multiplicand as u16 * self.multiplier as u16
+    }
+
+    /// 7654 3210
+    ///
+    /// PF.. ....
+    ///
+    /// IRQ pending, in frame
+    fn irq_status(&self) -> u8 {
+        ((self.irq_pending as u8) << 7) | ((self.in_frame as u8) << 6)
+    }
+
+    /// Finds the bank register in charge of the given address in $8000-$FFFF, and the 8 KB bank it selects.
+    /// Registers are numbered from $5113.
+    fn prg_mapping(&self, addr: u16) -> (usize, usize) {
+        let slot = ((addr - 0x8000) >> 13) as usize;
+
+        let (register, mask) = match (self.prg_mode, slot) {
+            // 32 KB
+            (0, _) => (4, 0b0111_1100),
+            // 16 KB + 16 KB
+            (1, _) => (2 + (slot & 0b10), 0b0111_1110),
+            // 16 KB + 8 KB + 8 KB
+            (2, 0 | 1) => (2, 0b0111_1110),
+            (2, _) => (1 + slot, 0b0111_1111),
+            // 8 KB each
+            _ => (1 + slot, 0b0111_1111),
+        };
+
+        let offset = slot & !(mask as usize) & 0b11;
+
+        (
+            register,
+            (self.prg_banks[register] & mask) as usize | offset,
+        )
+    }
+
+    fn prg_is_rom(&self, register: usize) -> bool {
+        register == 4 || get_bit(self.prg_banks[register], 7)
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    fn chr_bank(&self, set: ChrSet, addr: u16) -> (usize, usize) {
+        let (register, bank_size) = match (set, self.chr_mode) {
+            (ChrSet::Sprites, 0) => (7, 0x2000),
+            (ChrSet::Sprites, 1) => (3 + ((addr >> 12) & 1) * 4, 0x1000),
+            (ChrSet::Sprites, 2) => (1 + ((addr >> 11) & 0b11) * 2, 0x0800),
+            (ChrSet::Sprites, _) => ((addr >> 10) & 0b111, 0x0400),
+            (ChrSet::Background, 0) => (11, 0x2000),
+            (ChrSet::Background, 1) => (11, 0x1000),
+            (ChrSet::Background, 2) => (9 + ((addr >> 11) & 1) * 2, 0x0800),
+            (ChrSet::Background, _) => (8 + ((addr >> 10) & 0b11), 0x0400),
+        };
+
+        (self.chr_banks[register as usize] as usize, bank_size)
+    }
+
+    /// Figures out which CHR set a pattern fetch goes through.
+    /// With 8x8 sprites, only the sprite set is used.
+    fn chr_set(&self, fetch: Option<u16>) -> ChrSet {
+        if !self.large_sprites {
+            return ChrSet::Sprites;
+        }
+
+        match fetch {
+            Some(BACKGROUND_FETCHES_END..SPRITE_FETCHES_END) => ChrSet::Sprites,
+            Some(_) => ChrSet::Background,
+            None => self.last_chr_set,
+        }
+    }
+
+    /// Keeps track of the PPU's progress through the frame.
+    /// Returns the position of this read within the scanline, if the PPU is rendering.
+    fn observe_ppu_read(&mut self, addr: u16) -> Option<u16> {
+        self.idle_cycles = 0;
+
+        if (0x2000..=0x2FFF).contains(&addr) && addr == self.last_ppu_addr {
+            self.matching_reads += 1;
+
+            if self.matching_reads == 2 {
+                self.start_scanline();
+            }
+        } else {
+            self.matching_reads = 0;
+        }
+
+        self.last_ppu_addr = addr;
+
+        if !self.in_frame {
+            return None;
+        }
+
+        let fetch = self.line_fetches;
+        self.line_fetches = self.line_fetches.saturating_add(1);
+
+        Some(fetch)
+    }
+
+    fn start_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+
+            if self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+        }
+
+        self.line_fetches = 0;
+    }
+
+    fn end_frame(&mut self) {
+        self.in_frame = false;
+        self.matching_reads = 0;
+        self.last_ppu_addr = 0;
+    }
+
+    /// The background tile column (0-33) and scanline a fetch belongs to.
+    /// The last two tiles of a scanline get fetched at the end of the previous one.
+    fn background_tile(&self, fetch: u16) -> Option<(u8, u8)> {
+        match fetch {
+            0..BACKGROUND_FETCHES_END => Some((fetch as u8 / 4 + 2, self.scanline)),
+            SPRITE_FETCHES_END..PREFETCH_END => Some((
+                (fetch - SPRITE_FETCHES_END) as u8 / 4,
+                self.scanline.wrapping_add(1),
+            )),
+            _ => None,
+        }
+    }
+
+    /// If the tile falls into the split region, the Y coordinate within the split.
+    fn split_y(&self, column: u8, scanline: u8) -> Option<u16> {
+        if !get_bit(self.split_control, 7) || self.exram_mode > 1 {
+            return None;
+        }
+
+        let tile_count = self.split_control & 0b1_1111;
+
+        let in_split = if get_bit(self.split_control, 6) {
+            column >= tile_count
+        } else {
+            column < tile_count
+        };
+
+        in_split.then(|| (self.split_scroll as u16 + scanline as u16) % 240)
+    }
+
+    /// Background nametable and attribute fetches, which split screen and extended attributes intercept.
+    fn background_nametable_read(&mut self, addr: u16, fetch: u16) -> Option<u8> {
+        let (column, scanline) = self.background_tile(fetch)?;
+        let is_attribute = fetch % 4 == 1;
+
+        if let Some(y) = self.split_y(column, scanline) {
+            let row = y / 8;
+            let column = column as u16 % 32;
+
+            return if is_attribute {
+                let attribute = self.exram[(0x03C0 + (row / 4) * 8 + column / 4) as usize];
+                let shift = ((row & 0b10) << 1) | (column & 0b10);
+
+                Some(((attribute >> shift) & 0b11) * 0b0101_0101)
+            } else {
+                Some(self.exram[(row * 32 + column) as usize])
+            };
+        }
+
+        if self.exram_mode == 1 {
+            if is_attribute {
+                // The palette applies to the whole tile, so it gets copied into every quadrant.
+                return Some((self.extended_attribute >> 6) * 0b0101_0101);
+            }
+
+            self.extended_attribute = self.exram[(addr & 0x03FF) as usize];
+        }
+
+        None
+    }
+
+    /// Pattern fetches, which also get redirected by split screen and extended attributes.
+    fn pattern_read(&self, addr: u16, fetch: Option<u16>) -> u8 {
+        let background_tile = fetch
+            .filter(|_| self.rendering_enabled)
+            .and_then(|fetch| self.background_tile(fetch));
+
+        if let Some((column, scanline)) = background_tile {
+            if let Some(y) = self.split_y(column, scanline) {
+                // The split has its own fine Y scroll.
+                let addr = (addr & !0b111) | (y & 0b111);
+
+                return self.memory.chr(self.split_bank as usize, 0x1000, addr);
+            }
+
+            if self.exram_mode == 1 {
+                let bank = ((self.chr_upper_bits as usize) << 6)
+                    | (self.extended_attribute & 0b0011_1111) as usize;
+
+                return self.memory.chr(bank, 0x1000, addr);
+            }
+        }
+
+        let (bank, bank_size) = self.chr_bank(self.chr_set(fetch), addr);
+
+        self.memory.chr(bank, bank_size, addr)
+    }
+
+    fn nametable_source(&self, addr: u16) -> u8 {
+        let slot = (addr >> 10) & 0b11;
+
+        (self.nametable_mapping >> (slot * 2)) & 0b11
+    }
+}
+
+impl Mapper for MMC5 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x5010 | 0x5015 => self.audio.peek(addr),
+            0x5204 => Some(self.irq_status()),
+            0x5205 => Some(self.product().to_le_bytes()[0]),
+            0x5206 => Some(self.product().to_le_bytes()[1]),
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Some(self.exram[(addr - 0x5C00) as usize]),
+            0x6000..=0x7FFF => {
+                self.memory
+                    .prg_ram(self.prg_banks[0] as usize & 0b111, 0x2000, addr)
+            }
+            0x8000..=0xFFFF => {
+                let (register, bank) = self.prg_mapping(addr);
+
+                if self.prg_is_rom(register) {
+                    Some(self.memory.prg_rom(bank, 0x2000, addr))
+                } else {
+                    self.memory.prg_ram(bank & 0b111, 0x2000, addr)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        let value = match addr {
+            0x5010 => self.audio.read(addr),
+            // Reading the status acknowledges the IRQ.
+            0x5204 => {
+                let status = self.irq_status();
+                self.irq_pending = false;
+
+                Some(status)
+            }
+            _ => self.cpu_peek(addr),
+        };
+
+        match addr {
+            0x8000..=0xBFFF => self.audio.observe_prg_read(value.unwrap_or(0)),
+            // Fetching the NMI vector means the PPU has entered vertical blank.
+            0xFFFA | 0xFFFB => self.end_frame(),
+            _ => {}
+        }
+
+        value
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x5000..=0x5015 => self.audio.write(addr, value),
+            0x5100 => self.prg_mode = value & 0b11,
+            0x5101 => self.chr_mode = value & 0b11,
+            0x5102 => self.prg_ram_protect[0] = value & 0b11,
+            0x5103 => self.prg_ram_protect[1] = value & 0b11,
+            0x5104 => self.exram_mode = value & 0b11,
+            0x5105 => self.nametable_mapping = value,
+            0x5106 => self.fill_tile = value,
+            0x5107 => self.fill_attribute = value & 0b11,
+            0x5113..=0x5117 => self.prg_banks[(addr - 0x5113) as usize] = value,
+            0x5120..=0x512B => {
+                let register = (addr - 0x5120) as usize;
+
+                self.chr_banks[register] = ((self.chr_upper_bits as u16) << 8) | value as u16;
+                self.last_chr_set = if register < 8 {
+                    ChrSet::Sprites
+                } else {
+                    ChrSet::Background
+                };
+            }
+            0x5130 => self.chr_upper_bits = value & 0b11,
+            0x5200 => self.split_control = value,
+            0x5201 => self.split_scroll = value,
+            0x5202 => self.split_bank = value,
+            0x5203 => self.irq_compare = value,
+            0x5204 => self.irq_enabled = get_bit(value, 7),
+            0x5205 => self.multiplicand = value,
+            0x5206 => self.multiplier = value,
+            0x5C00..=0x5FFF => {
+                let index = (addr - 0x5C00) as usize;
+
+                match self.exram_mode {
+                    // While it's used for rendering, ExRAM can only be written during rendering. Otherwise, 0 gets written.
+                    0 | 1 => self.exram[index] = if self.in_frame { value } else { 0 },
+                    2 => self.exram[index] = value,
+                    _ => {}
+                }
+            }
+            0x6000..=0x7FFF if self.prg_ram_writable() => {
+                let bank = self.prg_banks[0] as usize & 0b111;
+
+                self.memory.write_prg_ram(bank, 0x2000, addr, value);
+            }
+            0x8000..=0xDFFF if self.prg_ram_writable() => {
+                let (register, bank) = self.prg_mapping(addr);
+
+                if !self.prg_is_rom(register) {
+                    self.memory.write_prg_ram(bank & 0b111, 0x2000, addr, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn cpu_snoop(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x2000..=0x3FFF if addr.is_multiple_of(8) => self.large_sprites = get_bit(value, 5),
+            0x2000..=0x3FFF if addr % 8 == 1 => {
+                self.rendering_enabled = get_bit(value, 3) || get_bit(value, 4);
+
+                if !self.rendering_enabled {
+                    self.end_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.pattern_read(addr, None)
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let fetch = self.observe_ppu_read(addr);
+
+        self.pattern_read(addr, fetch)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        let (bank, bank_size) = self.chr_bank(self.last_chr_set, addr);
+
+        self.memory.write_chr(bank, bank_size, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        let mut pages = [0u8; 4];
+
+        for (slot, page) in pages.iter_mut().enumerate() {
+            // ExRAM and fill mode slots don't use CIRAM at all.
+            *page = (self.nametable_mapping >> (slot * 2)) & 1;
+        }
+
+        Mirroring::Custom(pages)
+    }
+
+    fn nametable_peek(&self, addr: u16) -> Option<u8> {
+        match self.nametable_source(addr) {
+            0 | 1 => None,
+            2 if self.exram_mode <= 1 => Some(self.exram[(addr & 0x03FF) as usize]),
+            2 => Some(0),
+            _ if addr & 0x03FF >= 0x03C0 => Some(self.fill_attribute * 0b0101_0101),
+            _ => Some(self.fill_tile),
+        }
+    }
+
+    fn nametable_read(&mut self, addr: u16) -> Option<u8> {
+        let fetch = self.observe_ppu_read(addr);
+
+        if let Some(fetch) = fetch.filter(|_| self.rendering_enabled) {
+            if let Some(value) = self.background_nametable_read(addr, fetch) {
+                return Some(value);
+            }
+        }
+
+        self.nametable_peek(addr)
+    }
+
+    fn nametable_write(&mut self, addr: u16, value: u8) -> bool {
+        match self.nametable_source(addr) {
+            0 | 1 => false,
+            2 => {
+                if self.exram_mode <= 1 {
+                    self.exram[(addr & 0x03FF) as usize] = value;
+                }
+
+                true
+            }
+            _ => true,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.audio.clock();
+
+        if self.in_frame {
+            self.idle_cycles += 1;
+
+            if self.idle_cycles >= 3 {
+                self.end_frame();
+            }
+        }
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_pending && self.irq_enabled) || self.audio.irq()
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+    use std::num::NonZeroU32;
+
+    /// Three reads in a row from the same nametable address, after something else was read.
+    /// Returns what the last one, the first fetch of the scanline, read.
+    fn scanline(mmc5: &mut MMC5, addr: u16) -> Option<u8> {
+        mmc5.ppu_read(0x0000);
+        mmc5.nametable_read(addr);
+        mmc5.nametable_read(addr);
+        mmc5.nametable_read(addr)
+    }
+
+    fn prg_banks(mmc5: &MMC5) -> [Option<u8>; 4] {
+        [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| mmc5.cpu_peek(addr))
+    }
+
+    #[test]
+    fn prg_banking_modes() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+
+        // $5117 always maps ROM, whatever bit 7 is.
+        for (addr, value) in [
+            (0x5114, 0x81),
+            (0x5115, 0x83),
+            (0x5116, 0x86),
+            (0x5117, 0x0A),
+        ] {
+            mmc5.cpu_write(addr, value);
+        }
+        assert_eq!(prg_banks(&mmc5), [Some(1), Some(3), Some(6), Some(10)]);
+
+        mmc5.cpu_write(0x5100, 0);
+        mmc5.cpu_write(0x5117, 0x87);
+        assert_eq!(prg_banks(&mmc5), [Some(4), Some(5), Some(6), Some(7)]);
+
+        mmc5.cpu_write(0x5100, 1);
+        mmc5.cpu_write(0x5115, 0x83);
+        mmc5.cpu_write(0x5117, 0x8D);
+        assert_eq!(prg_banks(&mmc5), [Some(2), Some(3), Some(12), Some(13)]);
+
+        mmc5.cpu_write(0x5100, 2);
+        mmc5.cpu_write(0x5115, 0x85);
+        mmc5.cpu_write(0x5116, 0x89);
+        mmc5.cpu_write(0x5117, 0x8F);
+        assert_eq!(prg_banks(&mmc5), [Some(4), Some(5), Some(9), Some(15)]);
+    }
+
+    #[test]
+    fn prg_ram_select_and_protect() {
+        let mut rom = test_rom(5, 0);
+        rom.info.prg_ram_size = NonZeroU32::new(0x10000);
+        let mut mmc5 = MMC5::new(rom);
+
+        mmc5.cpu_write(0x5113, 1);
+        mmc5.cpu_write(0x5114, 0x01);
+        mmc5.cpu_write(0x5115, 0x02);
+
+        // Locked until both protect registers hold their magic values.
+        mmc5.cpu_write(0x6000, 0xAA);
+        assert_eq!(mmc5.cpu_peek(0x6000), Some(0));
+
+        mmc5.cpu_write(0x5102, 0b10);
+        mmc5.cpu_write(0x5103, 0b01);
+        mmc5.cpu_write(0x6000, 0xAA);
+        mmc5.cpu_write(0xA000, 0x55);
+
+        // Bit 7 clear maps PRG-RAM banks into $8000-$DFFF.
+        assert_eq!(mmc5.cpu_peek(0x8000), Some(0xAA));
+        mmc5.cpu_write(0x5113, 2);
+        assert_eq!(mmc5.cpu_peek(0x6000), Some(0x55));
+
+        // ROM banks ignore writes.
+        mmc5.cpu_write(0x5116, 0x86);
+        mmc5.cpu_write(0xC000, 0xFF);
+        assert_eq!(mmc5.cpu_peek(0xC000), Some(6));
+
+        mmc5.cpu_write(0x5103, 0);
+        mmc5.cpu_write(0x6000, 0xFF);
+        assert_eq!(mmc5.cpu_peek(0x6000), Some(0x55));
+    }
+
+    #[test]
+    fn chr_sets_for_large_sprites() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+        mmc5.cpu_write(0x5101, 3);
+
+        for register in 0..12 {
+            mmc5.cpu_write(0x5120 + register, 10 + register as u8);
+        }
+
+        let banks = |mmc5: &MMC5| {
+            (0..8)
+                .map(|slot| mmc5.ppu_peek(slot * 0x0400))
+                .collect::<Vec<_>>()
+        };
+
+        // With 8x8 sprites, the background set isn't used at all.
+        assert_eq!(banks(&mmc5), [10, 11, 12, 13, 14, 15, 16, 17]);
+
+        // With 8x16 sprites, the CPU sees whichever set was written last.
+        mmc5.cpu_snoop(0x2000, 0b0010_0000);
+        assert_eq!(banks(&mmc5), [18, 19, 20, 21, 18, 19, 20, 21]);
+        mmc5.cpu_write(0x5127, 17);
+        assert_eq!(banks(&mmc5), [10, 11, 12, 13, 14, 15, 16, 17]);
+
+        // While rendering, background fetches use the background set and sprite fetches the sprite set.
+        scanline(&mut mmc5, 0x2000);
+        assert_eq!(mmc5.ppu_read(0x0400), 19);
+
+        for _ in 2..BACKGROUND_FETCHES_END {
+            mmc5.ppu_read(0x0000);
+        }
+        assert_eq!(mmc5.ppu_read(0x0400), 11);
+
+        for _ in BACKGROUND_FETCHES_END + 1..SPRITE_FETCHES_END {
+            mmc5.ppu_read(0x0000);
+        }
+        assert_eq!(mmc5.ppu_read(0x0400), 19);
+    }
+
+    #[test]
+    fn exram_modes() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+        mmc5.cpu_write(0x5105, 0b10);
+
+        // 2: plain CPU RAM.
+        mmc5.cpu_write(0x5104, 2);
+        mmc5.cpu_write(0x5C00, 0x42);
+        assert_eq!(mmc5.cpu_peek(0x5C00), Some(0x42));
+        assert_eq!(mmc5.nametable_peek(0x2000), Some(0));
+
+        // 3: read-only.
+        mmc5.cpu_write(0x5104, 3);
+        mmc5.cpu_write(0x5C00, 0x99);
+        assert_eq!(mmc5.cpu_peek(0x5C00), Some(0x42));
+
+        // 0: nametable, which the CPU can only write while the PPU is rendering.
+        mmc5.cpu_write(0x5104, 0);
+        assert_eq!(mmc5.cpu_peek(0x5C00), None);
+        assert_eq!(mmc5.nametable_peek(0x2000), Some(0x42));
+
+        mmc5.cpu_write(0x5C00, 0x37);
+        assert_eq!(mmc5.nametable_peek(0x2000), Some(0));
+
+        scanline(&mut mmc5, 0x2400);
+        mmc5.cpu_write(0x5C00, 0x37);
+        assert_eq!(mmc5.nametable_peek(0x2000), Some(0x37));
+    }
+
+    #[test]
+    fn extended_attributes() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+
+        // The first tile fetched on a scanline is in column 2.
+        mmc5.cpu_write(0x5104, 2);
+        mmc5.cpu_write(0x5C02, 0b1000_0101);
+        mmc5.cpu_write(0x5104, 1);
+        mmc5.cpu_snoop(0x2001, 0b0001_1000);
+
+        assert_eq!(scanline(&mut mmc5, 0x2002), None);
+        assert_eq!(mmc5.nametable_read(0x23C0), Some(0b1010_1010));
+        // 4 KB bank 5.
+        assert_eq!(mmc5.ppu_read(0x0000), 20);
+    }
+
+    #[test]
+    fn nametable_mapping_and_fill_mode() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+        mmc5.cpu_write(0x5105, 0b11_10_01_00);
+        mmc5.cpu_write(0x5106, 0x24);
+        mmc5.cpu_write(0x5107, 0b10);
+
+        assert_eq!(mmc5.mirroring(), Mirroring::Custom([0, 1, 0, 1]));
+        assert_eq!(mmc5.nametable_peek(0x2000), None);
+        assert_eq!(mmc5.nametable_peek(0x2400), None);
+        assert_eq!(mmc5.nametable_peek(0x2800), Some(0));
+
+        // The fill tile everywhere, with the fill palette copied into every attribute quadrant.
+        assert_eq!(mmc5.nametable_peek(0x2C00), Some(0x24));
+        assert_eq!(mmc5.nametable_peek(0x2FBF), Some(0x24));
+        assert_eq!(mmc5.nametable_peek(0x2FC0), Some(0b1010_1010));
+
+        assert!(mmc5.nametable_write(0x2C00, 0xFF));
+        assert_eq!(mmc5.nametable_peek(0x2C00), Some(0x24));
+    }
+
+    #[test]
+    fn vertical_split() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+
+        mmc5.cpu_write(0x5104, 2);
+        // Tile row 1, column 2.
+        mmc5.cpu_write(0x5C22, 0x77);
+        // Top right quadrant of the first attribute byte.
+        mmc5.cpu_write(0x5FC0, 0b0000_1100);
+        mmc5.cpu_write(0x5104, 0);
+
+        // The left 3 tiles, scrolled down by 8 pixels, from 4 KB bank 3.
+        mmc5.cpu_write(0x5200, 0b1000_0011);
+        mmc5.cpu_write(0x5201, 8);
+        mmc5.cpu_write(0x5202, 3);
+        mmc5.cpu_snoop(0x2001, 0b0001_1000);
+
+        assert_eq!(scanline(&mut mmc5, 0x2000), Some(0x77));
+        assert_eq!(mmc5.nametable_read(0x23C0), Some(0xFF));
+        assert_eq!(mmc5.ppu_read(0x0000), 12);
+        mmc5.ppu_read(0x0008);
+
+        // Column 3 is outside the split.
+        assert_eq!(mmc5.nametable_read(0x2001), None);
+
+        // On the right side, it is.
+        mmc5.cpu_write(0x5200, 0b1100_0011);
+        assert_eq!(mmc5.nametable_read(0x23C0), Some(0xFF));
+    }
+
+    #[test]
+    fn scanline_irq() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+        mmc5.cpu_write(0x5203, 2);
+        mmc5.cpu_write(0x5204, 0x80);
+
+        assert_eq!(mmc5.cpu_peek(0x5204), Some(0));
+
+        scanline(&mut mmc5, 0x2000);
+        assert_eq!(mmc5.cpu_peek(0x5204), Some(0b0100_0000));
+
+        scanline(&mut mmc5, 0x2000);
+        assert!(!mmc5.irq());
+
+        scanline(&mut mmc5, 0x2000);
+        assert!(mmc5.irq());
+
+        // Reading the status acknowledges it.
+        assert_eq!(mmc5.cpu_read(0x5204), Some(0b1100_0000));
+        assert!(!mmc5.irq());
+        assert_eq!(mmc5.cpu_peek(0x5204), Some(0b0100_0000));
+
+        // The PPU stopped reading, so the frame is over.
+        for _ in 0..3 {
+            mmc5.cpu_clock();
+        }
+        assert_eq!(mmc5.cpu_peek(0x5204), Some(0));
+
+        // So is fetching the NMI vector.
+        scanline(&mut mmc5, 0x2000);
+        mmc5.cpu_read(0xFFFA);
+        assert_eq!(mmc5.cpu_peek(0x5204), Some(0));
+
+        // Pending IRQs only get raised when enabled.
+        mmc5.cpu_write(0x5204, 0);
+        for _ in 0..3 {
+            scanline(&mut mmc5, 0x2000);
+        }
+        assert_eq!(mmc5.cpu_peek(0x5204), Some(0b1100_0000));
+        assert!(!mmc5.irq());
+    }
+
+    #[test]
+    fn multiplier() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+
+        assert_eq!(mmc5.cpu_peek(0x5205), Some(0x01));
+        assert_eq!(mmc5.cpu_peek(0x5206), Some(0xFE));
+
+        mmc5.cpu_write(0x5205, 200);
+        mmc5.cpu_write(0x5206, 100);
+        assert_eq!(mmc5.cpu_peek(0x5205), Some(0x20));
+        assert_eq!(mmc5.cpu_peek(0x5206), Some(0x4E));
+    }
+
+    #[test]
+    fn pulse_channels() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+
+        // Constant volume 15, 12.5% duty, length counter of 2.
+        mmc5.cpu_write(0x5015, 0b01);
+        mmc5.cpu_write(0x5000, 0b0001_1111);
+        mmc5.cpu_write(0x5002, 0x10);
+        mmc5.cpu_write(0x5003, 0b0001_1000);
+        assert_eq!(mmc5.cpu_peek(0x5015), Some(0b01));
+
+        // The sequencer starts on a low step.
+        assert_eq!(mmc5.audio_output(), 0.0);
+        mmc5.cpu_clock();
+        assert!(mmc5.audio_output() > 0.0);
+
+        // The length counter gets clocked every 7457 cycles.
+        for _ in 1..7457 * 2 {
+            mmc5.cpu_clock();
+        }
+        assert_eq!(mmc5.cpu_peek(0x5015), Some(0));
+        assert_eq!(mmc5.audio_output(), 0.0);
+
+        // Disabling the channel clears the length counter right away.
+        mmc5.cpu_write(0x5015, 0b10);
+        mmc5.cpu_write(0x5007, 0b0000_1000);
+        assert_eq!(mmc5.cpu_peek(0x5015), Some(0b10));
+        mmc5.cpu_write(0x5015, 0);
+        assert_eq!(mmc5.cpu_peek(0x5015), Some(0));
+    }
+
+    #[test]
+    fn pcm_channel() {
+        let mut mmc5 = MMC5::new(test_rom(5, 0));
+
+        mmc5.cpu_write(0x5011, 0xFF);
+        assert_eq!(mmc5.audio_output(), 0.42);
+
+        // In read mode, it picks up reads from $8000-$BFFF.
+        mmc5.cpu_write(0x5010, 0b0000_0001);
+        mmc5.cpu_write(0x5011, 0x80);
+        mmc5.cpu_write(0x5114, 0x85);
+        mmc5.cpu_read(0x8000);
+        assert_eq!(mmc5.audio_output(), 5.0 / 255.0 * 0.42);
+
+        // Reading a 0 raises an IRQ instead, acknowledged by reading $5010.
+        mmc5.cpu_write(0x5010, 0b1000_0001);
+        mmc5.cpu_read(0x8001);
+        assert!(mmc5.irq());
+        assert_eq!(mmc5.audio_output(), 5.0 / 255.0 * 0.42);
+
+        assert_eq!(mmc5.cpu_read(0x5010), Some(0b1000_0001));
+        assert!(!mmc5.irq());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::utils::bits::get_bit;
+
+/// Length counter values, indexed by the upper 5 bits of the length register. Same as the APU's.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// The envelope and length counters don't have a frame counter to drive them,
+/// they get clocked at a fixed ~240 Hz instead.
+const FRAME_PERIOD: u16 = 7457;
+
+/// An APU pulse channel, minus the sweep unit.
+#[derive(Default)]
+struct Pulse {
+    enabled: bool,
+    duty: u8,
+    sequence_step: u8,
+    /// Doubles as the envelope loop flag.
+    length_halt: bool,
+    constant_volume: bool,
+    /// Either the constant volume, or the envelope period.
+    volume: u8,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+    timer_period: u16,
+    timer: u16,
+    length_counter: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = value >> 6;
+                self.length_halt = get_bit(value, 5);
+                self.constant_volume = get_bit(value, 4);
+                self.volume = value & 0b1111;
+            }
+            2 => self.timer_period = (self.timer_period & 0xFF00) | value as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((value & 0b111) as u16) << 8);
+
+                if self.enabled {
+                    self.length_counter = LENGTH_TABLE[(value >> 3) as usize];
+                }
+
+                self.sequence_step = 0;
+                self.envelope_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+
+        if !enabled {
+            self.length_counter = 0;
+        }
+    }
+
+    /// Clocked every other CPU cycle.
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_envelope(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.length_halt {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+    }
+
+    fn clock_length_counter(&mut self) {
+        if !self.length_halt && self.length_counter > 0 {
+            self.length_counter -= 1;
+        }
+    }
+
+    /// 0-15
+    fn output(&self) -> u8 {
+        if self.length_counter == 0
+            || DUTY_SEQUENCES[self.duty as usize][self.sequence_step as usize] == 0
+        {
+            return 0;
+        }
+
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
+
+/// MMC5's expansion audio: two pulse channels and an 8-bit PCM channel.
+/// Refer to: https://www.nesdev.org/wiki/MMC5_audio
+#[derive(Default)]
+pub struct Audio {
+    pulses: [Pulse; 2],
+    /// In read mode, the PCM channel picks up whatever the CPU reads from $8000-$BFFF.
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+    pcm: u8,
+    odd_cycle: bool,
+    frame_divider: u16,
+}
+
+impl Audio {
+    pub fn read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            // Reading acknowledges the IRQ.
+            0x5010 => {
+                let value = ((self.pcm_irq as u8) << 7) | self.pcm_read_mode as u8;
+                self.pcm_irq = false;
+
+                Some(value)
+            }
+            0x5015 => Some(self.peek_status()),
+            _ => None,
+        }
+    }
+
+    pub fn peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x5010 => Some(((self.pcm_irq as u8) << 7) | self.pcm_read_mode as u8),
+            0x5015 => Some(self.peek_status()),
+            _ => None,
+        }
+    }
+
+    fn peek_status(&self) -> u8 {
+        ((self.pulses[1].length_counter > 0) as u8) << 1 | (self.pulses[0].length_counter > 0) as u8
+    }
+
+    pub fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x5000..=0x5003 => self.pulses[0].write(addr - 0x5000, value),
+            0x5004..=0x5007 => self.pulses[1].write(addr - 0x5004, value),
+            0x5010 => {
+                self.pcm_read_mode = get_bit(value, 0);
+                self.pcm_irq_enabled = get_bit(value, 7);
+            }
+            0x5011 if !self.pcm_read_mode => self.write_pcm(value),
+            0x5015 => {
+                self.pulses[0].set_enabled(get_bit(value, 0));
+                self.pulses[1].set_enabled(get_bit(value, 1));
+            }
+            _ => {}
+        }
+    }
+
+    /// Only called for CPU reads from $8000-$BFFF.
+    pub fn observe_prg_read(&mut self, value: u8) {
+        if self.pcm_read_mode {
+            self.write_pcm(value);
+        }
+    }
+
+    /// A 0 can't be written to the PCM channel. Trying to do so raises an IRQ instead.
+    fn write_pcm(&mut self, value: u8) {
+        if value == 0 {
+            self.pcm_irq = self.pcm_irq_enabled;
+        } else {
+            self.pcm = value;
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.pcm_irq && self.pcm_irq_enabled
+    }
+
+    pub fn clock(&mut self) {
+        self.odd_cycle = !self.odd_cycle;
+
+        if self.odd_cycle {
+            self.pulses.iter_mut().for_each(Pulse::clock_timer);
+        }
+
+        self.frame_divider += 1;
+
+        if self.frame_divider == FRAME_PERIOD {
+            self.frame_divider = 0;
+
+            for pulse in &mut self.pulses {
+                pulse.clock_envelope();
+                pulse.clock_length_counter();
+            }
+        }
+    }
+
+    /// The pulses go through the same kind of DAC as the APU's, the PCM channel is linear.
+    pub fn output(&self) -> f32 {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+
+        let pulse_out = if pulses == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulses + 100.0)
+        };
+
+        pulse_out + self.pcm as f32 / 255.0 * 0.42
+    }
+}
@@ -0,0 +1,296 @@
+use super::{CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+mod audio;
+
+/// Bank register values from $E0 up select CIRAM pages instead of CHR-ROM.
+const CIRAM_BANKS: u8 = 0xE0;
+
+/// Mapper 19: Namco 129 and 163. Only 163 has audio, but they're otherwise the same.
+///
+/// PRG-ROM is switched in three 8 KB banks, with the last one fixed. CHR is switched in eight 1 KB banks,
+/// and each nametable can be either page of CIRAM or any 1 KB of CHR-ROM.
+/// There's also a 15-bit IRQ counter, and 128 bytes of internal RAM for the audio,
+/// which get kept by the battery along with PRG-RAM.
+/// Refer to: https://www.nesdev.org/wiki/Namco_163
+pub struct Namco163 {
+    memory: CartridgeMemory,
+    battery: bool,
+    chr_banks: [u8; 8],
+    nametable_banks: [u8; 4],
+    prg_banks: [u8; 3],
+    sound_disabled: bool,
+    /// 7654 3210
+    ///
+    /// KKKK 3210
+    ///
+    /// Write enable Key (has to be 0b0100), write protection for each 2 KB of PRG-RAM.
+    /// Shares its address with the audio address port.
+    prg_ram_protect: u8,
+    /// Bit 15 enables counting.
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: audio::Audio,
+}
+
+impl Namco163 {
+    pub fn new(rom: ROM) -> Namco163 {
+        Namco163 {
+            battery: rom.info.nonvolatile_memory,
+            chr_banks: [0; 8],
+            nametable_banks: [CIRAM_BANKS; 4],
+            prg_banks: [0; 3],
+            sound_disabled: false,
+            prg_ram_protect: 0,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: audio::Audio::new(),
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    fn prg_ram_writable(&self, addr: u16) -> bool {
+        let slice = ((addr - 0x6000) >> 11) as u8;
+
+        self.prg_ram_protect >> 4 == 0b0100 && !get_bit(self.prg_ram_protect, slice)
+    }
+
+    fn prg_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x8000..=0xDFFF => self.prg_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.memory.prg_rom_banks(0x2000) - 1,
+        }
+    }
+
+    /// Pattern table banks in the CIRAM range would map nametables into the pattern tables.
+    /// Hardly anything uses that, so those banks just read CHR like any other.
+    fn chr_bank(&self, addr: u16) -> usize {
+        self.chr_banks[(addr >> 10) as usize & 0b111] as usize
+    }
+
+    fn nametable_bank(&self, addr: u16) -> u8 {
+        self.nametable_banks[((addr >> 10) & 0b11) as usize]
+    }
+}
+
+impl Mapper for Namco163 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x4800..=0x4FFF => Some(self.audio.peek_data()),
+            0x5000..=0x57FF => Some(self.irq_counter.to_le_bytes()[0]),
+            0x5800..=0x5FFF => Some(self.irq_counter.to_le_bytes()[1]),
+            0x6000..=0x7FFF => self.memory.prg_ram(0, 0x2000, addr),
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(addr), 0x2000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x4800..=0x4FFF => Some(self.audio.read_data()),
+            _ => self.cpu_peek(addr),
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x4800..=0x4FFF => {
+                self.audio.write_data(value);
+                self.memory.battery_ram_dirty |= self.battery;
+            }
+            // Writing either half of the counter acknowledges the IRQ.
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0xFF00) | value as u16;
+                self.irq_pending = false;
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | ((value as u16) << 8);
+                self.irq_pending = false;
+            }
+            0x6000..=0x7FFF if self.prg_ram_writable(addr) => {
+                self.memory.write_prg_ram(0, 0x2000, addr, value)
+            }
+            0x8000..=0xBFFF => self.chr_banks[((addr - 0x8000) >> 11) as usize] = value,
+            0xC000..=0xDFFF => self.nametable_banks[((addr - 0xC000) >> 11) as usize] = value,
+            0xE000..=0xE7FF => {
+                self.prg_banks[0] = value & 0b0011_1111;
+                self.sound_disabled = get_bit(value, 6);
+            }
+            0xE800..=0xEFFF => self.prg_banks[1] = value & 0b0011_1111,
+            0xF000..=0xF7FF => self.prg_banks[2] = value & 0b0011_1111,
+            0xF800..=0xFFFF => {
+                self.prg_ram_protect = value;
+                self.audio.write_address(value);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank(addr), 0x0400, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory
+            .write_chr(self.chr_bank(addr), 0x0400, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        // Nametables in CHR-ROM don't use CIRAM, so the page doesn't matter for them.
+        Mirroring::Custom(self.nametable_banks.map(|bank| bank & 1))
+    }
+
+    fn nametable_peek(&self, addr: u16) -> Option<u8> {
+        let bank = self.nametable_bank(addr);
+
+        (bank < CIRAM_BANKS).then(|| self.memory.chr(bank as usize, 0x0400, addr))
+    }
+
+    /// CHR-ROM nametables can't be written to.
+    fn nametable_write(&mut self, addr: u16, _value: u8) -> bool {
+        self.nametable_bank(addr) < CIRAM_BANKS
+    }
+
+    /// The IRQ counter counts up every CPU cycle, and raises an IRQ once it reaches $7FFF.
+    fn cpu_clock(&mut self) {
+        let enabled = get_bit((self.irq_counter >> 8) as u8, 7);
+
+        if enabled && self.irq_counter & 0x7FFF != 0x7FFF {
+            self.irq_counter += 1;
+
+            if self.irq_counter & 0x7FFF == 0x7FFF {
+                self.irq_pending = true;
+            }
+        }
+
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn audio_output(&self) -> f32 {
+        if self.sound_disabled {
+            return 0.0;
+        }
+
+        self.audio.output()
+    }
+
+    fn battery_ram(&self) -> Option<Vec<u8>> {
+        if !self.battery {
+            return None;
+        }
+
+        let cartridge_ram = self.memory.battery_ram().unwrap_or_default();
+
+        Some([cartridge_ram.as_slice(), &self.audio.ram].concat())
+    }
+
+    /// The internal RAM comes after the cartridge's battery-backed memory.
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let (cartridge_ram, internal_ram) =
+            data.split_at(data.len().saturating_sub(self.audio.ram.len()));
+
+        self.memory.load_battery_ram(cartridge_ram);
+
+        for (byte, saved) in self.audio.ram.iter_mut().zip(internal_ram) {
+            *byte = *saved;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn internal_ram_auto_increment() {
+        let mut namco163 = Namco163::new(test_rom(19, 0));
+
+        // Wraps around within the 128 bytes.
+        namco163.cpu_write(0xF800, 0x80 | 0x7F);
+        namco163.cpu_write(0x4800, 1);
+        namco163.cpu_write(0x4800, 2);
+
+        namco163.cpu_write(0xF800, 0x80 | 0x7F);
+        assert_eq!(namco163.cpu_peek(0x4800), Some(1));
+        assert_eq!(namco163.cpu_read(0x4800), Some(1));
+        assert_eq!(namco163.cpu_read(0x4800), Some(2));
+
+        // Without bit 7, the address stays put.
+        namco163.cpu_write(0xF800, 0x10);
+        namco163.cpu_write(0x4800, 3);
+        namco163.cpu_write(0x4800, 4);
+        assert_eq!(namco163.cpu_read(0x4800), Some(4));
+        assert_eq!(namco163.cpu_read(0x4800), Some(4));
+    }
+
+    #[test]
+    fn prg_ram_write_protection() {
+        let mut namco163 = Namco163::new(test_rom(19, 0));
+
+        namco163.cpu_write(0x6000, 0xAA);
+        assert_eq!(namco163.cpu_peek(0x6000), Some(0));
+
+        // The key in the upper bits, then the second 2 KB protected.
+        namco163.cpu_write(0xF800, 0b0100_0010);
+        namco163.cpu_write(0x6000, 0xAA);
+        namco163.cpu_write(0x6800, 0xBB);
+        assert_eq!(namco163.cpu_peek(0x6000), Some(0xAA));
+        assert_eq!(namco163.cpu_peek(0x6800), Some(0));
+    }
+
+    #[test]
+    fn nametables_from_ciram_or_chr_rom() {
+        let mut namco163 = Namco163::new(test_rom(19, 0));
+
+        namco163.cpu_write(0xC000, 0xE1);
+        namco163.cpu_write(0xC800, 0xE0);
+        namco163.cpu_write(0xD000, 5);
+        namco163.cpu_write(0xD800, 0xFF);
+
+        assert_eq!(namco163.mirroring(), Mirroring::Custom([1, 0, 1, 1]));
+
+        assert_eq!(namco163.nametable_peek(0x2000), None);
+        assert_eq!(namco163.nametable_peek(0x2800), Some(5));
+        assert_eq!(namco163.nametable_peek(0x2C00), None);
+
+        // CHR-ROM nametables swallow writes, CIRAM ones get them.
+        assert!(namco163.nametable_write(0x2800, 0xFF));
+        assert!(!namco163.nametable_write(0x2000, 0xFF));
+        assert_eq!(namco163.nametable_peek(0x2800), Some(5));
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut namco163 = Namco163::new(test_rom(19, 0));
+
+        namco163.cpu_write(0x5000, 0xFD);
+        namco163.cpu_write(0x5800, 0xFF);
+
+        namco163.cpu_clock();
+        assert!(!namco163.irq());
+        namco163.cpu_clock();
+        assert!(namco163.irq());
+        assert_eq!(namco163.cpu_peek(0x5000), Some(0xFF));
+
+        // It stops at $7FFF.
+        namco163.cpu_clock();
+        assert_eq!(namco163.cpu_peek(0x5000), Some(0xFF));
+
+        // Writing the counter acknowledges it.
+        namco163.cpu_write(0x5800, 0xFF);
+        assert!(!namco163.irq());
+    }
+}
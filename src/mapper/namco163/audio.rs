@@ -0,0 +1,186 @@
+/// The chip only outputs one channel at a time, so every channel is quieter the more of them are enabled.
+const LEVEL: f32 = 0.0025;
+
+/// A channel gets updated every 15 CPU cycles.
+const CYCLES_PER_CHANNEL: u8 = 15;
+
+/// Namco 163's wavetable audio. It shares 128 bytes of RAM between waveforms and up to 8 channels' registers.
+///
+/// Channel registers live at the end of RAM, 8 bytes each, channel 7 at $78-$7F and channel 0 at $40-$47:
+///
+/// * +0: Frequency, bits 0-7
+/// * +1: Phase, bits 0-7
+/// * +2: Frequency, bits 8-15
+/// * +3: Phase, bits 8-15
+/// * +4: Wave length (256 - bits 2-7), frequency bits 16-17
+/// * +5: Phase, bits 16-23
+/// * +6: Wave address, in 4-bit samples
+/// * +7: Volume (bits 0-3). At $7F, bits 4-6 also hold the amount of enabled channels minus 1.
+///
+/// Refer to: https://www.nesdev.org/wiki/Namco_163_audio
+pub struct Audio {
+    pub ram: [u8; 0x80],
+    /// Bit 7 enables auto-increment.
+    address: u8,
+    divider: u8,
+    /// Channels are updated one after another, from channel 7 down.
+    current_channel: u8,
+    output: i8,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio::new()
+    }
+}
+
+impl Audio {
+    pub fn new() -> Audio {
+        Audio {
+            ram: [0; 0x80],
+            address: 0,
+            divider: 0,
+            current_channel: 7,
+            output: 0,
+        }
+    }
+
+    pub fn write_address(&mut self, value: u8) {
+        self.address = value;
+    }
+
+    pub fn peek_data(&self) -> u8 {
+        self.ram[(self.address & 0x7F) as usize]
+    }
+
+    pub fn read_data(&mut self) -> u8 {
+        let value = self.peek_data();
+        self.increment_address();
+
+        value
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.ram[(self.address & 0x7F) as usize] = value;
+        self.increment_address();
+    }
+
+    fn increment_address(&mut self) {
+        if self.address & 0x80 != 0 {
+            self.address = 0x80 | (self.address.wrapping_add(1) & 0x7F);
+        }
+    }
+
+    fn enabled_channels(&self) -> u8 {
+        ((self.ram[0x7F] >> 4) & 0b111) + 1
+    }
+
+    pub fn clock(&mut self) {
+        self.divider += 1;
+
+        if self.divider < CYCLES_PER_CHANNEL {
+            return;
+        }
+
+        self.divider = 0;
+
+        self.output = self.update_channel(self.current_channel);
+
+        let first_channel = 8 - self.enabled_channels();
+
+        self.current_channel = if self.current_channel <= first_channel {
+            7
+        } else {
+            self.current_channel - 1
+        };
+    }
+
+    /// Advances the channel's phase, and returns its new output.
+    fn update_channel(&mut self, channel: u8) -> i8 {
+        let base = 0x40 + channel as usize * 8;
+        let registers = &mut self.ram[base..base + 8];
+
+        let frequency = u32::from_le_bytes([registers[0], registers[2], registers[4] & 0b11, 0]);
+        let phase = u32::from_le_bytes([registers[1], registers[3], registers[5], 0]);
+        let length = 256 - (registers[4] & 0b1111_1100) as u32;
+        let wave_address = registers[6] as u32;
+        let volume = (registers[7] & 0x0F) as i8;
+
+        let phase = (phase + frequency) % (length << 16);
+
+        let [phase_low, phase_mid, phase_high, _] = phase.to_le_bytes();
+        registers[1] = phase_low;
+        registers[3] = phase_mid;
+        registers[5] = phase_high;
+
+        // Samples are 4 bits, low nibble first.
+        let sample_index = ((phase >> 16) + wave_address) & 0xFF;
+        let sample = (self.ram[(sample_index / 2) as usize] >> ((sample_index & 1) * 4)) & 0x0F;
+
+        (sample as i8 - 8) * volume
+    }
+
+    pub fn output(&self) -> f32 {
+        self.output as f32 * LEVEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn updated_channels(audio: &mut Audio, count: usize) -> Vec<u8> {
+        (0..count)
+            .map(|_| {
+                let channel = audio.current_channel;
+
+                for _ in 0..CYCLES_PER_CHANNEL {
+                    audio.clock();
+                }
+
+                channel
+            })
+            .collect()
+    }
+
+    #[test]
+    fn channel_count_from_7f() {
+        let mut audio = Audio::new();
+
+        assert_eq!(updated_channels(&mut audio, 3), [7, 7, 7]);
+
+        // 4 channels: 7 down to 4.
+        audio.ram[0x7F] = 0b0011_0000;
+        assert_eq!(updated_channels(&mut audio, 5), [7, 6, 5, 4, 7]);
+
+        audio.ram[0x7F] = 0b0111_0000;
+        assert_eq!(updated_channels(&mut audio, 9), [6, 5, 4, 3, 2, 1, 0, 7, 6]);
+    }
+
+    #[test]
+    fn disabled_channels_keep_their_phase() {
+        let mut audio = Audio::new();
+        audio.ram[0x7F] = 0b0011_0000;
+
+        // One sample per update for channels 4 and 3.
+        audio.ram[0x64] = 0x01;
+        audio.ram[0x5C] = 0x01;
+
+        updated_channels(&mut audio, 4);
+        assert_eq!(audio.ram[0x65], 1);
+        assert_eq!(audio.ram[0x5D], 0);
+    }
+
+    #[test]
+    fn wave_output() {
+        let mut audio = Audio::new();
+
+        // Sample 1 of the wave is 0xF, channel 7 reads it at volume 2 after advancing by one sample.
+        audio.ram[0x00] = 0xF0;
+        audio.ram[0x7C] = 0x01;
+        audio.ram[0x7F] = 0x02;
+
+        updated_channels(&mut audio, 1);
+        assert_eq!(audio.output(), 14.0 * LEVEL);
+    }
+}
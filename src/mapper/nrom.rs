@@ -0,0 +1,59 @@
+use super::{CartridgeMemory, Mapper, Mirroring};
+use crate::rom::ROM;
+
+/// Mapper 0, no banking at all.
+///
+/// NROM-128 has 16 KB of PRG-ROM, mirrored into both halves of $8000-$FFFF.
+/// NROM-256 has 32 KB of PRG-ROM, filling the whole space.
+/// Family Basic carts also have PRG-RAM at $6000-$7FFF.
+#[allow(clippy::upper_case_acronyms)]
+pub struct NROM {
+    memory: CartridgeMemory,
+    mirroring: Mirroring,
+}
+
+impl NROM {
+    pub fn new(rom: ROM) -> NROM {
+        NROM {
+            mirroring: Mirroring::from_header(&rom.info),
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+}
+
+impl Mapper for NROM {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.memory.prg_ram(0, 0x2000, addr),
+            // Mirroring NROM-128 comes for free, since bank 0 wraps around the ROM size.
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(0, 0x8000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        if let 0x6000..=0x7FFF = addr {
+            self.memory.write_prg_ram(0, 0x2000, addr, value);
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(0, 0x2000, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory.write_chr(0, 0x2000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,143 @@
+use super::{BusConflicts, CartridgeMemory, Mapper, Mirroring};
+use crate::rom::ROM;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Variant {
+    /// Mapper 2: UNROM and UOROM. $8000 is switchable, $C000 is fixed to the last bank.
+    Standard,
+    /// Mapper 94: UN1ROM, used by Senjou no Ookami. The bank number sits in bits 2-4.
+    UN1ROM,
+    /// Mapper 180: UNROM with a 74HC08 instead of a 74HC32, used by Crazy Climber.
+    /// $8000 is fixed to the first bank, $C000 is switchable.
+    Reversed,
+}
+
+/// Mappers 2, 94 and 180, switching 16 KB of PRG-ROM with an 8 KB CHR-RAM.
+/// Writing anywhere in $8000-$FFFF selects the bank.
+/// Refer to: https://www.nesdev.org/wiki/UxROM
+#[allow(clippy::upper_case_acronyms)]
+pub struct UxROM {
+    memory: CartridgeMemory,
+    mirroring: Mirroring,
+    bus_conflicts: BusConflicts,
+    variant: Variant,
+    prg_bank: u8,
+}
+
+impl UxROM {
+    pub fn new(rom: ROM) -> UxROM {
+        let variant = match rom.info.mapper {
+            94 => Variant::UN1ROM,
+            180 => Variant::Reversed,
+            _ => Variant::Standard,
+        };
+
+        UxROM {
+            mirroring: Mirroring::from_header(&rom.info),
+            bus_conflicts: BusConflicts::from_header(&rom.info),
+            variant,
+            prg_bank: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    /// Picks the 16 KB PRG-ROM bank for the given address.
+    fn prg_bank(&self, addr: u16) -> usize {
+        let last_bank = self.memory.prg_rom_banks(0x4000) - 1;
+
+        match (self.variant, addr >= 0xC000) {
+            (Variant::Reversed, false) => 0,
+            (Variant::Reversed, true) => self.prg_bank as usize,
+            (_, false) => self.prg_bank as usize,
+            (_, true) => last_bank,
+        }
+    }
+}
+
+impl Mapper for UxROM {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.memory.prg_ram(0, 0x2000, addr),
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(addr), 0x4000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.memory.write_prg_ram(0, 0x2000, addr, value),
+            0x8000..=0xFFFF => {
+                let rom_value = self.memory.prg_rom(self.prg_bank(addr), 0x4000, addr);
+                let value = self.bus_conflicts.apply(rom_value, value);
+
+                self.prg_bank = match self.variant {
+                    Variant::UN1ROM => (value >> 2) & 0b111,
+                    // UNROM only connects 3 bits, UOROM 4, but some homebrew uses all 8.
+                    _ => value,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(0, 0x2000, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory.write_chr(0, 0x2000, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    fn prg_banks(uxrom: &UxROM) -> [Option<u8>; 2] {
+        [0x8000, 0xC000].map(|addr| uxrom.cpu_peek(addr))
+    }
+
+    #[test]
+    fn bank_switching() {
+        // Every 16 KB bank starts with twice its own number.
+        let mut uxrom = UxROM::new(test_rom(2, 0));
+        uxrom.cpu_write(0x8000, 3);
+        assert_eq!(prg_banks(&uxrom), [Some(6), Some(14)]);
+
+        let mut un1rom = UxROM::new(test_rom(94, 0));
+        un1rom.cpu_write(0x8000, 3 << 2);
+        assert_eq!(prg_banks(&un1rom), [Some(6), Some(14)]);
+
+        let mut reversed = UxROM::new(test_rom(180, 0));
+        reversed.cpu_write(0x8000, 3);
+        assert_eq!(prg_banks(&reversed), [Some(0), Some(6)]);
+    }
+
+    #[test]
+    fn bus_conflicts_from_submapper() {
+        for (submapper, bank) in [(1, 7), (2, 3)] {
+            let mut rom = test_rom(2, submapper);
+            rom.prg_rom[0x1FFF0] = 0x03;
+
+            let mut uxrom = UxROM::new(rom);
+            uxrom.cpu_write(0xFFF0, 0xFF);
+
+            // 0xFF wraps around to the last of the 8 banks, 0xFF & 0x03 doesn't.
+            assert_eq!(uxrom.cpu_peek(0x8000), Some(bank * 2), "{submapper}");
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use super::{vrc_irq::VrcIrq, CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+/// Which CPU address lines the chip's A0 and A1 pins are connected to.
+/// Konami wasn't consistent about it, so every board revision is its own variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Wiring {
+    a0: u16,
+    a1: u16,
+}
+
+impl Wiring {
+    const fn new(a0: u8, a1: u8) -> Wiring {
+        Wiring {
+            a0: 1 << a0,
+            a1: 1 << a1,
+        }
+    }
+
+    /// Without a submapper, all of the mapper's variants are wired up at once.
+    /// Games only ever write to addresses meant for their own variant, so this works for most of them.
+    const fn combined(first: Wiring, second: Wiring) -> Wiring {
+        Wiring {
+            a0: first.a0 | second.a0,
+            a1: first.a1 | second.a1,
+        }
+    }
+
+    /// Turns a CPU address into the register address the chip sees, $x000-$x003.
+    fn register(self, addr: u16) -> u16 {
+        let a0 = (addr & self.a0 != 0) as u16;
+        let a1 = (addr & self.a1 != 0) as u16;
+
+        (addr & 0xF000) | (a1 << 1) | a0
+    }
+}
+
+const VRC4A: Wiring = Wiring::new(1, 2);
+const VRC4B: Wiring = Wiring::new(1, 0);
+const VRC4C: Wiring = Wiring::new(6, 7);
+const VRC4D: Wiring = Wiring::new(3, 2);
+const VRC4E: Wiring = Wiring::new(2, 3);
+const VRC4F: Wiring = Wiring::new(0, 1);
+const VRC2A: Wiring = Wiring::new(1, 0);
+const VRC2B: Wiring = Wiring::new(0, 1);
+const VRC2C: Wiring = Wiring::new(1, 0);
+
+/// Mappers 21, 22, 23 and 25: Konami's VRC4, and the VRC2 it's an extension of.
+///
+/// Both switch two 8 KB PRG-ROM banks and eight 1 KB CHR banks.
+/// VRC4 adds a PRG swap mode, single-screen mirroring and the VRC IRQ counter.
+/// Mapper numbers group boards by how they're wired, NES 2.0 submappers tell the variants apart:
+///
+/// * 21: VRC4a (1), VRC4c (2)
+/// * 22: VRC2a
+/// * 23: VRC4f (1), VRC4e (2), VRC2b (3)
+/// * 25: VRC4b (1), VRC4d (2), VRC2c (3)
+///
+/// Refer to: https://www.nesdev.org/wiki/VRC2_and_VRC4
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC4 {
+    memory: CartridgeMemory,
+    wiring: Wiring,
+    vrc2: bool,
+    /// VRC2a ignores the lowest bit of CHR bank numbers.
+    chr_shift: u8,
+    prg_banks: [u8; 2],
+    prg_swap_mode: bool,
+    chr_banks: [u16; 8],
+    /// VRC2 only has one bit here, and can't do single-screen mirroring.
+    mirroring: u8,
+    irq: VrcIrq,
+    /// VRC2 boards without PRG-RAM still have a single bit of storage at $6000-$6FFF, used for copy protection.
+    microwire_latch: u8,
+}
+
+impl VRC4 {
+    pub fn new(rom: ROM) -> VRC4 {
+        let (wiring, vrc2) = match (rom.info.mapper, rom.info.submapper) {
+            (21, 1) => (VRC4A, false),
+            (21, 2) => (VRC4C, false),
+            (21, _) => (Wiring::combined(VRC4A, VRC4C), false),
+            (22, _) => (VRC2A, true),
+            (23, 1) => (VRC4F, false),
+            (23, 2) => (VRC4E, false),
+            (23, 3) => (VRC2B, true),
+            (23, _) => (Wiring::combined(VRC4F, VRC4E), false),
+            (25, 1) => (VRC4B, false),
+            (25, 2) => (VRC4D, false),
+            (25, 3) => (VRC2C, true),
+            (_, _) => (Wiring::combined(VRC4B, VRC4D), false),
+        };
+
+        VRC4 {
+            wiring,
+            vrc2,
+            chr_shift: (rom.info.mapper == 22) as u8,
+            prg_banks: [0, 0],
+            prg_swap_mode: false,
+            chr_banks: [0; 8],
+            mirroring: 0,
+            irq: VrcIrq::default(),
+            microwire_latch: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    /// Picks the 8 KB PRG-ROM bank for the given address.
+    fn prg_bank(&self, addr: u16) -> usize {
+        let second_last = self.memory.prg_rom_banks(0x2000).saturating_sub(2);
+
+        match (addr, self.prg_swap_mode) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => self.prg_banks[0] as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => self.prg_banks[1] as usize,
+            _ => second_last + 1,
+        }
+    }
+
+    fn chr_bank(&self, addr: u16) -> usize {
+        (self.chr_banks[(addr >> 10) as usize & 0b111] >> self.chr_shift) as usize
+    }
+
+    /// CHR banks are written a nibble at a time, two registers per bank.
+    fn write_chr_bank(&mut self, register: u16, value: u8) {
+        let index = (((register - 0xB000) >> 12) * 2 + ((register >> 1) & 1)) as usize;
+        let bank = &mut self.chr_banks[index];
+
+        if register & 1 == 0 {
+            *bank = (*bank & 0x1F0) | (value & 0x0F) as u16;
+        } else {
+            // VRC2 only has 4 bits in the high nibble.
+            let mask = if self.vrc2 { 0x0F } else { 0x1F };
+
+            *bank = (*bank & 0x00F) | (((value & mask) as u16) << 4);
+        }
+    }
+}
+
+impl Mapper for VRC4 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x6FFF if self.vrc2 && self.memory.prg_ram.is_empty() => {
+                Some(self.microwire_latch)
+            }
+            0x6000..=0x7FFF => self.memory.prg_ram(0, 0x2000, addr),
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(addr), 0x2000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x6FFF if self.vrc2 && self.memory.prg_ram.is_empty() => {
+                self.microwire_latch = value & 1;
+            }
+            0x6000..=0x7FFF => self.memory.write_prg_ram(0, 0x2000, addr, value),
+            0x8000..=0xFFFF => match self.wiring.register(addr) {
+                0x8000..=0x8003 => self.prg_banks[0] = value & 0b1_1111,
+                0x9000..=0x9003 if self.vrc2 => self.mirroring = value & 1,
+                0x9000 | 0x9001 => self.mirroring = value & 0b11,
+                0x9002 | 0x9003 if !self.vrc2 => self.prg_swap_mode = get_bit(value, 1),
+                0xA000..=0xA003 => self.prg_banks[1] = value & 0b1_1111,
+                register @ 0xB000..=0xEFFF => self.write_chr_bank(register, value),
+                0xF000 if !self.vrc2 => self.irq.write_latch_low(value),
+                0xF001 if !self.vrc2 => self.irq.write_latch_high(value),
+                0xF002 if !self.vrc2 => self.irq.write_control(value),
+                0xF003 if !self.vrc2 => self.irq.acknowledge(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        self.memory.chr(self.chr_bank(addr), 0x0400, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.memory
+            .write_chr(self.chr_bank(addr), 0x0400, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn wiring_tables() {
+        // The CPU addresses of registers $x001, $x002 and $x003 for every variant.
+        let variants = [
+            (VRC4A, [0x02, 0x04, 0x06]),
+            (VRC4B, [0x02, 0x01, 0x03]),
+            (VRC4C, [0x40, 0x80, 0xC0]),
+            (VRC4D, [0x08, 0x04, 0x0C]),
+            (VRC4E, [0x04, 0x08, 0x0C]),
+            (VRC4F, [0x01, 0x02, 0x03]),
+            (VRC2A, [0x02, 0x01, 0x03]),
+            (VRC2B, [0x01, 0x02, 0x03]),
+            (VRC2C, [0x02, 0x01, 0x03]),
+        ];
+
+        for (wiring, offsets) in variants {
+            assert_eq!(wiring.register(0xB000), 0xB000);
+
+            for (register, offset) in (1..=3).zip(offsets) {
+                assert_eq!(
+                    wiring.register(0xB000 | offset),
+                    0xB000 | register,
+                    "{wiring:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn submappers_pick_their_wiring() {
+        // Mapper, submapper, and the CPU addresses of the low and high nibble of the second CHR bank.
+        let boards = [
+            (21, 1, [0xB004, 0xB006]),
+            (21, 2, [0xB080, 0xB0C0]),
+            (22, 0, [0xB001, 0xB003]),
+            (23, 1, [0xB002, 0xB003]),
+            (23, 2, [0xB008, 0xB00C]),
+            (23, 3, [0xB002, 0xB003]),
+            (25, 1, [0xB001, 0xB003]),
+            (25, 2, [0xB004, 0xB00C]),
+            (25, 3, [0xB001, 0xB003]),
+        ];
+
+        for (mapper, submapper, [low, high]) in boards {
+            let mut vrc = VRC4::new(test_rom(mapper, submapper));
+
+            vrc.cpu_write(low, 0x04);
+            vrc.cpu_write(high, 0x01);
+
+            // VRC2a drops the lowest bit of the bank number.
+            let expected = if mapper == 22 { 0x0A } else { 0x14 };
+
+            assert_eq!(vrc.ppu_peek(0x0400), expected, "{mapper}.{submapper}");
+        }
+    }
+
+    #[test]
+    fn combined_wiring_without_submapper() {
+        // VRC4a and VRC4c both work on a mapper 21 board with no submapper.
+        for offset in [0x02, 0x40] {
+            let mut vrc = VRC4::new(test_rom(21, 0));
+
+            vrc.cpu_write(0xB000, 0x03);
+            vrc.cpu_write(0xB000 | offset, 0x01);
+
+            assert_eq!(vrc.ppu_peek(0x0000), 0x13);
+        }
+    }
+
+    #[test]
+    fn prg_swap_mode() {
+        let mut vrc = VRC4::new(test_rom(21, 1));
+
+        vrc.cpu_write(0x8000, 3);
+        vrc.cpu_write(0xA000, 5);
+        assert_eq!(vrc.cpu_peek(0x8000), Some(3));
+        assert_eq!(vrc.cpu_peek(0xA000), Some(5));
+        assert_eq!(vrc.cpu_peek(0xC000), Some(14));
+        assert_eq!(vrc.cpu_peek(0xE000), Some(15));
+
+        // $9002 on VRC4a
+        vrc.cpu_write(0x9004, 0b10);
+        assert_eq!(vrc.cpu_peek(0x8000), Some(14));
+        assert_eq!(vrc.cpu_peek(0xC000), Some(3));
+    }
+}
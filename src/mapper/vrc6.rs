@@ -0,0 +1,236 @@
+use super::{vrc_irq::VrcIrq, CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+mod audio;
+
+/// Mappers 24 and 26: Konami's VRC6, with expansion audio.
+///
+/// PRG-ROM is switched as one 16 KB and one 8 KB bank, with the last 8 KB fixed.
+/// VRC6b (mapper 26) has the A0 and A1 lines swapped compared to VRC6a (mapper 24).
+/// Refer to: https://www.nesdev.org/wiki/VRC6
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC6 {
+    memory: CartridgeMemory,
+    swapped_lines: bool,
+    prg_bank_16k: u8,
+    prg_bank_8k: u8,
+    chr_banks: [u8; 8],
+    /// 7654 3210
+    ///
+    /// W.AN MMDD
+    ///
+    /// PRG-RAM enable, CHR A10 from the PPU, Nametables from CHR-ROM, Mirroring, CHR banking mode
+    ///
+    /// No licensed game puts nametables in CHR-ROM, so that bit is ignored.
+    banking_control: u8,
+    irq: VrcIrq,
+    audio: audio::Audio,
+}
+
+impl VRC6 {
+    pub fn new(rom: ROM) -> VRC6 {
+        VRC6 {
+            swapped_lines: rom.info.mapper == 26,
+            prg_bank_16k: 0,
+            prg_bank_8k: 0,
+            chr_banks: [0; 8],
+            banking_control: 0,
+            irq: VrcIrq::default(),
+            audio: audio::Audio::default(),
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    /// Turns a CPU address into the register address the chip sees, $x000-$x003.
+    fn register(&self, addr: u16) -> u16 {
+        let lines = addr & 0b11;
+
+        let lines = if self.swapped_lines {
+            ((lines & 1) << 1) | (lines >> 1)
+        } else {
+            lines
+        };
+
+        (addr & 0xF000) | lines
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        get_bit(self.banking_control, 7)
+    }
+
+    /// Finds the CHR bank and bank size for the given address.
+    fn chr_bank(&self, addr: u16) -> (usize, usize) {
+        let slot = (addr >> 10) as usize & 0b111;
+
+        match self.banking_control & 0b11 {
+            // 1 KB banks
+            0 => (self.chr_banks[slot] as usize, 0x0400),
+            // 2 KB banks
+            1 => self.chr_bank_2k(self.chr_banks[slot / 2]),
+            // 1 KB banks for $0000-$0FFF, 2 KB banks for $1000-$1FFF
+            _ if slot < 4 => (self.chr_banks[slot] as usize, 0x0400),
+            _ => self.chr_bank_2k(self.chr_banks[4 + (slot - 4) / 2]),
+        }
+    }
+
+    /// The registers still select 1 KB banks in the 2 KB modes.
+    /// With bit 5 of $B003 set, PPU A10 replaces the low bit, which makes them 2 KB banks.
+    /// Otherwise, the same 1 KB shows up in both halves.
+    fn chr_bank_2k(&self, register: u8) -> (usize, usize) {
+        if get_bit(self.banking_control, 5) {
+            ((register >> 1) as usize, 0x0800)
+        } else {
+            (register as usize, 0x0400)
+        }
+    }
+}
+
+impl Mapper for VRC6 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.memory.prg_ram(0, 0x2000, addr),
+            0x8000..=0xBFFF => Some(
+                self.memory
+                    .prg_rom(self.prg_bank_16k as usize, 0x4000, addr),
+            ),
+            0xC000..=0xDFFF => Some(self.memory.prg_rom(self.prg_bank_8k as usize, 0x2000, addr)),
+            0xE000..=0xFFFF => {
+                let last_bank = self.memory.prg_rom_banks(0x2000) - 1;
+
+                Some(self.memory.prg_rom(last_bank, 0x2000, addr))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.memory.write_prg_ram(0, 0x2000, addr, value)
+            }
+            0x8000..=0xFFFF => match self.register(addr) {
+                0x8000..=0x8003 => self.prg_bank_16k = value & 0x0F,
+                register @ (0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002) => {
+                    self.audio.write(register, value)
+                }
+                0xB003 => self.banking_control = value,
+                0xC000..=0xC003 => self.prg_bank_8k = value & 0x1F,
+                register @ (0xD000..=0xD003 | 0xE000..=0xE003) => {
+                    let index = (((register - 0xD000) >> 12) * 4 + (register & 0b11)) as usize;
+
+                    self.chr_banks[index] = value;
+                }
+                0xF000 => self.irq.write_latch(value),
+                0xF001 => self.irq.write_control(value),
+                0xF002 => self.irq.acknowledge(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        let (bank, bank_size) = self.chr_bank(addr);
+
+        self.memory.chr(bank, bank_size, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        let (bank, bank_size) = self.chr_bank(addr);
+
+        self.memory.write_chr(bank, bank_size, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking_control >> 2) & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn vrc6b_swaps_a0_and_a1() {
+        let mut vrc6a = VRC6::new(test_rom(24, 0));
+        let mut vrc6b = VRC6::new(test_rom(26, 0));
+
+        for vrc in [&mut vrc6a, &mut vrc6b] {
+            vrc.cpu_write(0xD001, 0x11);
+            vrc.cpu_write(0xD002, 0x22);
+        }
+
+        assert_eq!(vrc6a.ppu_peek(0x0400), 0x11);
+        assert_eq!(vrc6a.ppu_peek(0x0800), 0x22);
+        assert_eq!(vrc6b.ppu_peek(0x0400), 0x22);
+        assert_eq!(vrc6b.ppu_peek(0x0800), 0x11);
+    }
+
+    #[test]
+    fn banking_control_register() {
+        let mut vrc6a = VRC6::new(test_rom(24, 0));
+        let mut vrc6b = VRC6::new(test_rom(26, 0));
+
+        // Both lines are set, so swapping them changes nothing.
+        vrc6a.cpu_write(0xB003, 0b0000_0100);
+        vrc6b.cpu_write(0xB003, 0b0000_0100);
+        assert_eq!(vrc6a.mirroring(), Mirroring::Horizontal);
+        assert_eq!(vrc6b.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn chr_2k_modes() {
+        let mut vrc = VRC6::new(test_rom(24, 0));
+
+        vrc.cpu_write(0xD000, 0x07);
+
+        // Without PPU A10, the same 1 KB bank shows up twice.
+        vrc.cpu_write(0xB003, 0b01);
+        assert_eq!(vrc.ppu_peek(0x0000), 0x07);
+        assert_eq!(vrc.ppu_peek(0x0400), 0x07);
+
+        // With it, the register picks a 2 KB bank.
+        vrc.cpu_write(0xB003, 0b0010_0001);
+        assert_eq!(vrc.ppu_peek(0x0000), 0x06);
+        assert_eq!(vrc.ppu_peek(0x0400), 0x07);
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut vrc = VRC6::new(test_rom(26, 0));
+
+        vrc.cpu_write(0x8000, 2);
+        vrc.cpu_write(0xC000, 9);
+        assert_eq!(vrc.cpu_peek(0x8000), Some(4));
+        assert_eq!(vrc.cpu_peek(0xA000), Some(5));
+        assert_eq!(vrc.cpu_peek(0xC000), Some(9));
+        assert_eq!(vrc.cpu_peek(0xE000), Some(15));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::utils::bits::get_bit;
+
+/// One step of any channel is about as loud as one step of an APU pulse channel.
+const LEVEL: f32 = 0.0098;
+
+/// Pulse channels have 8 duty cycles, and a mode that ignores duty altogether.
+#[derive(Default)]
+struct Pulse {
+    /// 7654 3210
+    ///
+    /// MDDD VVVV
+    ///
+    /// Mode (constant output), Duty, Volume
+    control: u8,
+    period: u16,
+    enabled: bool,
+    timer: u16,
+    step: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.control = value,
+            1 => self.period = (self.period & 0x0F00) | value as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | (((value & 0x0F) as u16) << 8);
+                self.enabled = get_bit(value, 7);
+
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = self.step.checked_sub(1).unwrap_or(15);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// 0-15
+    fn output(&self) -> u8 {
+        let duty = (self.control >> 4) & 0b111;
+        let constant = get_bit(self.control, 7);
+
+        if self.enabled && (constant || self.step <= duty) {
+            self.control & 0x0F
+        } else {
+            0
+        }
+    }
+}
+
+/// The sawtooth adds its rate to an accumulator every other clock, and resets after 7 additions.
+#[derive(Default)]
+struct Sawtooth {
+    rate: u8,
+    period: u16,
+    enabled: bool,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Sawtooth {
+    fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.rate = value & 0b0011_1111,
+            1 => self.period = (self.period & 0x0F00) | value as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | (((value & 0x0F) as u16) << 8);
+                self.enabled = get_bit(value, 7);
+
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period >> shift;
+        self.step += 1;
+
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step.is_multiple_of(2) {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    /// 0-31
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+/// VRC6's expansion audio: two pulse channels and a sawtooth.
+/// Refer to: https://www.nesdev.org/wiki/VRC6_audio
+#[derive(Default)]
+pub struct Audio {
+    pulses: [Pulse; 2],
+    sawtooth: Sawtooth,
+    halt: bool,
+    /// Divides every period by 16 or 256.
+    frequency_shift: u8,
+}
+
+impl Audio {
+    /// Takes register addresses as seen by the chip, after the A0/A1 swap on VRC6b.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0x9000..=0x9002 => self.pulses[0].write(register - 0x9000, value),
+            // H: halt, B: 16x frequency, A: 256x frequency, which takes precedence.
+            0x9003 => {
+                self.halt = get_bit(value, 0);
+                self.frequency_shift = if get_bit(value, 2) {
+                    8
+                } else if get_bit(value, 1) {
+                    4
+                } else {
+                    0
+                };
+            }
+            0xA000..=0xA002 => self.pulses[1].write(register - 0xA000, value),
+            0xB000..=0xB002 => self.sawtooth.write(register - 0xB000, value),
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.halt {
+            return;
+        }
+
+        self.pulses[0].clock(self.frequency_shift);
+        self.pulses[1].clock(self.frequency_shift);
+        self.sawtooth.clock(self.frequency_shift);
+    }
+
+    pub fn output(&self) -> f32 {
+        let sum = self.pulses[0].output() + self.pulses[1].output() + self.sawtooth.output();
+
+        sum as f32 * LEVEL
+    }
+}
@@ -0,0 +1,204 @@
+use super::{vrc_irq::VrcIrq, CartridgeMemory, Mapper, Mirroring};
+use crate::{rom::ROM, utils::bits::get_bit};
+
+mod opll;
+
+/// The FM channels are a lot louder than anything else, so they get scaled down to sit with the APU.
+const LEVEL: f32 = 0.12;
+
+/// Mapper 85: Konami's VRC7, with an FM synthesizer built in.
+///
+/// PRG-ROM is switched in three 8 KB banks, with the last one fixed. CHR is switched in eight 1 KB banks.
+/// Every register pair is told apart by a single address line: A4 on VRC7a (submapper 2), A3 on VRC7b (submapper 1).
+/// The exception is the audio ports, which are at $9010 and $9030 either way.
+/// Refer to: https://www.nesdev.org/wiki/VRC7
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC7 {
+    memory: CartridgeMemory,
+    /// Bitmask of the address line(s) selecting the second register in each pair.
+    select_line: u16,
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    /// 7654 3210
+    ///
+    /// WS.. ..MM
+    ///
+    /// PRG-RAM enable, Sound reset (silences the audio), Mirroring
+    control: u8,
+    irq: VrcIrq,
+    opll: opll::OPLL,
+    opll_divider: u8,
+}
+
+impl VRC7 {
+    pub fn new(rom: ROM) -> VRC7 {
+        // Without a submapper, both lines are wired up at once.
+        let select_line = match rom.info.submapper {
+            1 => 0x0008,
+            2 => 0x0010,
+            _ => 0x0018,
+        };
+
+        VRC7 {
+            select_line,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::default(),
+            opll: opll::OPLL::new(),
+            opll_divider: 0,
+            memory: CartridgeMemory::new(rom),
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        get_bit(self.control, 7)
+    }
+
+    fn prg_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x8000..=0xDFFF => self.prg_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.memory.prg_rom_banks(0x2000) - 1,
+        }
+    }
+}
+
+impl Mapper for VRC7 {
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut CartridgeMemory {
+        &mut self.memory
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.memory.prg_ram(0, 0x2000, addr),
+            0x8000..=0xFFFF => Some(self.memory.prg_rom(self.prg_bank(addr), 0x2000, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) {
+        if let 0x6000..=0x7FFF = addr {
+            if self.prg_ram_enabled() {
+                self.memory.write_prg_ram(0, 0x2000, addr, value);
+            }
+
+            return;
+        }
+
+        // The audio ports sit at $9010 and $9030 on both variants, so they're decoded before the select line.
+        match addr & 0xF030 {
+            0x9010 => return self.opll.select(value),
+            0x9030 => return self.opll.write(value),
+            _ => {}
+        }
+
+        let second = addr & self.select_line != 0;
+
+        match (addr & 0xF000, second) {
+            (0x8000, false) => self.prg_banks[0] = value & 0b0011_1111,
+            (0x8000, true) => self.prg_banks[1] = value & 0b0011_1111,
+            (0x9000, false) => self.prg_banks[2] = value & 0b0011_1111,
+            (base @ 0xA000..=0xDFFF, second) => {
+                self.chr_banks[(((base - 0xA000) >> 12) * 2 + second as u16) as usize] = value
+            }
+            (0xE000, false) => self.control = value,
+            (0xE000, true) => self.irq.write_latch(value),
+            (0xF000, false) => self.irq.write_control(value),
+            (0xF000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> u8 {
+        let bank = self.chr_banks[(addr >> 10) as usize & 0b111] as usize;
+
+        self.memory.chr(bank, 0x0400, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        let bank = self.chr_banks[(addr >> 10) as usize & 0b111] as usize;
+
+        self.memory.write_chr(bank, 0x0400, addr, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+
+        self.opll_divider += 1;
+
+        if self.opll_divider == opll::CPU_CYCLES_PER_SAMPLE {
+            self.opll_divider = 0;
+            self.opll.clock();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn audio_output(&self) -> f32 {
+        if get_bit(self.control, 6) {
+            return 0.0;
+        }
+
+        self.opll.output() * LEVEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::test_rom;
+
+    #[test]
+    fn submappers_pick_their_select_line() {
+        // Submapper, and the CPU address of the second PRG bank register.
+        for (submapper, addr) in [(1, 0x8008), (2, 0x8010), (0, 0x8008), (0, 0x8010)] {
+            let mut vrc = VRC7::new(test_rom(85, submapper));
+
+            vrc.cpu_write(0x8000, 3);
+            vrc.cpu_write(addr, 5);
+
+            assert_eq!(vrc.cpu_peek(0x8000), Some(3), "{submapper}");
+            assert_eq!(vrc.cpu_peek(0xA000), Some(5), "{submapper}");
+        }
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut vrc = VRC7::new(test_rom(85, 1));
+
+        vrc.cpu_write(0xA000, 0x10);
+        vrc.cpu_write(0xA008, 0x11);
+        vrc.cpu_write(0xD008, 0x17);
+
+        assert_eq!(vrc.ppu_peek(0x0000), 0x10);
+        assert_eq!(vrc.ppu_peek(0x0400), 0x11);
+        assert_eq!(vrc.ppu_peek(0x1C00), 0x17);
+    }
+
+    #[test]
+    fn audio_ports_are_not_prg_banks() {
+        // $9010 and $9030 have the VRC7b select line clear, but are still the audio ports.
+        let mut vrc = VRC7::new(test_rom(85, 1));
+
+        vrc.cpu_write(0x9000, 4);
+        vrc.cpu_write(0x9010, 0x30);
+        vrc.cpu_write(0x9030, 0x10);
+
+        assert_eq!(vrc.cpu_peek(0xC000), Some(4));
+    }
+}
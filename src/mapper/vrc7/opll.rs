@@ -0,0 +1,455 @@
+// VRC7's sound core is a cut down YM2413 (OPLL), with 6 FM channels instead of 9 and no rhythm mode.
+// This isn't a bit-exact emulation of the chip's log-sin and exponent tables,
+// but the phase, envelope and LFO logic follows the hardware closely.
+// Refer to: https://www.nesdev.org/wiki/VRC7_audio
+
+use std::f32::consts::TAU;
+
+use crate::utils::bits::get_bit;
+
+/// The chip runs on its own 3.58 MHz crystal, and takes 72 of its cycles per sample.
+/// That's exactly 36 NTSC CPU cycles.
+pub const CPU_CYCLES_PER_SAMPLE: u8 = 36;
+
+const CHANNELS: usize = 6;
+
+/// VRC7's built-in instruments. Instrument 0 is the custom one, set through registers $00-$07.
+/// Dumped from the chip by Nuke.YKT.
+const INSTRUMENTS: [[u8; 8]; 16] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+/// Frequency multipliers, doubled so that 1/2 fits in an integer.
+const MULTIPLIERS: [u32; 16] = [1, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 20, 24, 24, 30, 30];
+
+/// Key scale level attenuation in dB, indexed by the upper 4 bits of the F-number. Applies to block 7.
+const KEY_SCALE_LEVELS: [f32; 16] = [
+    0.0, 9.0, 12.0, 13.875, 15.0, 16.125, 16.875, 17.625, 18.0, 18.75, 19.125, 19.5, 19.875, 20.25,
+    20.625, 21.0,
+];
+
+/// Vibrato offsets to the F-number, indexed by its upper 3 bits and the LFO position.
+const VIBRATO: [[i8; 8]; 8] = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 1, 0, 0, 0, -1, 0],
+    [0, 1, 2, 1, 0, -1, -2, -1],
+    [0, 1, 3, 1, 0, -1, -3, -1],
+    [0, 2, 4, 2, 0, -2, -4, -2],
+    [0, 2, 5, 2, 0, -2, -5, -2],
+    [0, 3, 6, 3, 0, -3, -6, -3],
+    [0, 3, 7, 3, 0, -3, -7, -3],
+];
+
+/// The vibrato LFO moves to its next position every 1024 samples (~6.1 Hz for the whole cycle).
+const VIBRATO_PERIOD: u32 = 1024;
+
+/// The tremolo LFO is a triangle wave at ~3.7 Hz, up to 4.8 dB deep.
+const TREMOLO_PERIOD: u32 = 13432;
+const TREMOLO_DEPTH: f32 = 4.8;
+
+/// The envelope generator works in 10-bit attenuation, in units of 0.09375 dB (96 dB full scale).
+const MAX_ATTENUATION: u16 = 0x3FF;
+const ATTENUATION_STEP_DB: f32 = 0.093_75;
+
+/// Envelope increments, 8 per rate. Which one applies depends on the envelope counter.
+const ENVELOPE_INCREMENTS: [u32; 16] = [
+    0x0000_0000,
+    0x0000_0000,
+    0x1010_1010,
+    0x1010_1010,
+    0x1010_1010,
+    0x1010_1010,
+    0x1110_1110,
+    0x1110_1110,
+    0x1010_1010,
+    0x1011_1010,
+    0x1110_1110,
+    0x1111_1110,
+    0x1111_1111,
+    0x2111_2111,
+    0x2121_2121,
+    0x2221_2221,
+];
+
+/// Rates 48 and up double their increments every 4 rates.
+fn envelope_increment(rate: u8, index: u32) -> u16 {
+    let pattern = match rate {
+        0..=7 => ENVELOPE_INCREMENTS[rate as usize],
+        8..=47 => ENVELOPE_INCREMENTS[8 + (rate % 4) as usize],
+        48..=59 => ENVELOPE_INCREMENTS[12 + (rate % 4) as usize] << (rate / 4 - 12),
+        _ => 0x8888_8888,
+    };
+
+    ((pattern >> (4 * index)) & 0xF) as u16
+}
+
+/// The two operators of a channel share one patch.
+#[derive(Clone, Copy, Default)]
+struct Patch {
+    tremolo: bool,
+    vibrato: bool,
+    /// Sustained tones hold at the sustain level, percussive ones keep decaying.
+    sustained: bool,
+    key_scale_rate: bool,
+    multiplier: u8,
+    key_scale_level: u8,
+    /// Half-wave rectified sine, instead of a full one.
+    rectified: bool,
+    attack_rate: u8,
+    decay_rate: u8,
+    sustain_level: u8,
+    release_rate: u8,
+}
+
+impl Patch {
+    /// `operator` is 0 for the modulator, 1 for the carrier.
+    fn decode(instrument: &[u8; 8], operator: usize) -> Patch {
+        let flags = instrument[operator];
+
+        Patch {
+            tremolo: get_bit(flags, 7),
+            vibrato: get_bit(flags, 6),
+            sustained: get_bit(flags, 5),
+            key_scale_rate: get_bit(flags, 4),
+            multiplier: flags & 0x0F,
+            key_scale_level: instrument[2 + operator] >> 6,
+            rectified: get_bit(instrument[3], 3 + operator as u8),
+            attack_rate: instrument[4 + operator] >> 4,
+            decay_rate: instrument[4 + operator] & 0x0F,
+            sustain_level: instrument[6 + operator] >> 4,
+            release_rate: instrument[6 + operator] & 0x0F,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Off,
+}
+
+#[derive(Clone, Copy)]
+struct Operator {
+    /// 20 bits make one full cycle.
+    phase: u32,
+    state: EnvelopeState,
+    attenuation: u16,
+    /// The last two outputs, for feedback.
+    outputs: [f32; 2],
+}
+
+impl Default for Operator {
+    fn default() -> Self {
+        Operator {
+            phase: 0,
+            state: EnvelopeState::Off,
+            attenuation: MAX_ATTENUATION,
+            outputs: [0.0; 2],
+        }
+    }
+}
+
+impl Operator {
+    fn key_on(&mut self) {
+        self.phase = 0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        if self.state != EnvelopeState::Off {
+            self.state = EnvelopeState::Release;
+        }
+    }
+
+    fn clock_envelope(&mut self, patch: &Patch, sustain: bool, key_scale: u8, counter: u32) {
+        let base_rate = match self.state {
+            EnvelopeState::Attack => patch.attack_rate,
+            EnvelopeState::Decay => patch.decay_rate,
+            EnvelopeState::Sustain if patch.sustained => 0,
+            EnvelopeState::Sustain => patch.release_rate,
+            EnvelopeState::Release if sustain => 5,
+            EnvelopeState::Release if patch.sustained => patch.release_rate,
+            EnvelopeState::Release => 7,
+            EnvelopeState::Off => return,
+        };
+
+        let rate = if base_rate == 0 {
+            0
+        } else {
+            (base_rate * 4 + key_scale).min(63)
+        };
+
+        // Steps only happen when the counter lines up with the rate.
+        let shift = (rate / 4) as u32;
+        let counter = counter << shift;
+
+        if counter & 0x7FF != 0 {
+            return;
+        }
+
+        let index = (counter >> shift.max(11)) & 0b111;
+        let increment = envelope_increment(rate, index);
+
+        match self.state {
+            EnvelopeState::Attack => {
+                if rate >= 62 {
+                    self.attenuation = 0;
+                } else if increment > 0 {
+                    // The attack curve is exponential, it slows down as it approaches full volume.
+                    let step = ((self.attenuation as u32 + 1) * increment as u32).div_ceil(16);
+
+                    self.attenuation -= step.min(self.attenuation as u32) as u16;
+                }
+
+                if self.attenuation == 0 {
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.attenuation = (self.attenuation + increment).min(MAX_ATTENUATION);
+
+                // Sustain levels are in 3 dB steps.
+                if self.attenuation >= (patch.sustain_level as u16) << 5 {
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            _ => {
+                self.attenuation = (self.attenuation + increment).min(MAX_ATTENUATION);
+
+                if self.attenuation == MAX_ATTENUATION && self.state == EnvelopeState::Release {
+                    self.state = EnvelopeState::Off;
+                }
+            }
+        }
+    }
+
+    /// -1.0 to 1.0. `modulation` is a phase offset, in cycles.
+    fn output(&self, patch: &Patch, modulation: f32, attenuation_db: f32) -> f32 {
+        if self.state == EnvelopeState::Off {
+            return 0.0;
+        }
+
+        let phase = self.phase as f32 / (1 << 20) as f32 + modulation;
+        let wave = (phase * TAU).sin();
+
+        if patch.rectified && wave < 0.0 {
+            return 0.0;
+        }
+
+        let attenuation = self.attenuation as f32 * ATTENUATION_STEP_DB + attenuation_db;
+
+        wave * 10f32.powf(-attenuation / 20.0)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Channel {
+    f_number: u16,
+    block: u8,
+    sustain: bool,
+    key_on: bool,
+    instrument: u8,
+    /// 3 dB steps of attenuation.
+    volume: u8,
+    operators: [Operator; 2],
+}
+
+impl Channel {
+    fn set_key(&mut self, key_on: bool) {
+        if key_on && !self.key_on {
+            self.operators.iter_mut().for_each(Operator::key_on);
+        } else if !key_on && self.key_on {
+            self.operators.iter_mut().for_each(Operator::key_off);
+        }
+
+        self.key_on = key_on;
+    }
+
+    /// Higher notes get faster envelopes, and with key scale rate set, a lot faster.
+    fn key_scale(&self, patch: &Patch) -> u8 {
+        let key_scale = (self.block << 1) | (self.f_number >> 8) as u8;
+
+        if patch.key_scale_rate {
+            key_scale
+        } else {
+            key_scale >> 2
+        }
+    }
+
+    /// Higher notes get quieter, at 1.5, 3 or 6 dB per octave.
+    fn key_scale_attenuation(&self, patch: &Patch) -> f32 {
+        if patch.key_scale_level == 0 {
+            return 0.0;
+        }
+
+        let level = KEY_SCALE_LEVELS[(self.f_number >> 5) as usize] - 3.0 * (7 - self.block) as f32;
+
+        level.max(0.0) / (1 << (3 - patch.key_scale_level)) as f32
+    }
+}
+
+/// The sound core, generating a sample every 36 CPU cycles.
+#[allow(clippy::upper_case_acronyms)]
+pub struct OPLL {
+    address: u8,
+    custom_instrument: [u8; 8],
+    channels: [Channel; CHANNELS],
+    sample_counter: u32,
+    output: f32,
+}
+
+impl Default for OPLL {
+    fn default() -> Self {
+        OPLL::new()
+    }
+}
+
+impl OPLL {
+    pub fn new() -> OPLL {
+        OPLL {
+            address: 0,
+            custom_instrument: [0; 8],
+            channels: [Channel::default(); CHANNELS],
+            sample_counter: 0,
+            output: 0.0,
+        }
+    }
+
+    pub fn select(&mut self, address: u8) {
+        self.address = address;
+    }
+
+    pub fn write(&mut self, value: u8) {
+        let channel = (self.address & 0x0F) as usize;
+
+        match self.address {
+            0x00..=0x07 => self.custom_instrument[self.address as usize] = value,
+            0x10..=0x15 => {
+                self.channels[channel].f_number =
+                    (self.channels[channel].f_number & 0x100) | value as u16
+            }
+            0x20..=0x25 => {
+                let channel = &mut self.channels[channel];
+
+                channel.f_number = (channel.f_number & 0xFF) | (((value & 1) as u16) << 8);
+                channel.block = (value >> 1) & 0b111;
+                channel.sustain = get_bit(value, 5);
+                channel.set_key(get_bit(value, 4));
+            }
+            0x30..=0x35 => {
+                self.channels[channel].instrument = value >> 4;
+                self.channels[channel].volume = value & 0x0F;
+            }
+            _ => {}
+        }
+    }
+
+    fn instrument(&self, index: u8) -> &[u8; 8] {
+        if index == 0 {
+            &self.custom_instrument
+        } else {
+            &INSTRUMENTS[index as usize]
+        }
+    }
+
+    /// Runs the chip for one sample.
+    pub fn clock(&mut self) {
+        self.sample_counter = self.sample_counter.wrapping_add(1);
+
+        let vibrato_position = ((self.sample_counter / VIBRATO_PERIOD) % 8) as usize;
+
+        let tremolo_position =
+            (self.sample_counter % TREMOLO_PERIOD) as f32 / TREMOLO_PERIOD as f32;
+        let tremolo = (1.0 - (tremolo_position * 2.0 - 1.0).abs()) * TREMOLO_DEPTH;
+
+        let mut output = 0.0;
+
+        for index in 0..CHANNELS {
+            let channel = self.channels[index];
+            let instrument = *self.instrument(channel.instrument);
+            let feedback = instrument[3] & 0b111;
+            let patches = [Patch::decode(&instrument, 0), Patch::decode(&instrument, 1)];
+
+            let mut operator_outputs = [0.0; 2];
+
+            for (operator_index, patch) in patches.iter().enumerate() {
+                let operator = &channel.operators[operator_index];
+
+                let mut attenuation = channel.key_scale_attenuation(patch);
+
+                if patch.tremolo {
+                    attenuation += tremolo;
+                }
+
+                attenuation += if operator_index == 0 {
+                    // The modulator's total level is in 0.75 dB steps.
+                    (instrument[2] & 0b0011_1111) as f32 * 0.75
+                } else {
+                    channel.volume as f32 * 3.0
+                };
+
+                let modulation = if operator_index == 0 {
+                    if feedback == 0 {
+                        0.0
+                    } else {
+                        (operator.outputs[0] + operator.outputs[1]) * 2f32.powi(feedback as i32 - 7)
+                    }
+                } else {
+                    // A full-scale modulator shifts the carrier's phase by up to 4 cycles.
+                    operator_outputs[0] * 4.0
+                };
+
+                operator_outputs[operator_index] = operator.output(patch, modulation, attenuation);
+            }
+
+            output += operator_outputs[1];
+
+            let channel = &mut self.channels[index];
+
+            for (operator_index, patch) in patches.iter().enumerate() {
+                let key_scale = channel.key_scale(patch);
+                let f_number = if patch.vibrato {
+                    let offset = VIBRATO[(channel.f_number >> 6) as usize][vibrato_position];
+
+                    channel.f_number.wrapping_add_signed(offset as i16) & 0x1FF
+                } else {
+                    channel.f_number
+                };
+
+                let operator = &mut channel.operators[operator_index];
+
+                operator.outputs = [operator.outputs[1], operator_outputs[operator_index]];
+                operator.phase = (operator.phase
+                    + ((f_number as u32) << channel.block)
+                        * MULTIPLIERS[patch.multiplier as usize])
+                    & 0xF_FFFF;
+                operator.clock_envelope(patch, channel.sustain, key_scale, self.sample_counter);
+            }
+        }
+
+        self.output = output;
+    }
+
+    /// Sum of all channels, -6.0 to 6.0.
+    pub fn output(&self) -> f32 {
+        self.output
+    }
+}
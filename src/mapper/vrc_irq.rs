@@ -0,0 +1,89 @@
+use crate::utils::bits::get_bit;
+
+/// The prescaler approximates scanlines in CPU cycles, by counting down 341 PPU dots 3 at a time.
+const PRESCALER_PERIOD: i16 = 341;
+
+/// The IRQ counter Konami put in VRC4, VRC6 and VRC7.
+///
+/// It counts up from a latched value, and raises an IRQ when it overflows.
+/// In scanline mode, it gets clocked about once per scanline (every 113 2/3 CPU cycles).
+/// In cycle mode, it gets clocked every CPU cycle.
+/// Refer to: https://www.nesdev.org/wiki/VRC_IRQ
+#[derive(Default)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    /// VRC4 splits the latch into two nibbles.
+    pub fn write_latch_low(&mut self, value: u8) {
+        self.latch = (self.latch & 0xF0) | (value & 0x0F);
+    }
+
+    pub fn write_latch_high(&mut self, value: u8) {
+        self.latch = (self.latch & 0x0F) | (value << 4);
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /// 7654 3210
+    ///
+    /// .... .MEA
+    ///
+    /// Mode (1 for cycles), Enable, enable After acknowledgement
+    pub fn write_control(&mut self, value: u8) {
+        self.enable_after_ack = get_bit(value, 0);
+        self.enabled = get_bit(value, 1);
+        self.cycle_mode = get_bit(value, 2);
+        self.pending = false;
+
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enable_after_ack;
+    }
+
+    /// Called once per CPU cycle.
+    pub fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.cycle_mode {
+            self.clock_counter();
+        } else {
+            self.prescaler -= 3;
+
+            if self.prescaler <= 0 {
+                self.prescaler += PRESCALER_PERIOD;
+                self.clock_counter();
+            }
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.pending
+    }
+}
@@ -1,38 +1,507 @@
-use crate::cpu::CPU;
+use std::io;
 
+use crate::{
+    apu::APU,
+    bus::Bus,
+    controller::Controller,
+    mapper::Mapper,
+    ppu::PPU,
+    save::{SaveError, SaveFile},
+};
+
+/// The NES CPU memory map:
+///
+/// 0x0000-0x07FF: 2 KB internal RAM, mirrored up to 0x1FFF
+///
+/// 0x2000-0x2007: PPU registers, mirrored every 8 bytes up to 0x3FFF
+///
+/// 0x4000-0x4017: APU and I/O registers
+///
+/// 0x4018-0x401F: APU and I/O test functionality, normally disabled
+///
+/// 0x4020-0xFFFF: Cartridge space, handled by the mapper:
+///
+/// * 0x4020-0x5FFF: Expansion area, used by some cartridges
+///
+/// * 0x6000-0x7FFF: PRG-RAM (if present on the cartridge)
+///
+/// * 0x8000-0xFFFF: PRG-ROM and mapper registers
 pub struct Memory {
     internal_ram: Box<[u8; 0x0800]>, // 2 KB of internal RAM
+    pub ppu: PPU,
+    /// Page written to $4014, to be copied into OAM once the CPU halts.
+    oam_dma_page: Option<u8>,
+    pub apu: APU,
+    pub controllers: [Controller; 2],
+    /// Without a cartridge inserted, the whole cartridge space is open bus.
+    mapper: Option<Box<dyn Mapper>>,
+    /// The last value seen on the data bus. Reads of unmapped addresses return whatever is left on it.
+    open_bus: u8,
+    /// Where the cartridge's battery-backed memory gets kept, if it has any.
+    save_file: Option<SaveFile>,
+    /// The address of the last read. A DMA halting the CPU on it repeats it.
+    last_read: u16,
+    /// Writes since the last read. The CPU can't be halted while it's writing.
+    writes_since_read: usize,
+    /// How many cycles of the current instruction were left when the DMC asked for a byte.
+    dmc_request_cycles_left: Option<usize>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::new()
+    }
 }
 
 impl Memory {
     pub fn new() -> Memory {
         Memory {
             internal_ram: Box::new([0u8; 0x0800]), // Internal memory does not have a reliable state at startup. Opting to zero it out.
+            ppu: PPU::new(),
+            oam_dma_page: None,
+            apu: APU::new(),
+            controllers: [Controller::new(), Controller::new()],
+            mapper: None,
+            open_bus: 0,
+            save_file: None,
+            last_read: 0,
+            writes_since_read: 0,
+            dmc_request_cycles_left: None,
+        }
+    }
+
+    pub fn insert_cartridge(&mut self, mapper: Box<dyn Mapper>) {
+        self.mapper = Some(mapper);
+    }
+
+    /// Loads the cartridge's battery-backed memory from `save_file`, and keeps it there from now on.
+    /// Nothing gets written until [Memory::save] is called.
+    pub fn attach_save_file(&mut self, save_file: SaveFile) -> Result<(), SaveError> {
+        if let Some(mapper) = &mut self.mapper {
+            save_file.load(mapper.as_mut())?;
+            mapper.memory_mut().battery_ram_dirty = false;
+        }
+
+        self.save_file = Some(save_file);
+
+        Ok(())
+    }
+
+    /// Writes battery-backed memory out to the save file, if it changed since the last time.
+    /// It's up to the frontend to call this every now and then, and once more before quitting.
+    pub fn save(&mut self) -> io::Result<()> {
+        let (Some(save_file), Some(mapper)) = (&self.save_file, &mut self.mapper) else {
+            return Ok(());
+        };
+
+        if mapper.memory().battery_ram_dirty {
+            save_file.save(mapper.as_ref())?;
+            mapper.memory_mut().battery_ram_dirty = false;
         }
+
+        Ok(())
+    }
+}
+
+/// The DMA unit reads on odd cycles and writes on even ones.
+fn is_get_cycle(cycle: usize) -> bool {
+    cycle % 2 == 1
+}
+
+impl Memory {
+    fn dma_tick(&mut self, cycle: &mut usize) {
+        self.tick(1);
+        *cycle += 1;
     }
 
-    pub fn fetch(&self, addr: u16) -> u8 {
-        // Okay, so a little bit of explanation.
-        // 6502 or the NES memory map uses addresses from 0x0000 to 0x07FF to address the 2 KB of the internal RAM.
-        // Additionally, there are also three mirrors of these addresses, up to 0x2000.
-        // What we do here, is we find a remainder of 0x0800 (2 KB) and use it to fetch a value from the memory.
-        // Casting to usize is required to index the slice - such an operation should not be lossy.
-        // (Unless you're running on an 8 bit address space target, in which case you have much larger issues.)
-        // Note that we COULD get the value in a much more concise way using a slice, however a more explicit error is very welcome.
-        if addr < 0x2000 {
-            return *self.internal_ram.get((addr % 0x0800) as usize).expect("Tried fetching an address larger than 0x0800, despite the address being the remainder of 0x0800.");
+    /// Reads the sample byte the DMC asked for, if it still wants one.
+    fn fetch_dmc_sample(&mut self) {
+        if let Some(addr) = self.apu.dmc_dma_request() {
+            let value = self.read(addr);
+
+            self.apu.finish_dmc_dma(value);
+            self.dmc_request_cycles_left = None;
         }
+    }
+
+    /// DMC DMA on its own: a halt cycle, a dummy cycle, an alignment cycle if needed, and the read.
+    /// That stalls the CPU for 3-4 cycles, depending on where the halt lands.
+    ///
+    /// The DMC can ask for a byte in the middle of an instruction, but DMA only gets run after it.
+    /// So where the halt would've landed is worked out from how many cycles were left when the DMC asked,
+    /// and what the CPU was doing in them. It can only halt the CPU on a read, so if the rest of the instruction
+    /// was writes (say, an RMW instruction or JSR), it waits for the next opcode fetch.
+    /// Otherwise the rest of the instruction waits for the DMA, which comes down to the same stall.
+    ///
+    /// This is an approximation, since the CPU doesn't let the bus catch up after every access:
+    ///
+    /// * Writes are assumed to be the last cycles of the instruction, which holds for everything but BRK.
+    ///
+    /// * Only the instruction's last read can be repeated by the halt. Dummy reads aren't made at all,
+    ///   so e.g. the page crossing re-read of `LDA $4016,X` never clocks the controller twice.
+    ///
+    /// * A halt landing on an earlier read gets the stall right, but still repeats the last read instead.
+    fn run_dmc_dma(&mut self, cycle: usize) -> usize {
+        let cycles_left = self.dmc_request_cycles_left.unwrap_or(0);
+
+        let halt = if cycles_left <= self.writes_since_read {
+            cycle
+        } else {
+            self.repeat_halted_read(cycles_left);
+            cycle - cycles_left
+        };
+
+        // Halt and dummy cycles, then waiting for a get cycle.
+        let mut get = halt + 2;
+
+        if !is_get_cycle(get) {
+            get += 1;
+        }
+
+        self.tick(get - halt);
+        self.fetch_dmc_sample();
+        self.tick(1);
 
-        panic!("Tried fetching outside of internal RAM")
+        get + 1 - halt
     }
 
-    pub fn write(&mut self, addr: u16, value: u8) {
-        if addr < 0x2000 {
-            let mem_ref = self.internal_ram.get_mut((addr % 0x0800) as usize).expect("Tried writing to an address larger than 0x0800, despite the address being the remainder of 0x0800.");
-            *mem_ref = value;
+    /// A halted CPU keeps repeating the read it was halted on, until the DMA is over.
+    /// Only the instruction's last read is known, so that's the only one that gets repeated.
+    /// The controllers see the repeated reads as one more read, and lose a bit.
+    /// Their /OE line stays low the whole time, so it's only ever the one.
+    fn repeat_halted_read(&mut self, cycles_left: usize) {
+        if cycles_left != self.writes_since_read + 1 {
+            return;
         }
 
-        assert_eq!(self.fetch(addr), value);
-        panic!("Tried writing outside of internal RAM");
+        match self.last_read {
+            0x4016 => {
+                self.controllers[0].read();
+            }
+            0x4017 => {
+                self.controllers[1].read();
+            }
+            _ => {}
+        }
+    }
+
+    /// OAM DMA copies a whole page ($XX00-$XXFF) to OAMDATA, one read and one write per byte.
+    /// It only ever halts the CPU on the cycle after the write to $4014, then lines up with a get cycle.
+    ///
+    /// If the DMC asks for a byte in the meantime, it takes the next get cycle, and OAM DMA takes an extra one
+    /// to realign, for a 2 cycle stall. At the very end, it's different:
+    /// if the DMC asks during the last read, the DMA unit is still running, so it gets the cycle right after (1).
+    /// If it asks during the last write, OAM DMA has already let go, so it goes through its own halt and dummy cycles (3).
+    fn run_oam_dma(&mut self, page: u8, cycle: usize) -> usize {
+        let start = cycle;
+        let mut cycle = cycle;
+
+        self.dma_tick(&mut cycle);
+
+        let mut dmc_asked_during_last_read = false;
+
+        for low in 0..=0xFF {
+            while !is_get_cycle(cycle) || self.apu.dmc_dma_request().is_some() {
+                if is_get_cycle(cycle) {
+                    self.fetch_dmc_sample();
+                }
+
+                self.dma_tick(&mut cycle);
+            }
+
+            let value = self.read(u16::from_le_bytes([low, page]));
+            self.dma_tick(&mut cycle);
+
+            dmc_asked_during_last_read = self.apu.dmc_dma_request().is_some();
+
+            self.write(0x2004, value);
+            self.dma_tick(&mut cycle);
+        }
+
+        if dmc_asked_during_last_read {
+            self.fetch_dmc_sample();
+            self.dma_tick(&mut cycle);
+        } else if self.apu.dmc_dma_request().is_some() {
+            // Halt and dummy cycles, which leave it on a get cycle.
+            self.dma_tick(&mut cycle);
+            self.dma_tick(&mut cycle);
+
+            self.fetch_dmc_sample();
+            self.dma_tick(&mut cycle);
+        }
+
+        cycle - start
+    }
+}
+
+impl Bus for Memory {
+    fn read(&mut self, addr: u16) -> u8 {
+        let value = match addr {
+            0x2000..=0x3FFF => self.ppu.read_register(&mut self.mapper, addr),
+            0x4015 => (self.open_bus & 0b0010_0000) | self.apu.read_status(),
+            // Reading the controllers shifts them, and only drives the lowest bits.
+            0x4016 => (self.open_bus & 0b1110_0000) | self.controllers[0].read(),
+            0x4017 => (self.open_bus & 0b1110_0000) | self.controllers[1].read(),
+            0x4020..=0xFFFF => match &mut self.mapper {
+                Some(mapper) => mapper.cpu_read(addr).unwrap_or(self.open_bus),
+                None => self.open_bus,
+            },
+            _ => self.peek(addr),
+        };
+
+        self.open_bus = value;
+        self.last_read = addr;
+        self.writes_since_read = 0;
+
+        value
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => {
+                // Okay, so a little bit of explanation.
+                // 6502 or the NES memory map uses addresses from 0x0000 to 0x07FF to address the 2 KB of the internal RAM.
+                // Additionally, there are also three mirrors of these addresses, up to 0x2000.
+                // What we do here, is we find a remainder of 0x0800 (2 KB) and use it to fetch a value from the memory.
+                // Casting to usize is required to index the slice - such an operation should not be lossy.
+                // (Unless you're running on an 8 bit address space target, in which case you have much larger issues.)
+                // Note that we COULD get the value in a much more concise way using a slice, however a more explicit error is very welcome.
+                *self.internal_ram.get((addr % 0x0800) as usize).expect("Tried fetching an address larger than 0x0800, despite the address being the remainder of 0x0800.")
+            }
+            0x2000..=0x3FFF => self.ppu.peek_register(addr),
+            // Bit 5 of APU status isn't driven.
+            0x4015 => (self.open_bus & 0b0010_0000) | self.apu.peek_status(),
+            0x4016 => (self.open_bus & 0b1110_0000) | self.controllers[0].peek(),
+            0x4017 => (self.open_bus & 0b1110_0000) | self.controllers[1].peek(),
+            // The rest of the APU registers are write-only, and the test registers are disabled.
+            0x4000..=0x401F => self.open_bus,
+            0x4020..=0xFFFF => self
+                .mapper
+                .as_ref()
+                .and_then(|mapper| mapper.cpu_peek(addr))
+                .unwrap_or(self.open_bus),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.open_bus = value;
+        self.writes_since_read += 1;
+
+        match addr {
+            0x0000..=0x1FFF => {
+                let mem_ref = self.internal_ram.get_mut((addr % 0x0800) as usize).expect("Tried writing to an address larger than 0x0800, despite the address being the remainder of 0x0800.");
+                *mem_ref = value;
+            }
+            0x2000..=0x3FFF => self.ppu.write_register(&mut self.mapper, addr, value),
+            0x4014 => self.oam_dma_page = Some(value),
+            // Both controllers share the strobe line.
+            0x4016 => {
+                self.controllers[0].write(value);
+                self.controllers[1].write(value);
+            }
+            0x4000..=0x4017 => self.apu.write(addr, value),
+            0x4018..=0x401F => {}
+            0x4020..=0xFFFF => {
+                if let Some(mapper) = &mut self.mapper {
+                    mapper.cpu_write(addr, value);
+                }
+            }
+        }
+
+        // The cartridge sees every write on the bus, not just the ones to its own address space.
+        if addr < 0x4020 {
+            if let Some(mapper) = &mut self.mapper {
+                mapper.cpu_snoop(addr, value);
+            }
+        }
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        // The PPU runs 3 dots for every CPU cycle.
+        for cycle in 0..cycles {
+            let expansion_audio = match &mut self.mapper {
+                Some(mapper) => {
+                    mapper.cpu_clock();
+                    mapper.audio_output()
+                }
+                None => 0.0,
+            };
+
+            self.apu.tick(expansion_audio);
+
+            if self.dmc_request_cycles_left.is_none() && self.apu.dmc_dma_request().is_some() {
+                self.dmc_request_cycles_left = Some(cycles - 1 - cycle);
+            }
+
+            for _ in 0..3 {
+                self.ppu.tick(&mut self.mapper);
+            }
+        }
+    }
+
+    /// Runs whichever DMA got started: OAM DMA after a write to $4014, DMC DMA when the DMC needs a sample byte.
+    /// Either one halts the CPU, and the DMA unit alternates between get (read) and put (write) cycles.
+    /// Refer to: https://www.nesdev.org/wiki/DMA
+    fn run_dma(&mut self, cycle: usize) -> usize {
+        match self.oam_dma_page.take() {
+            Some(page) => self.run_oam_dma(page, cycle),
+            None if self.apu.dmc_dma_request().is_some() => self.run_dmc_dma(cycle),
+            None => 0,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.apu.irq() || self.mapper.as_ref().is_some_and(|mapper| mapper.irq())
+    }
+
+    fn nmi(&self) -> bool {
+        self.ppu.nmi()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{controller::Button, mapper::test_rom};
+
+    fn memory() -> Memory {
+        let mut memory = Memory::new();
+        memory.insert_cartridge(crate::mapper::create(test_rom(0, 0)).unwrap());
+
+        memory
+    }
+
+    /// Starts the DMC on a 17 byte sample, with its first byte already fetched.
+    fn start_dmc(memory: &mut Memory) {
+        memory.write(0x4010, 0x0F);
+        memory.write(0x4013, 0x01);
+        memory.write(0x4015, 0x10);
+        memory.fetch_dmc_sample();
+    }
+
+    /// The DMC asking for a byte `cycles_left` cycles before the end of the instruction.
+    fn dmc_request(memory: &mut Memory, cycles_left: usize) {
+        memory.write(0x4013, 0x00);
+        memory.write(0x4015, 0x10);
+        memory.dmc_request_cycles_left = Some(cycles_left);
+    }
+
+    #[test]
+    fn dmc_dma_stalls_3_or_4_cycles() {
+        // Halting on a put cycle needs an extra one to line up with a get cycle.
+        for (cycle, stall) in [(100, 4), (101, 3)] {
+            let mut memory = memory();
+            dmc_request(&mut memory, 0);
+            memory.read(0x8000);
+
+            assert_eq!(memory.run_dma(cycle), stall);
+            assert_eq!(memory.apu.dmc_dma_request(), None);
+        }
+    }
+
+    #[test]
+    fn dmc_dma_halts_mid_instruction_on_reads() {
+        // Halting 2 cycles before the end of the instruction, on cycle 99 or 98.
+        for (cycle, stall) in [(101, 3), (100, 4)] {
+            let mut memory = memory();
+            dmc_request(&mut memory, 2);
+            memory.read(0x8000);
+
+            assert_eq!(memory.run_dma(cycle), stall);
+        }
+    }
+
+    #[test]
+    fn dmc_dma_waits_for_writes() {
+        // Like the last two cycles of INC: the halt has to wait for the next opcode fetch.
+        for (cycle, stall) in [(100, 4), (101, 3)] {
+            let mut memory = memory();
+            dmc_request(&mut memory, 2);
+            memory.read(0x0010);
+            memory.write(0x0010, 0);
+            memory.write(0x0010, 1);
+
+            assert_eq!(memory.run_dma(cycle), stall);
+        }
+    }
+
+    #[test]
+    fn dmc_dma_repeats_a_controller_read() {
+        let mut memory = memory();
+        memory.controllers[0].set_button(Button::B, true);
+        memory.write(0x4016, 1);
+        memory.write(0x4016, 0);
+
+        // Halted on the last cycle of LDA $4016, the read gets repeated and B gets lost.
+        dmc_request(&mut memory, 1);
+        assert_eq!(memory.read(0x4016) & 1, 0);
+        memory.run_dma(100);
+        assert_eq!(memory.read(0x4016) & 1, 0);
+
+        // Without the DMA, B would've been next.
+        let mut memory = self::memory();
+        memory.controllers[0].set_button(Button::B, true);
+        memory.write(0x4016, 1);
+        memory.write(0x4016, 0);
+
+        assert_eq!(memory.read(0x4016) & 1, 0);
+        assert_eq!(memory.read(0x4016) & 1, 1);
+    }
+
+    #[test]
+    fn dmc_dma_during_oam_dma() {
+        // How long the DMC takes to ask for its second byte.
+        let mut memory = memory();
+        start_dmc(&mut memory);
+
+        let mut wait = 0;
+
+        while memory.apu.dmc_dma_request().is_none() {
+            memory.tick(1);
+            wait += 1;
+        }
+
+        // OAM DMA cycle the DMC asks on, and how much longer than 513 cycles it takes.
+        // Cycle 1 is the halt, then reads and writes alternate, and cycle 512 is the last read.
+        for (asks_on, extra) in [(100, 2), (511, 2), (512, 1), (513, 3)] {
+            let mut memory = self::memory();
+            start_dmc(&mut memory);
+            memory.tick(wait - asks_on);
+
+            memory.write(0x4014, 0x02);
+            assert_eq!(memory.run_dma(0), 513 + extra, "{asks_on}");
+            assert_eq!(memory.apu.dmc_dma_request(), None, "{asks_on}");
+        }
+    }
+
+    #[test]
+    fn oam_dma_takes_513_or_514_cycles() {
+        // One more to line up with a get cycle when the halt lands on a put cycle.
+        for (cycle, stall) in [(100, 513), (101, 514)] {
+            let mut memory = memory();
+            memory.write(0x4014, 0x02);
+
+            assert_eq!(memory.run_dma(cycle), stall);
+            assert_eq!(memory.run_dma(cycle + stall), 0);
+        }
+    }
+
+    #[test]
+    fn oam_dma_copies_a_page_starting_at_oamaddr() {
+        let mut memory = memory();
+
+        for low in 0..=0xFF {
+            memory.write(0x0300 | low, low as u8);
+        }
+
+        memory.write(0x2003, 0x10);
+        memory.write(0x4014, 0x03);
+        memory.run_dma(0);
+
+        // The writes wrap around OAM.
+        for (oam_addr, value) in [(0x10, 0x00), (0xFF, 0xEF), (0x00, 0xF0), (0x0F, 0xFF)] {
+            memory.write(0x2003, oam_addr);
+            assert_eq!(memory.read(0x2004), value, "{oam_addr:02X}");
+        }
     }
 }
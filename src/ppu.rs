@@ -0,0 +1,846 @@
+use crate::mapper::Mapper;
+
+use palette::{palette_ram_index, Palette};
+use registers::{PPUCtrl, PPUMask};
+use sprites::{SpriteSlot, SPRITES_PER_SCANLINE};
+
+pub mod palette;
+pub mod registers;
+mod sprites;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+const DOTS_PER_SCANLINE: u16 = 341;
+const VBLANK_SCANLINE: u16 = 241;
+/// The last scanline of the frame, which fetches the same things as a visible one, but doesn't output anything.
+const PRERENDER_SCANLINE: u16 = 261;
+
+/// The 2C02 picture processing unit.
+///
+/// It runs 3 dots per CPU cycle, 341 dots per scanline and 262 scanlines per frame.
+/// The first 240 scanlines are visible, the PPU fetches tiles two at a time ahead of where it's drawing.
+/// Everything it fetches goes through the cartridge, apart from nametables in CIRAM and the palette.
+///
+/// Scrolling is handled by the internal registers (after loopy, who figured them out):
+///
+/// v, t: 0yyy NNYY YYYX XXXX
+///
+/// fine Y scroll, Nametable select, coarse Y scroll, coarse X scroll
+///
+/// v is the current VRAM address, and t is the address of the top left tile, which v gets reset to.
+/// x is the fine X scroll, and w is the first/second write toggle shared by PPUSCROLL and PPUADDR.
+/// Refer to: https://www.nesdev.org/wiki/PPU_scrolling
+#[allow(clippy::upper_case_acronyms)]
+pub struct PPU {
+    ctrl: PPUCtrl,
+    mask: PPUMask,
+    vblank: bool,
+    sprite_zero_hit: bool,
+    /// The pixel pipeline is a dot behind the fetches, so a hit only shows up in PPUSTATUS on the next dot.
+    /// The earliest that can be is dot 2, for the leftmost pixel.
+    sprite_zero_hit_delayed: bool,
+    sprite_overflow: bool,
+    oam_addr: u8,
+    /// 64 sprites, 4 bytes each: Y position (minus 1), tile number, attributes, X position.
+    oam: [u8; 0x100],
+    /// The sprites picked for the next scanline.
+    secondary_oam: [u8; SPRITES_PER_SCANLINE * 4],
+    sprite_count: usize,
+    /// Whether slot 0 holds sprite 0, which is the only one that can trigger a sprite 0 hit.
+    sprite_zero_in_line: bool,
+    sprites: [SpriteSlot; SPRITES_PER_SCANLINE],
+    v: u16,
+    t: u16,
+    x: u8,
+    w: bool,
+    /// PPUDATA reads return what was read the previous time, apart from the palette.
+    read_buffer: u8,
+    /// The data bus between the CPU and the PPU, which write-only registers read back.
+    latch: u8,
+    /// The console's 2 KB of VRAM (CIRAM), followed by the 2 KB four-screen cartridges add.
+    /// The cartridge decides how it's laid out into nametables.
+    vram: Box<[u8; 0x1000]>,
+    palette_ram: [u8; 0x20],
+    /// Only used to turn the frame into RGB, the PPU itself only deals in color indices.
+    palette: Palette,
+    scanline: u16,
+    dot: u16,
+    odd_frame: bool,
+    frame_count: u64,
+    /// Tile data fetched for the next tile, which goes into the shift registers 8 dots later.
+    nametable_byte: u8,
+    attribute_bits: u8,
+    pattern_low: u8,
+    pattern_high: u8,
+    /// The upper byte is the tile being drawn, the lower one the tile after it.
+    background_pattern_low: u16,
+    background_pattern_high: u16,
+    /// Attribute bits, expanded to match the pattern shift registers.
+    background_attribute_low: u16,
+    background_attribute_high: u16,
+    /// 6-bit colors, as they were looked up from palette RAM, with the emphasis bits above them.
+    frame: Box<[u16; SCREEN_WIDTH * SCREEN_HEIGHT]>,
+}
+
+impl Default for PPU {
+    fn default() -> Self {
+        PPU::new()
+    }
+}
+
+impl PPU {
+    pub fn new() -> PPU {
+        PPU {
+            ctrl: PPUCtrl::default(),
+            mask: PPUMask::default(),
+            vblank: false,
+            sprite_zero_hit: false,
+            sprite_zero_hit_delayed: false,
+            sprite_overflow: false,
+            oam_addr: 0,
+            oam: [0; 0x100],
+            secondary_oam: [0xFF; SPRITES_PER_SCANLINE * 4],
+            sprite_count: 0,
+            sprite_zero_in_line: false,
+            sprites: [SpriteSlot::default(); SPRITES_PER_SCANLINE],
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+            read_buffer: 0,
+            latch: 0,
+            vram: Box::new([0; 0x1000]),
+            palette_ram: [0; 0x20],
+            palette: Palette::default(),
+            scanline: 0,
+            dot: 0,
+            odd_frame: false,
+            frame_count: 0,
+            nametable_byte: 0,
+            attribute_bits: 0,
+            pattern_low: 0,
+            pattern_high: 0,
+            background_pattern_low: 0,
+            background_pattern_high: 0,
+            background_attribute_low: 0,
+            background_attribute_high: 0,
+            frame: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+        }
+    }
+
+    /// The last finished frame (or the one being drawn, during the visible scanlines), row by row.
+    /// Each pixel is a 6-bit NES color, with the PPUMASK emphasis bits above it. [Palette] turns them into RGB.
+    pub fn frame(&self) -> &[u16; SCREEN_WIDTH * SCREEN_HEIGHT] {
+        &self.frame
+    }
+
+    /// The frame as 24-bit RGB, 3 bytes per pixel.
+    pub fn frame_rgb(&self) -> Vec<u8> {
+        self.frame
+            .iter()
+            .flat_map(|pixel| self.palette.rgb(*pixel))
+            .collect()
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// How many frames have been finished. Goes up as soon as VBlank starts.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    /// State of the PPU's NMI output. It's held for as long as VBlank lasts (or until PPUSTATUS is read),
+    /// and the CPU reacts to it going high.
+    pub fn nmi(&self) -> bool {
+        self.vblank && self.ctrl.nmi_enabled
+    }
+
+    fn rendering(&self) -> bool {
+        self.mask.rendering_enabled()
+            && (self.scanline < 240 || self.scanline == PRERENDER_SCANLINE)
+    }
+
+    /// Reads a register, mirrored every 8 bytes from $2000 to $3FFF.
+    pub fn read_register(&mut self, mapper: &mut Option<Box<dyn Mapper>>, addr: u16) -> u8 {
+        match addr & 0b111 {
+            // PPUSTATUS only drives the upper 3 bits, the rest is whatever was left on the bus.
+            2 => {
+                self.latch = (self.status() & 0b1110_0000) | (self.latch & 0b0001_1111);
+                self.vblank = false;
+                self.w = false;
+            }
+            // The unused attribute bits don't exist in OAM, and read back as 0.
+            4 => self.latch = self.peek_oam(),
+            7 => {
+                let addr = self.v & 0x3FFF;
+
+                self.latch = if addr >= 0x3F00 {
+                    // Palette reads are immediate, but the buffer still gets the nametable "under" the palette.
+                    self.read_buffer = self.read_vram(mapper, addr - 0x1000);
+
+                    (self.latch & 0b1100_0000) | self.read_palette(addr)
+                } else {
+                    let value = self.read_vram(mapper, addr);
+
+                    std::mem::replace(&mut self.read_buffer, value)
+                };
+
+                self.increment_vram_addr();
+            }
+            // The rest are write-only.
+            _ => {}
+        }
+
+        self.latch
+    }
+
+    /// Reads a register without side effects.
+    pub fn peek_register(&self, addr: u16) -> u8 {
+        match addr & 0b111 {
+            2 => (self.status() & 0b1110_0000) | (self.latch & 0b0001_1111),
+            4 => self.peek_oam(),
+            7 if self.v & 0x3FFF >= 0x3F00 => {
+                (self.latch & 0b1100_0000) | self.read_palette(self.v & 0x3FFF)
+            }
+            7 => self.read_buffer,
+            _ => self.latch,
+        }
+    }
+
+    pub fn write_register(&mut self, mapper: &mut Option<Box<dyn Mapper>>, addr: u16, value: u8) {
+        self.latch = value;
+
+        match addr & 0b111 {
+            0 => {
+                self.ctrl = PPUCtrl::from(value);
+                self.t = (self.t & !0x0C00) | ((self.ctrl.nametable as u16) << 10);
+            }
+            1 => self.mask = PPUMask::from(value),
+            2 => {}
+            3 => self.oam_addr = value,
+            // Writes during rendering don't make it into OAM, but they still bump the sprite index.
+            4 if self.rendering() => self.oam_addr = self.oam_addr.wrapping_add(4),
+            4 => {
+                self.oam[self.oam_addr as usize] = value;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
+            5 => {
+                if self.w {
+                    self.t = (self.t & !0x73E0)
+                        | (((value & 0b111) as u16) << 12)
+                        | (((value & 0b1111_1000) as u16) << 2);
+                } else {
+                    self.t = (self.t & !0x001F) | (value >> 3) as u16;
+                    self.x = value & 0b111;
+                }
+
+                self.w = !self.w;
+            }
+            6 => {
+                if self.w {
+                    self.t = (self.t & 0xFF00) | value as u16;
+                    self.v = self.t;
+                } else {
+                    // The top bit of t gets cleared, as the address is only 14 bits wide.
+                    self.t = (self.t & 0x00FF) | (((value & 0b0011_1111) as u16) << 8);
+                }
+
+                self.w = !self.w;
+            }
+            _ => {
+                self.write_vram(mapper, self.v & 0x3FFF, value);
+                self.increment_vram_addr();
+            }
+        }
+    }
+
+    fn status(&self) -> u8 {
+        ((self.vblank as u8) << 7)
+            | ((self.sprite_zero_hit as u8) << 6)
+            | ((self.sprite_overflow as u8) << 5)
+    }
+
+    fn peek_oam(&self) -> u8 {
+        // Secondary OAM gets cleared during the first 64 dots, by reads that always return $FF.
+        if self.rendering() && self.scanline < 240 && (1..=64).contains(&self.dot) {
+            return 0xFF;
+        }
+
+        let value = self.oam[self.oam_addr as usize];
+
+        if self.oam_addr % 4 == 2 {
+            value & 0b1110_0011
+        } else {
+            value
+        }
+    }
+
+    /// PPUDATA accesses move on to the next address. During rendering, they instead glitch
+    /// both the coarse X and Y increments, as the same circuitry is used for both.
+    fn increment_vram_addr(&mut self) {
+        if self.rendering() {
+            self.increment_x();
+            self.increment_y();
+        } else {
+            self.v = self.v.wrapping_add(self.ctrl.vram_increment()) & 0x7FFF;
+        }
+    }
+
+    /// Reads the PPU address space:
+    ///
+    /// 0x0000-0x1FFF: Pattern tables, on the cartridge
+    ///
+    /// 0x2000-0x2FFF: Nametables, mirrored up to 0x3EFF. Usually CIRAM, but the cartridge can supply its own.
+    ///
+    /// 0x3F00-0x3F1F: Palette RAM, mirrored up to 0x3FFF
+    ///
+    /// Without a cartridge, nothing answers, and the bus still holds the low byte of the address.
+    fn read_vram(&mut self, mapper: &mut Option<Box<dyn Mapper>>, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => mapper
+                .as_mut()
+                .map_or(addr as u8, |mapper| mapper.ppu_read(addr)),
+            0x2000..=0x3EFF => {
+                let addr = 0x2000 | (addr & 0x0FFF);
+
+                match mapper {
+                    Some(mapper) => mapper
+                        .nametable_read(addr)
+                        .unwrap_or_else(|| self.vram[mapper.mirroring().vram_index(addr)]),
+                    None => addr as u8,
+                }
+            }
+            _ => self.read_palette(addr),
+        }
+    }
+
+    fn write_vram(&mut self, mapper: &mut Option<Box<dyn Mapper>>, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => {
+                if let Some(mapper) = mapper {
+                    mapper.ppu_write(addr, value);
+                }
+            }
+            0x2000..=0x3EFF => {
+                let addr = 0x2000 | (addr & 0x0FFF);
+
+                if let Some(mapper) = mapper {
+                    if !mapper.nametable_write(addr, value) {
+                        self.vram[mapper.mirroring().vram_index(addr)] = value;
+                    }
+                }
+            }
+            _ => self.palette_ram[palette_ram_index(addr)] = value & 0b0011_1111,
+        }
+    }
+
+    fn read_palette(&self, addr: u16) -> u8 {
+        self.palette_ram[palette_ram_index(addr)]
+    }
+
+    /// Moves on to the next tile, wrapping around into the horizontally adjacent nametable.
+    fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /// Moves on to the next row of pixels, wrapping around into the vertically adjacent nametable.
+    /// Coarse Y values of 30 and 31 are in the attribute table, and wrap around without switching nametables.
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+
+        let coarse_y = match (self.v & 0x03E0) >> 5 {
+            29 => {
+                self.v ^= 0x0800;
+                0
+            }
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+
+        self.v = (self.v & !0x03E0) | (coarse_y << 5);
+    }
+
+    fn copy_x(&mut self) {
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+
+    fn copy_y(&mut self) {
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    /// Runs the PPU for a single dot.
+    pub fn tick(&mut self, mapper: &mut Option<Box<dyn Mapper>>) {
+        if std::mem::take(&mut self.sprite_zero_hit_delayed) {
+            self.sprite_zero_hit = true;
+        }
+
+        if self.rendering() {
+            self.fetch(mapper);
+        }
+
+        if self.scanline < 240 && (1..=256).contains(&self.dot) {
+            self.output_pixel();
+        }
+
+        match (self.scanline, self.dot) {
+            (VBLANK_SCANLINE, 1) => {
+                self.vblank = true;
+                self.frame_count += 1;
+            }
+            (PRERENDER_SCANLINE, 1) => {
+                self.vblank = false;
+                self.sprite_zero_hit = false;
+                self.sprite_overflow = false;
+            }
+            _ => {}
+        }
+
+        self.advance();
+    }
+
+    fn advance(&mut self) {
+        // With rendering enabled, odd frames are one dot shorter.
+        if self.scanline == PRERENDER_SCANLINE
+            && self.dot == 339
+            && self.odd_frame
+            && self.mask.rendering_enabled()
+        {
+            self.dot = DOTS_PER_SCANLINE;
+        }
+
+        self.dot += 1;
+
+        if self.dot >= DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline > PRERENDER_SCANLINE {
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+    }
+
+    /// Memory accesses and scroll updates on visible and pre-render scanlines.
+    /// Every fetch takes 2 dots, and goes through the cartridge, which some mappers count on.
+    ///
+    /// 1-256: Background tiles for this scanline (nametable, attribute, pattern low, pattern high)
+    ///
+    /// 257-320: Sprite patterns for the next scanline, each preceded by two unused nametable fetches
+    ///
+    /// 321-336: The first two background tiles of the next scanline
+    ///
+    /// 337-340: Two unused nametable fetches
+    ///
+    /// Refer to: https://www.nesdev.org/wiki/PPU_rendering
+    fn fetch(&mut self, mapper: &mut Option<Box<dyn Mapper>>) {
+        let dot = self.dot;
+
+        if matches!(dot, 2..=257 | 322..=337) {
+            self.shift_background();
+
+            if (dot - 1).is_multiple_of(8) {
+                self.load_background();
+            }
+        }
+
+        match dot {
+            1..=256 | 321..=336 => match (dot - 1) % 8 {
+                0 => self.nametable_byte = self.read_vram(mapper, 0x2000 | (self.v & 0x0FFF)),
+                2 => self.fetch_attribute(mapper),
+                4 => self.pattern_low = self.read_vram(mapper, self.background_pattern_addr()),
+                6 => self.pattern_high = self.read_vram(mapper, self.background_pattern_addr() + 8),
+                7 => self.increment_x(),
+                _ => {}
+            },
+            257..=320 => {
+                if dot == 257 {
+                    self.evaluate_sprites();
+                }
+
+                self.fetch_sprite(mapper, ((dot - 257) / 8) as usize, (dot - 257) % 8);
+            }
+            337 | 339 => {
+                self.nametable_byte = self.read_vram(mapper, 0x2000 | (self.v & 0x0FFF));
+            }
+            _ => {}
+        }
+
+        match dot {
+            256 => self.increment_y(),
+            257 => self.copy_x(),
+            280..=304 if self.scanline == PRERENDER_SCANLINE => self.copy_y(),
+            _ => {}
+        }
+    }
+
+    /// Each attribute byte covers 4x4 tiles, two bits for each 2x2 quadrant.
+    fn fetch_attribute(&mut self, mapper: &mut Option<Box<dyn Mapper>>) {
+        let addr = 0x23C0 | (self.v & 0x0C00) | ((self.v >> 4) & 0x38) | ((self.v >> 2) & 0x07);
+        let attribute = self.read_vram(mapper, addr);
+
+        let shift = ((self.v >> 4) & 0b100) | (self.v & 0b10);
+
+        self.attribute_bits = (attribute >> shift) & 0b11;
+    }
+
+    fn background_pattern_addr(&self) -> u16 {
+        let fine_y = (self.v >> 12) & 0b111;
+
+        self.ctrl.background_table_addr() + self.nametable_byte as u16 * 16 + fine_y
+    }
+
+    fn shift_background(&mut self) {
+        self.background_pattern_low <<= 1;
+        self.background_pattern_high <<= 1;
+        self.background_attribute_low <<= 1;
+        self.background_attribute_high <<= 1;
+    }
+
+    fn load_background(&mut self) {
+        self.background_pattern_low |= self.pattern_low as u16;
+        self.background_pattern_high |= self.pattern_high as u16;
+        self.background_attribute_low |= if self.attribute_bits & 1 != 0 {
+            0xFF
+        } else {
+            0
+        };
+        self.background_attribute_high |= if self.attribute_bits & 2 != 0 {
+            0xFF
+        } else {
+            0
+        };
+    }
+
+    /// The 4-bit palette index of the background at the current dot. 0 is transparent.
+    fn background_pixel(&self) -> u8 {
+        let x = self.dot - 1;
+
+        if !self.mask.show_background || (x < 8 && !self.mask.show_background_left) {
+            return 0;
+        }
+
+        let bit = 15 - self.x;
+        let pick = |shift_register: u16| ((shift_register >> bit) & 1) as u8;
+
+        (pick(self.background_attribute_high) << 3)
+            | (pick(self.background_attribute_low) << 2)
+            | (pick(self.background_pattern_high) << 1)
+            | pick(self.background_pattern_low)
+    }
+
+    fn output_pixel(&mut self) {
+        let x = (self.dot - 1) as usize;
+        let y = self.scanline as usize;
+
+        let color = if self.mask.rendering_enabled() {
+            let background = self.background_pixel();
+            let background_opaque = background & 0b11 != 0;
+            let sprite = self.sprite_pixel();
+
+            if background_opaque && sprite.is_some() && self.sprite_zero_hits(x) {
+                self.sprite_zero_hit_delayed = true;
+            }
+
+            let pixel = match sprite {
+                Some((slot, _)) if background_opaque && self.sprite_behind_background(slot) => {
+                    background
+                }
+                Some((_, sprite)) => sprite,
+                None => background,
+            };
+
+            // Transparent pixels show the backdrop color.
+            if pixel & 0b11 == 0 {
+                self.palette_ram[0]
+            } else {
+                self.read_palette(pixel as u16)
+            }
+        } else if self.v & 0x3FFF >= 0x3F00 {
+            // With rendering disabled, pointing v into the palette shows that color instead of the backdrop.
+            self.read_palette(self.v)
+        } else {
+            self.palette_ram[0]
+        };
+
+        // Greyscale mode only keeps the brightness of the color, and leaves the hue at 0 (grey).
+        let color = if self.mask.greyscale {
+            color & 0b0011_0000
+        } else {
+            color
+        };
+
+        self.frame[y * SCREEN_WIDTH + x] = ((self.mask.emphasis as u16) << 6) | color as u16;
+    }
+
+    /// Sprite 0 hits need both sprite 0 and the background to be opaque at the same pixel.
+    /// The check is skipped at the last pixel of the scanline.
+    fn sprite_zero_hits(&self, x: usize) -> bool {
+        x != 255 && self.sprite_zero_opaque()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::{self, test_rom};
+
+    /// NROM with the test CHR-ROM, where the first byte of every 1 KB is its bank number.
+    fn ppu() -> (PPU, Option<Box<dyn Mapper>>) {
+        let mapper = mapper::create(test_rom(0, 0)).expect("NROM should be supported.");
+
+        (PPU::new(), Some(mapper))
+    }
+
+    fn set_vram_addr(ppu: &mut PPU, mapper: &mut Option<Box<dyn Mapper>>, addr: u16) {
+        ppu.write_register(mapper, 0x2006, (addr >> 8) as u8);
+        ppu.write_register(mapper, 0x2006, addr as u8);
+    }
+
+    /// Ticks until the start of the next frame, and returns how many dots it took.
+    fn run_frame(ppu: &mut PPU, mapper: &mut Option<Box<dyn Mapper>>) -> u32 {
+        let mut dots = 0;
+
+        loop {
+            ppu.tick(mapper);
+            dots += 1;
+
+            if (ppu.scanline, ppu.dot) == (0, 0) {
+                return dots;
+            }
+        }
+    }
+
+    #[test]
+    fn scroll_and_address_writes() {
+        let (mut ppu, mut mapper) = ppu();
+
+        ppu.write_register(&mut mapper, 0x2000, 0b10);
+        assert_eq!(ppu.t, 0x0800);
+
+        // Coarse X and fine X.
+        ppu.write_register(&mut mapper, 0x2005, 0x7D);
+        assert_eq!((ppu.t, ppu.x, ppu.w), (0x080F, 5, true));
+
+        // Coarse Y and fine Y.
+        ppu.write_register(&mut mapper, 0x2005, 0x5E);
+        assert_eq!((ppu.t, ppu.w), (0x696F, false));
+
+        // The high byte, without bit 14. v only changes on the second write.
+        ppu.write_register(&mut mapper, 0x2006, 0xFD);
+        assert_eq!((ppu.t, ppu.v, ppu.w), (0x3D6F, 0, true));
+
+        ppu.write_register(&mut mapper, 0x2006, 0xF0);
+        assert_eq!((ppu.t, ppu.v, ppu.w), (0x3DF0, 0x3DF0, false));
+
+        // Both registers share w, so this PPUADDR write counts as the second one.
+        ppu.write_register(&mut mapper, 0x2005, 0x00);
+        ppu.write_register(&mut mapper, 0x2006, 0x21);
+        assert_eq!((ppu.t, ppu.v, ppu.w), (0x3D21, 0x3D21, false));
+    }
+
+    #[test]
+    fn status_read_clears_vblank_and_w() {
+        let (mut ppu, mut mapper) = ppu();
+
+        ppu.vblank = true;
+        ppu.write_register(&mut mapper, 0x2005, 0x1F);
+        assert!(ppu.w);
+
+        // The lower bits are left over from the last write.
+        assert_eq!(ppu.read_register(&mut mapper, 0x2002), 0b1001_1111);
+        assert!(!ppu.vblank);
+        assert!(!ppu.w);
+
+        assert_eq!(ppu.read_register(&mut mapper, 0x2002), 0b0001_1111);
+    }
+
+    #[test]
+    fn buffered_data_reads() {
+        let (mut ppu, mut mapper) = ppu();
+
+        // Pattern tables and nametables are one read behind.
+        set_vram_addr(&mut ppu, &mut mapper, 0x0400);
+        assert_eq!(ppu.read_register(&mut mapper, 0x2007), 0);
+        set_vram_addr(&mut ppu, &mut mapper, 0x0800);
+        assert_eq!(ppu.read_register(&mut mapper, 0x2007), 1);
+        assert_eq!(ppu.read_register(&mut mapper, 0x2007), 2);
+
+        set_vram_addr(&mut ppu, &mut mapper, 0x2F01);
+        ppu.write_register(&mut mapper, 0x2007, 0x55);
+        set_vram_addr(&mut ppu, &mut mapper, 0x3F01);
+        ppu.write_register(&mut mapper, 0x2007, 0x2A);
+
+        // The palette isn't, but the buffer gets the nametable byte underneath it.
+        set_vram_addr(&mut ppu, &mut mapper, 0x3F01);
+        assert_eq!(ppu.read_register(&mut mapper, 0x2007), 0x2A);
+        assert_eq!(ppu.read_buffer, 0x55);
+    }
+
+    #[test]
+    fn data_access_increment() {
+        let (mut ppu, mut mapper) = ppu();
+
+        set_vram_addr(&mut ppu, &mut mapper, 0x2000);
+        ppu.write_register(&mut mapper, 0x2007, 0);
+        ppu.read_register(&mut mapper, 0x2007);
+        assert_eq!(ppu.v, 0x2002);
+
+        ppu.write_register(&mut mapper, 0x2000, 0b0000_0100);
+        ppu.write_register(&mut mapper, 0x2007, 0);
+        ppu.read_register(&mut mapper, 0x2007);
+        assert_eq!(ppu.v, 0x2042);
+    }
+
+    #[test]
+    fn scroll_increments_wrap_around() {
+        let mut ppu = PPU::new();
+
+        // Coarse X 31 wraps into the next nametable horizontally.
+        ppu.v = 0x001F;
+        ppu.increment_x();
+        assert_eq!(ppu.v, 0x0400);
+        ppu.increment_x();
+        assert_eq!(ppu.v, 0x0401);
+
+        // Fine Y overflows into coarse Y.
+        ppu.v = 0x7000 | (3 << 5);
+        ppu.increment_y();
+        assert_eq!(ppu.v, 4 << 5);
+
+        // Coarse Y 29 is the last row, and wraps into the next nametable vertically.
+        ppu.v = 0x7000 | (29 << 5);
+        ppu.increment_y();
+        assert_eq!(ppu.v, 0x0800);
+
+        // Coarse Y 31 is in the attribute table, and wraps without switching nametables.
+        ppu.v = 0x7000 | (31 << 5);
+        ppu.increment_y();
+        assert_eq!(ppu.v, 0);
+    }
+
+    #[test]
+    fn scroll_updates_during_rendering() {
+        let (mut ppu, mut mapper) = ppu();
+        ppu.write_register(&mut mapper, 0x2001, 0b0001_1000);
+
+        // Fine Y 1, coarse X 31.
+        ppu.scanline = 0;
+        ppu.dot = 256;
+        ppu.v = 0x101F;
+        ppu.t = 0x7BE5;
+
+        // Dot 256 moves on to the next tile and the next row.
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0x2400);
+
+        // Dot 257 resets the horizontal position.
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0x2005);
+
+        // The vertical position only gets reset on the pre-render scanline, during dots 280-304.
+        ppu.dot = 280;
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0x2005);
+
+        ppu.scanline = PRERENDER_SCANLINE;
+        ppu.dot = 279;
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0x2005);
+
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0x7BE5);
+
+        ppu.v = 0;
+        ppu.dot = 304;
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0x7BE0);
+
+        ppu.v = 0;
+        ppu.tick(&mut mapper);
+        assert_eq!(ppu.v, 0);
+    }
+
+    #[test]
+    fn vblank_and_nmi() {
+        let (mut ppu, mut mapper) = ppu();
+
+        ppu.scanline = VBLANK_SCANLINE;
+        ppu.dot = 0;
+        ppu.tick(&mut mapper);
+        assert!(!ppu.vblank);
+
+        ppu.tick(&mut mapper);
+        assert!(ppu.vblank);
+        assert_eq!(ppu.frame_count(), 1);
+        assert!(!ppu.nmi());
+
+        // Enabling NMIs during VBlank raises one right away.
+        ppu.write_register(&mut mapper, 0x2000, 0x80);
+        assert!(ppu.nmi());
+
+        ppu.read_register(&mut mapper, 0x2002);
+        assert!(!ppu.nmi());
+
+        ppu.vblank = true;
+        ppu.scanline = PRERENDER_SCANLINE;
+        ppu.dot = 1;
+        ppu.tick(&mut mapper);
+        assert!(!ppu.vblank);
+        assert!(!ppu.nmi());
+    }
+
+    #[test]
+    fn greyscale_and_emphasis() {
+        let (mut ppu, mut mapper) = ppu();
+        ppu.palette_ram[0] = 0x2A;
+
+        // Emphasis goes above the color, greyscale keeps only its brightness.
+        ppu.write_register(&mut mapper, 0x2001, 0b1010_0001);
+        ppu.dot = 1;
+        ppu.output_pixel();
+        assert_eq!(ppu.frame()[0], (0b101 << 6) | 0x20);
+        assert_eq!(ppu.frame_rgb()[..3], ppu.palette().rgb((0b101 << 6) | 0x20));
+
+        ppu.write_register(&mut mapper, 0x2001, 0);
+        ppu.output_pixel();
+        assert_eq!(ppu.frame()[0], 0x2A);
+    }
+
+    #[test]
+    fn odd_frames_skip_a_dot_while_rendering() {
+        let (mut ppu, mut mapper) = ppu();
+
+        assert_eq!(run_frame(&mut ppu, &mut mapper), 262 * 341);
+        assert_eq!(run_frame(&mut ppu, &mut mapper), 262 * 341);
+
+        ppu.write_register(&mut mapper, 0x2001, 0b0000_1000);
+        assert_eq!(run_frame(&mut ppu, &mut mapper), 262 * 341);
+        assert_eq!(run_frame(&mut ppu, &mut mapper), 262 * 341 - 1);
+    }
+}
@@ -0,0 +1,318 @@
+use std::{fmt, io::Read};
+
+use crate::rom::ines::VsPPUType;
+
+/// Every combination of the 64 colors and the 3 emphasis bits.
+pub const PALETTE_SIZE: usize = 512;
+
+/// Where a palette address ($3F00-$3FFF) lands in the 32 bytes of palette RAM.
+/// Entry 0 of each sprite palette is shared with the matching background palette,
+/// as neither is ever drawn (apart from $3F00, the backdrop).
+pub(super) fn palette_ram_index(addr: u16) -> usize {
+    let index = (addr & 0x1F) as usize;
+
+    if index & 0b1_0011 == 0b1_0000 {
+        index & 0x0F
+    } else {
+        index
+    }
+}
+
+/// The 2C02 doesn't output RGB, so any palette is just someone's idea of what the composite signal looks like.
+/// This one is a common, fairly neutral take on it.
+#[rustfmt::skip]
+const DEFAULT_COLORS: [[u8; 3]; 64] = [
+    [ 84,  84,  84], [  0,  30, 116], [  8,  16, 144], [ 48,   0, 136], [ 68,   0, 100], [ 92,   0,  48], [ 84,   4,   0], [ 60,  24,   0],
+    [ 32,  42,   0], [  8,  58,   0], [  0,  64,   0], [  0,  60,   0], [  0,  50,  60], [  0,   0,   0], [  0,   0,   0], [  0,   0,   0],
+    [152, 150, 152], [  8,  76, 196], [ 48,  50, 236], [ 92,  30, 228], [136,  20, 176], [160,  20, 100], [152,  34,  32], [120,  60,   0],
+    [ 84,  90,   0], [ 40, 114,   0], [  8, 124,   0], [  0, 118,  40], [  0, 102, 120], [  0,   0,   0], [  0,   0,   0], [  0,   0,   0],
+    [236, 238, 236], [ 76, 154, 236], [120, 124, 236], [176,  98, 236], [228,  84, 236], [236,  88, 180], [236, 106, 100], [212, 136,  32],
+    [160, 170,   0], [116, 196,   0], [ 76, 208,  32], [ 56, 204, 108], [ 56, 180, 204], [ 60,  60,  60], [  0,   0,   0], [  0,   0,   0],
+    [236, 238, 236], [168, 204, 236], [188, 188, 236], [212, 178, 236], [236, 174, 236], [236, 174, 212], [236, 180, 176], [228, 196, 144],
+    [204, 210, 120], [180, 222, 120], [168, 226, 144], [152, 226, 180], [160, 214, 228], [160, 162, 160], [  0,   0,   0], [  0,   0,   0],
+];
+
+/// The RGB PPUs (2C03, 2C04, 2C05) output 3 bits per channel, given here in octal (RGB).
+#[rustfmt::skip]
+const RGB_PPU_COLORS: [u16; 64] = [
+    0o333, 0o014, 0o006, 0o326, 0o403, 0o503, 0o510, 0o420, 0o320, 0o120, 0o031, 0o040, 0o022, 0o000, 0o000, 0o000,
+    0o555, 0o036, 0o027, 0o407, 0o507, 0o704, 0o700, 0o630, 0o430, 0o140, 0o040, 0o053, 0o044, 0o000, 0o000, 0o000,
+    0o777, 0o357, 0o447, 0o637, 0o707, 0o737, 0o740, 0o750, 0o660, 0o360, 0o070, 0o276, 0o077, 0o000, 0o000, 0o000,
+    0o777, 0o567, 0o657, 0o757, 0o747, 0o755, 0o764, 0o772, 0o773, 0o572, 0o473, 0o276, 0o467, 0o000, 0o000, 0o000,
+];
+
+/// The 2C04 variants have the same colors as the 2C03, but each one in a different order,
+/// so that Vs. System games only look right on the PPU they were made for.
+#[rustfmt::skip]
+const RP2C04_ORDER: [[u8; 64]; 4] = [
+    // RP2C04-0001
+    [
+        0x35, 0x23, 0x16, 0x22, 0x1C, 0x09, 0x1D, 0x15, 0x20, 0x00, 0x27, 0x05, 0x04, 0x28, 0x08, 0x20,
+        0x21, 0x3E, 0x1F, 0x29, 0x3C, 0x32, 0x36, 0x12, 0x3F, 0x2B, 0x2E, 0x1E, 0x3D, 0x2D, 0x24, 0x01,
+        0x0E, 0x31, 0x33, 0x2A, 0x2C, 0x0C, 0x1B, 0x14, 0x2E, 0x07, 0x34, 0x06, 0x13, 0x02, 0x26, 0x2E,
+        0x2E, 0x19, 0x10, 0x0A, 0x39, 0x03, 0x37, 0x17, 0x0F, 0x11, 0x0B, 0x0D, 0x38, 0x25, 0x18, 0x3A,
+    ],
+    // RP2C04-0002
+    [
+        0x2E, 0x27, 0x18, 0x39, 0x3A, 0x25, 0x1C, 0x31, 0x16, 0x13, 0x38, 0x34, 0x20, 0x23, 0x3C, 0x0B,
+        0x0F, 0x21, 0x06, 0x3D, 0x1B, 0x29, 0x1E, 0x22, 0x1D, 0x24, 0x0E, 0x2B, 0x32, 0x08, 0x2E, 0x03,
+        0x04, 0x36, 0x26, 0x33, 0x11, 0x1F, 0x10, 0x02, 0x14, 0x3F, 0x00, 0x09, 0x12, 0x2E, 0x28, 0x20,
+        0x3E, 0x0D, 0x2A, 0x17, 0x0C, 0x01, 0x15, 0x19, 0x2E, 0x2C, 0x07, 0x37, 0x35, 0x05, 0x0A, 0x2D,
+    ],
+    // RP2C04-0003
+    [
+        0x14, 0x25, 0x3A, 0x10, 0x0B, 0x20, 0x31, 0x09, 0x01, 0x2E, 0x36, 0x08, 0x15, 0x3D, 0x3E, 0x3C,
+        0x22, 0x1C, 0x05, 0x12, 0x19, 0x18, 0x17, 0x1B, 0x00, 0x03, 0x2E, 0x02, 0x16, 0x06, 0x34, 0x35,
+        0x23, 0x0F, 0x0E, 0x37, 0x0D, 0x27, 0x26, 0x20, 0x29, 0x04, 0x21, 0x24, 0x11, 0x2D, 0x2E, 0x1F,
+        0x2C, 0x1E, 0x39, 0x33, 0x07, 0x2A, 0x28, 0x1D, 0x0A, 0x2E, 0x32, 0x38, 0x13, 0x2B, 0x3F, 0x0C,
+    ],
+    // RP2C04-0004
+    [
+        0x18, 0x03, 0x1C, 0x28, 0x2E, 0x35, 0x01, 0x17, 0x10, 0x1F, 0x2A, 0x0E, 0x36, 0x37, 0x0B, 0x39,
+        0x25, 0x1E, 0x12, 0x34, 0x2E, 0x1D, 0x06, 0x26, 0x3E, 0x1B, 0x22, 0x19, 0x04, 0x2E, 0x3A, 0x21,
+        0x05, 0x0A, 0x07, 0x02, 0x13, 0x14, 0x00, 0x15, 0x0C, 0x3D, 0x11, 0x0F, 0x0D, 0x38, 0x2D, 0x24,
+        0x33, 0x20, 0x08, 0x16, 0x3F, 0x2B, 0x20, 0x3C, 0x2E, 0x27, 0x23, 0x31, 0x29, 0x32, 0x2C, 0x09,
+    ],
+];
+
+/// How much each emphasis bit darkens the other two channels on the 2C02.
+const EMPHASIS_ATTENUATION: f32 = 0.816328;
+
+/// Everything that can go wrong while loading a palette file.
+#[derive(Debug)]
+pub enum PaletteError {
+    /// .pal files are 64 or 512 RGB triplets, nothing else.
+    BadSize(usize),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::BadSize(size) => write!(
+                f,
+                "palette file should be 192 or 1536 bytes long, got {size}"
+            ),
+            PaletteError::Io(error) => write!(f, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PaletteError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PaletteError {
+    fn from(error: std::io::Error) -> Self {
+        PaletteError::Io(error)
+    }
+}
+
+/// Turns the PPU's output, a 6-bit color plus the 3 emphasis bits from PPUMASK, into RGB.
+///
+/// Indices are laid out the same way as in 512-entry .pal files: 0bBGR_CCCCCC,
+/// the emphasis bits (blue, green, red) above the color.
+/// Refer to: https://www.nesdev.org/wiki/PPU_palettes
+pub struct Palette {
+    colors: Box<[[u8; 3]; PALETTE_SIZE]>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::with_attenuated_emphasis(&DEFAULT_COLORS)
+    }
+}
+
+impl Palette {
+    /// Fills in the emphasized colors the way the 2C02 does it, by darkening the channels that aren't emphasized.
+    fn with_attenuated_emphasis(colors: &[[u8; 3]; 64]) -> Palette {
+        Palette::with_emphasis(colors, |value, channel, emphasis| {
+            let attenuated = (0..3)
+                .filter(|other| *other != channel && emphasis & (1 << other) != 0)
+                .count();
+
+            (value as f32 * EMPHASIS_ATTENUATION.powi(attenuated as i32)).round() as u8
+        })
+    }
+
+    /// The RGB PPUs don't darken anything, emphasis just turns the channel all the way up instead.
+    fn with_saturated_emphasis(colors: &[[u8; 3]; 64]) -> Palette {
+        Palette::with_emphasis(colors, |value, channel, emphasis| {
+            if emphasis & (1 << channel) != 0 {
+                0xFF
+            } else {
+                value
+            }
+        })
+    }
+
+    /// `emphasize` gets each channel's value, the channel (0 = red, 1 = green, 2 = blue) and the emphasis bits.
+    fn with_emphasis(
+        colors: &[[u8; 3]; 64],
+        emphasize: impl Fn(u8, usize, usize) -> u8,
+    ) -> Palette {
+        let mut palette = Box::new([[0u8; 3]; PALETTE_SIZE]);
+
+        for (index, entry) in palette.iter_mut().enumerate() {
+            let emphasis = index >> 6;
+            let color = colors[index & 0x3F];
+
+            for channel in 0..3 {
+                entry[channel] = emphasize(color[channel], channel, emphasis);
+            }
+        }
+
+        Palette { colors: palette }
+    }
+
+    /// Parses a .pal file: 64 RGB triplets, or 512 of them to also cover every emphasis combination.
+    /// With only 64 colors, the emphasized ones are made up the way the 2C02 does it.
+    pub fn from_bytes(data: &[u8]) -> Result<Palette, PaletteError> {
+        let triplets = data.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]);
+
+        match data.len() {
+            192 => {
+                let mut colors = [[0u8; 3]; 64];
+
+                for (entry, rgb) in colors.iter_mut().zip(triplets) {
+                    *entry = rgb;
+                }
+
+                Ok(Palette::with_attenuated_emphasis(&colors))
+            }
+            1536 => {
+                let mut colors = Box::new([[0u8; 3]; PALETTE_SIZE]);
+
+                for (entry, rgb) in colors.iter_mut().zip(triplets) {
+                    *entry = rgb;
+                }
+
+                Ok(Palette { colors })
+            }
+            size => Err(PaletteError::BadSize(size)),
+        }
+    }
+
+    /// Reads a .pal file from `reader` and parses it.
+    pub fn load(mut reader: impl Read) -> Result<Palette, PaletteError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Palette::from_bytes(&data)
+    }
+
+    /// The colors of the RGB PPUs used in the Vs. System (and PlayChoice-10 and a few TVs).
+    pub fn for_vs_ppu(ppu: VsPPUType) -> Palette {
+        let rgb = |color: u16| {
+            // 3 bits per channel, scaled to 8.
+            let channel = |shift: u16| (((color >> shift) & 0b111) * 255 / 7) as u8;
+
+            [channel(6), channel(3), channel(0)]
+        };
+
+        let order = match ppu {
+            VsPPUType::RP2C04_0001 => Some(&RP2C04_ORDER[0]),
+            VsPPUType::RP2C04_0002 => Some(&RP2C04_ORDER[1]),
+            VsPPUType::RP2C04_0003 => Some(&RP2C04_ORDER[2]),
+            VsPPUType::RP2C04_0004 => Some(&RP2C04_ORDER[3]),
+            _ => None,
+        };
+
+        let mut colors = [[0u8; 3]; 64];
+
+        for (index, entry) in colors.iter_mut().enumerate() {
+            let index = order.map_or(index, |order| order[index] as usize);
+
+            *entry = rgb(RGB_PPU_COLORS[index]);
+        }
+
+        Palette::with_saturated_emphasis(&colors)
+    }
+
+    /// The color of a pixel from [super::PPU::frame].
+    pub fn rgb(&self, pixel: u16) -> [u8; 3] {
+        self.colors[pixel as usize % PALETTE_SIZE]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprite_backdrop_entries_mirror_background_ones() {
+        for (addr, index) in [
+            (0x3F10, 0x00),
+            (0x3F14, 0x04),
+            (0x3F18, 0x08),
+            (0x3F1C, 0x0C),
+        ] {
+            assert_eq!(palette_ram_index(addr), index);
+        }
+
+        assert_eq!(palette_ram_index(0x3F04), 0x04);
+        assert_eq!(palette_ram_index(0x3F11), 0x11);
+        assert_eq!(palette_ram_index(0x3F1F), 0x1F);
+
+        // Mirrored every 32 bytes.
+        assert_eq!(palette_ram_index(0x3F30), 0x00);
+        assert_eq!(palette_ram_index(0x3FE5), 0x05);
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        for size in [0, 3, 191, 193, 1535, 1537] {
+            assert!(matches!(
+                Palette::from_bytes(&vec![0; size]),
+                Err(PaletteError::BadSize(bad)) if bad == size
+            ));
+        }
+
+        assert!(Palette::from_bytes(&[0; 192]).is_ok());
+        assert!(Palette::load(&[0u8; 1536][..]).is_ok());
+        assert!(matches!(
+            Palette::load(&[0u8; 200][..]),
+            Err(PaletteError::BadSize(200))
+        ));
+    }
+
+    #[test]
+    fn emphasis_selects_the_block_of_64() {
+        // Every color in block b is (b, color, 0).
+        let data: Vec<u8> = (0..PALETTE_SIZE)
+            .flat_map(|index| [(index >> 6) as u8, (index & 0x3F) as u8, 0])
+            .collect();
+        let palette = Palette::from_bytes(&data).unwrap();
+
+        for emphasis in 0..8 {
+            assert_eq!(
+                palette.rgb((emphasis << 6) | 0x2A),
+                [emphasis as u8, 0x2A, 0]
+            );
+        }
+    }
+
+    #[test]
+    fn emphasis_made_up_for_64_colors() {
+        let data = [200; 192];
+        let palette = Palette::from_bytes(&data).unwrap();
+
+        assert_eq!(palette.rgb(0x10), [200, 200, 200]);
+
+        // Emphasizing red darkens green and blue.
+        let darkened = (200.0 * EMPHASIS_ATTENUATION).round() as u8;
+        assert_eq!(palette.rgb(0b001 << 6), [200, darkened, darkened]);
+
+        // Emphasizing red and blue darkens green twice, and each of them once.
+        let darkened_twice = (200.0 * EMPHASIS_ATTENUATION.powi(2)).round() as u8;
+        assert_eq!(
+            palette.rgb(0b101 << 6),
+            [darkened, darkened_twice, darkened]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::utils::bits::get_bit;
+
+/// PPUCTRL ($2000) decoded:
+///
+/// 7654 3210
+///
+/// VPHB SINN
+///
+/// NMI enable (V), PPU master/slave (P), sprite Height, Background pattern table, Sprite pattern table,
+/// Increment mode, base Nametable
+#[derive(Clone, Copy, Default)]
+pub struct PPUCtrl {
+    /// Only used for the initial contents of `t`, the scroll position keeps track of it after that.
+    pub nametable: u8,
+    /// VRAM address increment per PPUDATA access: 32 (going down) if set, 1 (going across) otherwise.
+    pub increment_32: bool,
+    /// $1000 if set, $0000 otherwise. Ignored in 8x16 mode.
+    pub sprite_table: bool,
+    /// $1000 if set, $0000 otherwise.
+    pub background_table: bool,
+    /// 8x16 sprites if set, 8x8 otherwise.
+    pub large_sprites: bool,
+    /// Grounding EXT pins while in output mode can damage the PPU. Not emulated.
+    pub master_slave: bool,
+    pub nmi_enabled: bool,
+}
+
+impl From<u8> for PPUCtrl {
+    fn from(value: u8) -> Self {
+        PPUCtrl {
+            nametable: value & 0b11,
+            increment_32: get_bit(value, 2),
+            sprite_table: get_bit(value, 3),
+            background_table: get_bit(value, 4),
+            large_sprites: get_bit(value, 5),
+            master_slave: get_bit(value, 6),
+            nmi_enabled: get_bit(value, 7),
+        }
+    }
+}
+
+impl PPUCtrl {
+    pub fn vram_increment(&self) -> u16 {
+        if self.increment_32 {
+            32
+        } else {
+            1
+        }
+    }
+
+    pub fn sprite_table_addr(&self) -> u16 {
+        (self.sprite_table as u16) << 12
+    }
+
+    pub fn background_table_addr(&self) -> u16 {
+        (self.background_table as u16) << 12
+    }
+}
+
+/// PPUMASK ($2001) decoded:
+///
+/// 7654 3210
+///
+/// BGRs bMmG
+///
+/// emphasize Blue, emphasize Green, emphasize Red, show sprites, show background,
+/// show sprites in leftmost 8 pixels (M), show background in leftmost 8 pixels (m), Greyscale
+#[derive(Clone, Copy, Default)]
+pub struct PPUMask {
+    pub greyscale: bool,
+    pub show_background_left: bool,
+    pub show_sprites_left: bool,
+    pub show_background: bool,
+    pub show_sprites: bool,
+    /// Bits 5-7 as they are: red, green, blue.
+    pub emphasis: u8,
+}
+
+impl From<u8> for PPUMask {
+    fn from(value: u8) -> Self {
+        PPUMask {
+            greyscale: get_bit(value, 0),
+            show_background_left: get_bit(value, 1),
+            show_sprites_left: get_bit(value, 2),
+            show_background: get_bit(value, 3),
+            show_sprites: get_bit(value, 4),
+            emphasis: value >> 5,
+        }
+    }
+}
+
+impl PPUMask {
+    /// The PPU only fetches anything (and touches the scroll registers) while at least one layer is shown.
+    pub fn rendering_enabled(&self) -> bool {
+        self.show_background || self.show_sprites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctrl_decoding() {
+        let ctrl = PPUCtrl::from(0b1001_0110);
+
+        assert_eq!(ctrl.nametable, 0b10);
+        assert_eq!(ctrl.vram_increment(), 32);
+        assert_eq!(ctrl.sprite_table_addr(), 0x0000);
+        assert_eq!(ctrl.background_table_addr(), 0x1000);
+        assert!(!ctrl.large_sprites);
+        assert!(ctrl.nmi_enabled);
+
+        assert_eq!(PPUCtrl::from(0b0000_1000).vram_increment(), 1);
+        assert_eq!(PPUCtrl::from(0b0000_1000).sprite_table_addr(), 0x1000);
+    }
+
+    #[test]
+    fn mask_decoding() {
+        let mask = PPUMask::from(0b1010_0001);
+
+        assert!(mask.greyscale);
+        assert_eq!(mask.emphasis, 0b101);
+        assert!(!mask.rendering_enabled());
+
+        assert!(PPUMask::from(0b0000_1000).rendering_enabled());
+        assert!(PPUMask::from(0b0001_0000).rendering_enabled());
+        assert!(!PPUMask::from(0b0000_0110).rendering_enabled());
+    }
+}
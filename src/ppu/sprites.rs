@@ -0,0 +1,427 @@
+use super::{PPU, PRERENDER_SCANLINE};
+use crate::{mapper::Mapper, utils::bits::get_bit};
+
+/// The PPU can only draw this many sprites on a scanline.
+pub(super) const SPRITES_PER_SCANLINE: usize = 8;
+
+/// One of the sprites picked for the next scanline, with its pattern already fetched.
+///
+/// Attributes decoded:
+///
+/// 7654 3210
+///
+/// VHP. ..PP
+///
+/// flip Vertically, flip Horizontally, Priority (behind background), Palette (4-7)
+#[derive(Clone, Copy, Default)]
+pub(super) struct SpriteSlot {
+    pattern_low: u8,
+    pattern_high: u8,
+    attributes: u8,
+    x: u8,
+}
+
+impl PPU {
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl.large_sprites {
+            16
+        } else {
+            8
+        }
+    }
+
+    fn sprite_in_range(&self, y: u8) -> bool {
+        self.scanline.wrapping_sub(y as u16) < self.sprite_height()
+    }
+
+    /// Picks the sprites for the next scanline, the first 8 in OAM order that are in range.
+    /// The hardware spreads this out over dots 65-256, but nothing can see it happen apart from OAMDATA reads.
+    ///
+    /// Once secondary OAM is full, the PPU keeps looking to set the overflow flag.
+    /// Except it increments both the sprite index and the byte within the sprite,
+    /// so it ends up treating tile numbers, attributes and X positions as Y coordinates.
+    /// Refer to: https://www.nesdev.org/wiki/PPU_sprite_evaluation
+    pub(super) fn evaluate_sprites(&mut self) {
+        self.secondary_oam = [0xFF; SPRITES_PER_SCANLINE * 4];
+        self.sprite_count = 0;
+        self.sprite_zero_in_line = false;
+
+        // Nothing gets evaluated on the pre-render scanline, so no sprites show up on the first one.
+        if self.scanline == PRERENDER_SCANLINE {
+            return;
+        }
+
+        let mut n = 0;
+
+        while n < 64 {
+            let sprite = &self.oam[n * 4..n * 4 + 4];
+
+            if self.sprite_in_range(sprite[0]) {
+                let slot = self.sprite_count;
+
+                self.secondary_oam[slot * 4..slot * 4 + 4].copy_from_slice(sprite);
+                self.sprite_count += 1;
+
+                if n == 0 {
+                    self.sprite_zero_in_line = true;
+                }
+            }
+
+            n += 1;
+
+            if self.sprite_count == SPRITES_PER_SCANLINE {
+                break;
+            }
+        }
+
+        let mut m = 0;
+
+        while n < 64 {
+            if self.sprite_in_range(self.oam[n * 4 + m]) {
+                self.sprite_overflow = true;
+                break;
+            }
+
+            n += 1;
+            m = (m + 1) % 4;
+        }
+    }
+
+    /// Sprite fetches for one of the 8 sprite slots.
+    /// Empty slots still fetch tile $FF, which mappers watching the pattern table accesses count on.
+    pub(super) fn fetch_sprite(
+        &mut self,
+        mapper: &mut Option<Box<dyn Mapper>>,
+        slot: usize,
+        step: u16,
+    ) {
+        let [y, tile, attributes, x] = self.secondary_oam[slot * 4..slot * 4 + 4]
+            .try_into()
+            .expect("Sprite slots are 4 bytes long.");
+
+        let empty = slot >= self.sprite_count;
+
+        match step {
+            // The nametable fetches are unused, but they still happen.
+            0 | 2 => {
+                self.read_vram(mapper, 0x2000 | (self.v & 0x0FFF));
+            }
+            4 => {
+                let pattern_low =
+                    self.read_vram(mapper, self.sprite_pattern_addr(y, tile, attributes));
+
+                self.sprites[slot] = SpriteSlot {
+                    pattern_low: if empty { 0 } else { pattern_low },
+                    pattern_high: 0,
+                    attributes,
+                    x,
+                };
+            }
+            6 => {
+                let pattern_high =
+                    self.read_vram(mapper, self.sprite_pattern_addr(y, tile, attributes) + 8);
+
+                self.sprites[slot].pattern_high = if empty { 0 } else { pattern_high };
+
+                // Horizontal flipping happens as the pattern goes into the shift registers.
+                if get_bit(attributes, 6) {
+                    self.sprites[slot].pattern_low = self.sprites[slot].pattern_low.reverse_bits();
+                    self.sprites[slot].pattern_high =
+                        self.sprites[slot].pattern_high.reverse_bits();
+                }
+            }
+            _ => {}
+        }
+
+        // OAMADDR gets cleared during sprite fetches.
+        self.oam_addr = 0;
+    }
+
+    /// 8x16 sprites pick their pattern table with bit 0 of the tile number, and take up two tiles.
+    fn sprite_pattern_addr(&self, y: u8, tile: u8, attributes: u8) -> u16 {
+        let height = self.sprite_height();
+        let mut row = self.scanline.wrapping_sub(y as u16) % height;
+
+        if get_bit(attributes, 7) {
+            row = height - 1 - row;
+        }
+
+        if self.ctrl.large_sprites {
+            let table = ((tile & 1) as u16) << 12;
+            let tile = (tile & 0xFE) as u16 + row / 8;
+
+            table + tile * 16 + (row & 0b111)
+        } else {
+            self.ctrl.sprite_table_addr() + tile as u16 * 16 + row
+        }
+    }
+
+    /// The first opaque sprite at the current dot, as its slot and 5-bit palette index.
+    /// Lower slots always win, even if they're behind the background and a later one isn't.
+    pub(super) fn sprite_pixel(&self) -> Option<(usize, u8)> {
+        let x = self.dot - 1;
+
+        if !self.mask.show_sprites || (x < 8 && !self.mask.show_sprites_left) {
+            return None;
+        }
+
+        self.sprites[..self.sprite_count]
+            .iter()
+            .enumerate()
+            .find_map(|(slot, sprite)| {
+                let offset = x
+                    .checked_sub(sprite.x as u16)
+                    .filter(|offset| *offset < 8)?;
+                let bit = 7 - offset;
+
+                let color =
+                    (((sprite.pattern_high >> bit) & 1) << 1) | ((sprite.pattern_low >> bit) & 1);
+
+                (color != 0).then_some((slot, 0x10 | ((sprite.attributes & 0b11) << 2) | color))
+            })
+    }
+
+    /// Whether the sprite in `slot` is drawn behind the background.
+    pub(super) fn sprite_behind_background(&self, slot: usize) -> bool {
+        get_bit(self.sprites[slot].attributes, 5)
+    }
+
+    /// Whether sprite 0 is opaque at the current dot, regardless of whether another sprite is drawn over it.
+    pub(super) fn sprite_zero_opaque(&self) -> bool {
+        if !self.sprite_zero_in_line {
+            return false;
+        }
+
+        let x = self.dot - 1;
+        let sprite = &self.sprites[0];
+
+        match x.checked_sub(sprite.x as u16).filter(|offset| *offset < 8) {
+            Some(offset) => (sprite.pattern_low | sprite.pattern_high) & (0x80 >> offset) != 0,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mapper::{self, test_rom},
+        ppu::registers::PPUMask,
+    };
+
+    fn ppu_with_sprites(sprites: &[[u8; 4]]) -> PPU {
+        let mut ppu = PPU::new();
+
+        for (n, sprite) in sprites.iter().enumerate() {
+            ppu.oam[n * 4..n * 4 + 4].copy_from_slice(sprite);
+        }
+
+        // Everything else is out of range.
+        for byte in ppu.oam[sprites.len() * 4..].iter_mut() {
+            *byte = 0xF0;
+        }
+
+        ppu
+    }
+
+    #[test]
+    fn first_eight_sprites_in_range() {
+        let mut sprites = vec![[20, 0, 0, 0]];
+        sprites.extend((1..10).map(|n| [10, n, 0, n]));
+        let mut ppu = ppu_with_sprites(&sprites);
+
+        ppu.scanline = 10;
+        ppu.evaluate_sprites();
+
+        assert_eq!(ppu.sprite_count, 8);
+        assert!(!ppu.sprite_zero_in_line);
+        assert_eq!(ppu.secondary_oam[..4], [10, 1, 0, 1]);
+        assert_eq!(ppu.secondary_oam[28..], [10, 8, 0, 8]);
+        assert!(ppu.sprite_overflow);
+
+        // Sprites cover the 8 scanlines after their Y coordinate.
+        ppu.sprite_overflow = false;
+        ppu.scanline = 27;
+        ppu.evaluate_sprites();
+        assert_eq!(ppu.sprite_count, 1);
+        assert!(ppu.sprite_zero_in_line);
+        assert!(!ppu.sprite_overflow);
+
+        // Or 16 with 8x16 sprites.
+        ppu.scanline = 25;
+        ppu.ctrl.large_sprites = true;
+        ppu.evaluate_sprites();
+        assert_eq!(ppu.sprite_count, 8);
+        assert!(ppu.sprite_zero_in_line);
+    }
+
+    #[test]
+    fn overflow_checks_the_wrong_bytes() {
+        let in_range = [[10, 0, 0, 0]; 8];
+
+        // Sprite 9's tile number gets checked as its Y coordinate.
+        let mut sprites = in_range.to_vec();
+        sprites.push([0xF0, 0xF0, 0xF0, 0xF0]);
+        sprites.push([0xF0, 10, 0xF0, 0xF0]);
+        let mut ppu = ppu_with_sprites(&sprites);
+
+        ppu.scanline = 10;
+        ppu.evaluate_sprites();
+        assert!(ppu.sprite_overflow);
+
+        // While its actual Y coordinate is ignored.
+        let mut sprites = in_range.to_vec();
+        sprites.push([0xF0, 0xF0, 0xF0, 0xF0]);
+        sprites.push([10, 0xF0, 0xF0, 0xF0]);
+        let mut ppu = ppu_with_sprites(&sprites);
+
+        ppu.scanline = 10;
+        ppu.evaluate_sprites();
+        assert!(!ppu.sprite_overflow);
+
+        // The pre-render scanline doesn't evaluate anything.
+        ppu.scanline = PRERENDER_SCANLINE;
+        ppu.oam.fill(0);
+        ppu.evaluate_sprites();
+        assert_eq!(ppu.sprite_count, 0);
+        assert!(!ppu.sprite_overflow);
+    }
+
+    #[test]
+    fn large_sprite_pattern_tables() {
+        let mut ppu = PPU::new();
+        ppu.ctrl.large_sprites = true;
+        ppu.ctrl.sprite_table = true;
+        ppu.scanline = 12;
+
+        // Bit 0 of the tile number picks the table, the rest the top tile.
+        assert_eq!(ppu.sprite_pattern_addr(10, 0x02, 0), 0x0022);
+        assert_eq!(ppu.sprite_pattern_addr(10, 0x03, 0), 0x1022);
+
+        // The bottom half is the next tile.
+        ppu.scanline = 20;
+        assert_eq!(ppu.sprite_pattern_addr(10, 0x03, 0), 0x1032);
+
+        // Flipping vertically swaps the halves too.
+        assert_eq!(ppu.sprite_pattern_addr(10, 0x03, 0x80), 0x1025);
+
+        ppu.ctrl.large_sprites = false;
+        ppu.scanline = 12;
+        assert_eq!(ppu.sprite_pattern_addr(10, 0x03, 0), 0x1032);
+    }
+
+    /// Draws x = 10 on the first scanline, with an opaque or transparent background under it.
+    fn draw_pixel(ppu: &mut PPU, sprites: [SpriteSlot; 2], background_opaque: bool) -> u16 {
+        ppu.mask = PPUMask::from(0b0001_1110);
+        ppu.palette_ram[0x01] = 0x01;
+        ppu.palette_ram[0x11] = 0x21;
+        ppu.palette_ram[0x16] = 0x26;
+        ppu.background_pattern_low = if background_opaque { 0xFFFF } else { 0 };
+        ppu.sprites[..2].copy_from_slice(&sprites);
+        ppu.sprite_count = 2;
+        ppu.scanline = 0;
+        ppu.dot = 11;
+
+        ppu.output_pixel();
+
+        ppu.frame[10]
+    }
+
+    #[test]
+    fn sprite_priority() {
+        let mut ppu = PPU::new();
+
+        let sprite = |pattern_low, pattern_high, attributes, x| SpriteSlot {
+            pattern_low,
+            pattern_high,
+            attributes,
+            x,
+        };
+        // Color 2 of palette 5.
+        let front = sprite(0, 0xFF, 0b01, 10);
+
+        assert_eq!(
+            draw_pixel(&mut ppu, [sprite(0xFF, 0, 0, 10), front], true),
+            0x21
+        );
+
+        // The first opaque sprite decides, even when it's behind the background and a later one isn't.
+        let behind = sprite(0xFF, 0, 0x20, 10);
+        assert_eq!(draw_pixel(&mut ppu, [behind, front], true), 0x01);
+        assert_eq!(draw_pixel(&mut ppu, [behind, front], false), 0x21);
+
+        // Transparent sprite pixels don't count.
+        let elsewhere = sprite(0xFF, 0, 0, 100);
+        assert_eq!(draw_pixel(&mut ppu, [elsewhere, front], true), 0x26);
+    }
+
+    /// Sprite 0 at `sprite_x`, over a background that's opaque everywhere, starting at `dot` on the first scanline.
+    fn sprite_zero_setup(mask: u8, sprite_x: u8, dot: u16) -> (PPU, Option<Box<dyn Mapper>>) {
+        let mapper = mapper::create(test_rom(0, 0)).expect("NROM should be supported.");
+        let mut ppu = PPU::new();
+
+        ppu.mask = PPUMask::from(mask);
+        ppu.background_pattern_low = 0xFFFF;
+        ppu.sprites[0] = SpriteSlot {
+            pattern_low: 0xFF,
+            pattern_high: 0,
+            attributes: 0,
+            x: sprite_x,
+        };
+        ppu.sprite_count = 1;
+        ppu.sprite_zero_in_line = true;
+        ppu.scanline = 0;
+        ppu.dot = dot;
+
+        (ppu, Some(mapper))
+    }
+
+    #[test]
+    fn sprite_zero_hit_timing() {
+        let (mut ppu, mut mapper) = sprite_zero_setup(0b0001_1110, 0, 1);
+
+        // The leftmost pixel only sets the flag on dot 2.
+        ppu.tick(&mut mapper);
+        assert!(!ppu.sprite_zero_hit);
+        ppu.tick(&mut mapper);
+        assert!(ppu.sprite_zero_hit);
+
+        // Lasts until the pre-render scanline.
+        ppu.scanline = PRERENDER_SCANLINE;
+        ppu.dot = 1;
+        ppu.tick(&mut mapper);
+        assert!(!ppu.sprite_zero_hit);
+    }
+
+    #[test]
+    fn sprite_zero_hit_exclusions() {
+        // Clipped in the leftmost 8 pixels.
+        let (mut ppu, mut mapper) = sprite_zero_setup(0b0001_1000, 4, 1);
+
+        for _ in 1..=9 {
+            ppu.tick(&mut mapper);
+        }
+        assert!(!ppu.sprite_zero_hit);
+        ppu.tick(&mut mapper);
+        assert!(ppu.sprite_zero_hit);
+
+        // Never at x = 255.
+        let (mut ppu, mut mapper) = sprite_zero_setup(0b0001_1110, 250, 256);
+        ppu.tick(&mut mapper);
+        ppu.tick(&mut mapper);
+        assert!(!ppu.sprite_zero_hit);
+
+        let (mut ppu, mut mapper) = sprite_zero_setup(0b0001_1110, 250, 255);
+        ppu.tick(&mut mapper);
+        ppu.tick(&mut mapper);
+        assert!(ppu.sprite_zero_hit);
+
+        // Only sprite 0 counts.
+        let (mut ppu, mut mapper) = sprite_zero_setup(0b0001_1110, 0, 1);
+        ppu.sprite_zero_in_line = false;
+        ppu.tick(&mut mapper);
+        ppu.tick(&mut mapper);
+        assert!(!ppu.sprite_zero_hit);
+    }
+}
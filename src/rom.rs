@@ -1,41 +1,85 @@
 use std::{
     fs::File,
-    io::{BufReader, Bytes, Read},
+    io::{BufReader, Read},
 };
 
-use crate::cpu::instructions::exec::InstructionPair;
+use ines::iNESInfo;
 
-pub mod decoder;
-//pub mod ines;
+pub mod error;
+pub mod ines;
 
+pub use error::RomError;
+
+/// A parsed cartridge image.
 #[allow(clippy::upper_case_acronyms)] // No, I don't care about the acronyms.
 pub struct ROM {
-    rom_file_iter: Bytes<BufReader<File>>,
-    instruction_list: Vec<InstructionPair>,
+    pub info: iNESInfo,
+    /// 512 bytes, meant to be loaded at 0x7000. Only used by some old hacks and copier dumps.
+    pub trainer: Option<Vec<u8>>,
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>,
+    pub misc_rom: Vec<u8>,
 }
 
 impl ROM {
-    pub fn new(file: File) -> ROM {
-        ROM {
-            rom_file_iter: BufReader::new(file).bytes(),
-            instruction_list: Vec::new(),
-        }
+    /// Parses a ROM that's already in memory, e.g. one embedded with `include_bytes!`.
+    pub fn from_bytes(data: &[u8]) -> Result<ROM, RomError> {
+        ROM::load_ines(data)
+    }
+
+    /// Reads everything from `reader` (a pipe, a socket...) and parses it.
+    /// The whole file is needed anyway, since misc ROMs take up "the rest of the file".
+    pub fn from_reader(mut reader: impl Read) -> Result<ROM, RomError> {
+        let mut data = Vec::new();
+
+        reader.read_to_end(&mut data)?;
+
+        ROM::from_bytes(&data)
+    }
+
+    pub fn load(file: File) -> Result<ROM, RomError> {
+        ROM::from_reader(BufReader::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 16 KB PRG-ROM, 8 KB CHR-ROM image, with a marker at the start of each section.
+    fn image() -> Vec<u8> {
+        let mut data = b"NES\x1A\x01\x01".to_vec();
+        data.resize(16 + 0x4000 + 0x2000, 0);
+        data[16] = 0xAA;
+        data[16 + 0x4000] = 0xBB;
+
+        data
     }
 
-    fn simplified_ines() {}
-
-    pub fn load(mut self) -> std::io::Result<ROM> {
-        // Uses a loop instead of a for loop over an iterator due to issues with nesting iterator calls.
-        loop {
-            match self.rom_file_iter.next() {
-                Some(byte) => {
-                    let temp = ROM::decode_instr(&mut self, byte?);
-                    self.instruction_list.push(temp);
-                }
-                None => break,
+    #[test]
+    fn from_reader_matches_from_bytes() {
+        let data = image();
+
+        let from_bytes = ROM::from_bytes(&data).unwrap();
+        let from_reader = ROM::from_reader(&data[..]).unwrap();
+
+        assert_eq!(from_reader.prg_rom, from_bytes.prg_rom);
+        assert_eq!(from_reader.chr_rom, from_bytes.chr_rom);
+        assert_eq!(from_reader.info.mapper, from_bytes.info.mapper);
+        assert_eq!(from_reader.prg_rom[0], 0xAA);
+        assert_eq!(from_reader.chr_rom[0], 0xBB);
+    }
+
+    #[test]
+    fn io_errors() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
             }
         }
 
-        Ok(self)
+        assert!(matches!(ROM::from_reader(Broken), Err(RomError::Io(_))));
     }
 }
@@ -0,0 +1,62 @@
+use std::fmt;
+
+/// Everything that can go wrong while loading a ROM.
+#[derive(Debug)]
+pub enum RomError {
+    /// The file doesn't start with "NES<EOF>", or is too short to even contain a header.
+    BadMagic,
+    /// The header claims a trainer, but the file ends before it does.
+    TruncatedTrainer,
+    TruncatedPrgRom {
+        expected: u128,
+        actual: usize,
+    },
+    TruncatedChrRom {
+        expected: u128,
+        actual: usize,
+    },
+    UnsupportedMapper {
+        mapper: u16,
+        submapper: u8,
+    },
+    /// Header fields that contradict each other, hold reserved values, or describe a cartridge that can't work.
+    InconsistentHeader(&'static str),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::BadMagic => write!(f, "not an iNES file (bad magic number)"),
+            RomError::TruncatedTrainer => write!(f, "trainer is truncated"),
+            RomError::TruncatedPrgRom { expected, actual } => write!(
+                f,
+                "PRG-ROM is truncated: expected {expected} bytes, got {actual}"
+            ),
+            RomError::TruncatedChrRom { expected, actual } => write!(
+                f,
+                "CHR-ROM is truncated: expected {expected} bytes, got {actual}"
+            ),
+            RomError::UnsupportedMapper { mapper, submapper } => {
+                write!(f, "unsupported mapper {mapper} (submapper {submapper})")
+            }
+            RomError::InconsistentHeader(reason) => write!(f, "inconsistent header: {reason}"),
+            RomError::Io(error) => write!(f, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RomError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RomError {
+    fn from(error: std::io::Error) -> Self {
+        RomError::Io(error)
+    }
+}
@@ -1,15 +1,19 @@
-// This file will not be used for now!
 // Jesus, who thought NES ROMs could be so complicated!
-// For now, this file will not be attached to the project,
-// and the emulator itself will use a *very* simplified iNES implementation.
 
 // I believe NES 2.0 is simply a super-set of the iNES file format?
 // Thus, writing a separate implementation for iNES is likely not needed.
+// Refer to: https://www.nesdev.org/wiki/NES_2.0
 
-use std::num::NonZeroU16;
+use std::num::NonZeroU32;
 
-use super::ROM;
+use super::{RomError, ROM};
+use crate::utils::bits::get_bit;
 
+pub const HEADER_SIZE: usize = 16;
+pub const TRAINER_SIZE: usize = 512;
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum iNESVersion {
     /// AKA: iNES/.NES
     Ver1,
@@ -17,18 +21,20 @@ pub enum iNESVersion {
     Ver2,
 }
 
-pub fn check_ver(header: [u8; 16]) -> Option<iNESVersion> {
+pub fn check_ver(header: &[u8; HEADER_SIZE]) -> Result<iNESVersion, RomError> {
     // if the header starts with NES<EOF>:
     if header[0] == b'N' && header[1] == b'E' && header[2] == b'S' && header[3] == 0x1A {
         // if the byte at offset 7 has bit 2 clear and bit 3 set:
         if (header[7] & 0x0C) == 0x08 {
-            return Some(iNESVersion::Ver2);
+            return Ok(iNESVersion::Ver2);
         }
-        return Some(iNESVersion::Ver1);
+        return Ok(iNESVersion::Ver1);
     }
-    None
+    Err(RomError::BadMagic)
 }
 
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ConsoleType {
     NES,
     VsSystem,
@@ -38,12 +44,38 @@ pub enum ConsoleType {
     VT01STN,
     VT02,
     VT03,
+    VT09,
     VT32,
     VT369,
     UM6578,
-    FamicomNetworkSystem 
+    FamicomNetworkSystem,
+}
+
+impl ConsoleType {
+    /// Decodes the extended console type from the low nibble of byte 13.
+    /// The first three values match the basic console types from byte 7.
+    fn from_extended(value: u8) -> Option<ConsoleType> {
+        Some(match value {
+            0x0 => ConsoleType::NES,
+            0x1 => ConsoleType::VsSystem,
+            0x2 => ConsoleType::Playchoice10,
+            0x3 => ConsoleType::DecimalModeFamiclone,
+            0x4 => ConsoleType::PlugthroughOrEPSM,
+            0x5 => ConsoleType::VT01STN,
+            0x6 => ConsoleType::VT02,
+            0x7 => ConsoleType::VT03,
+            0x8 => ConsoleType::VT09,
+            0x9 => ConsoleType::VT32,
+            0xA => ConsoleType::VT369,
+            0xB => ConsoleType::UM6578,
+            0xC => ConsoleType::FamicomNetworkSystem,
+            _ => return None,
+        })
+    }
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VsPPUType {
     RP2C03B,
     RP2C03G,
@@ -57,9 +89,31 @@ pub enum VsPPUType {
     RC2C05_02,
     RC2C05_03,
     RC2C05_04,
-    RC2C05_05
+    RC2C05_05,
+}
+
+impl VsPPUType {
+    fn from_nibble(value: u8) -> Option<VsPPUType> {
+        Some(match value {
+            0x0 => VsPPUType::RP2C03B,
+            0x1 => VsPPUType::RP2C03G,
+            0x2 => VsPPUType::RP2C04_0001,
+            0x3 => VsPPUType::RP2C04_0002,
+            0x4 => VsPPUType::RP2C04_0003,
+            0x5 => VsPPUType::RP2C04_0004,
+            0x6 => VsPPUType::RC2C03B,
+            0x7 => VsPPUType::RC2C03C,
+            0x8 => VsPPUType::RC2C05_01,
+            0x9 => VsPPUType::RC2C05_02,
+            0xA => VsPPUType::RC2C05_03,
+            0xB => VsPPUType::RC2C05_04,
+            0xC => VsPPUType::RC2C05_05,
+            _ => return None,
+        })
+    }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VsHardwareType {
     Unisystem,
     UnisystemRBI,
@@ -67,50 +121,523 @@ pub enum VsHardwareType {
     UnisystemSuperXevious,
     UnisystemIceClimber,
     Dual,
-    DualRaid
+    DualRaid,
 }
 
+impl VsHardwareType {
+    fn from_nibble(value: u8) -> Option<VsHardwareType> {
+        Some(match value {
+            0x0 => VsHardwareType::Unisystem,
+            0x1 => VsHardwareType::UnisystemRBI,
+            0x2 => VsHardwareType::UnisystemTKO,
+            0x3 => VsHardwareType::UnisystemSuperXevious,
+            0x4 => VsHardwareType::UnisystemIceClimber,
+            0x5 => VsHardwareType::Dual,
+            0x6 => VsHardwareType::DualRaid,
+            _ => return None,
+        })
+    }
+}
+
+/// CPU/PPU timing the cartridge was made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timing {
+    /// RP2C02, North America, Japan, South Korea, Taiwan
+    NTSC,
+    /// RP2C07, Western Europe, Australia
+    PAL,
+    /// Identical ROM for multiple regions
+    MultipleRegion,
+    /// UA6538, Russia and other famiclones
+    Dendy,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug)]
 pub struct iNESInfo {
-    version: iNESVersion,
+    pub version: iNESVersion,
+    pub mapper: u16,
+    /// Always 0 for iNES, since only NES 2.0 can specify it.
+    pub submapper: u8,
     // Can you specify the ROM size(s) in a more concise way? YES.
     // The format uses only 12-bits for each.
     // Am I going to bother with floating numbers or a separate implementation
     // for decoding exponent-multiplier notation? NO.
-    prg_rom_size: u128, 
-    chr_rom_size: u128, 
+    pub prg_rom_size: u128,
+    pub chr_rom_size: u128,
 
     /// Hard-wired nametable mirroring type:
-    /// 
+    ///
     /// 0: Horizontal (vertical arrangement) or mapper-controlled
-    /// 
+    ///
     /// 1: Vertical (horizontal arrangement)
-    hardwired_nametable_mirroring: bool,
-    nonvolatile_memory: bool,
+    pub hardwired_nametable_mirroring: bool,
+    /// Battery or other non-volatile memory present.
+    pub nonvolatile_memory: bool,
     /// 512-byte Trainer
-    /// 
+    ///
     /// 0: Not present
-    /// 
+    ///
     /// 1: Present between Header and PRG-ROM data
-    has_trainer: bool,
-    /// Not enough IQ to understand this one.
-    hardwired_fourscreen_mode: bool,
-    console_type: ConsoleType,
+    pub has_trainer: bool,
+    /// The cartridge provides its own 2 KB of extra VRAM, giving four independent nametables.
+    /// Mirroring is ignored when this is set.
+    pub hardwired_fourscreen_mode: bool,
+    pub console_type: ConsoleType,
+    /// Only present when the console type is [ConsoleType::VsSystem].
+    pub vs_ppu_type: Option<VsPPUType>,
+    /// Only present when the console type is [ConsoleType::VsSystem].
+    pub vs_hardware_type: Option<VsHardwareType>,
+    pub timing: Timing,
     // Similarly to the ROM sizes, these technically can't reach this value in the NES 2.0 spec.
     // All of them are also represented as (64 << 4_bit_shift_count), thus taking a nibble each.
-    // I chose to represent the size as Option<NonZeroU16>, since it conveys the meaning better,
-    // and takes up the same amount of space as a u16.
-    // Due to the bit-shift encoding in the NES 2.0 specification,
-    // only valid numbers are the powers of two between 2^6 and 2^13.
-    prg_ram_size: Option<NonZeroU16>,
-    prg_nvram_size: Option<NonZeroU16>,
-    chr_ram_size: Option<NonZeroU16>,
-    chr_nvram_size: Option<NonZeroU16>,
-    // misc_roms: ??,
-    // default_expansion_device: DefaultExpansionDevice
+    // I chose to represent the size as Option<NonZeroU32>, since it conveys the meaning better,
+    // and takes up the same amount of space as a u32.
+    // A u16 would be enough for the most common 8 KB, but MMC5 boards go up to 128 KB.
+    pub prg_ram_size: Option<NonZeroU32>,
+    pub prg_nvram_size: Option<NonZeroU32>,
+    pub chr_ram_size: Option<NonZeroU32>,
+    pub chr_nvram_size: Option<NonZeroU32>,
+    /// Number of miscellaneous ROM areas following CHR-ROM.
+    pub misc_roms: u8,
+    /// Refer to: https://www.nesdev.org/wiki/NES_2.0#Default_Expansion_Device
+    pub default_expansion_device: u8,
+}
+
+/// PRG-ROM and CHR-ROM sizes share the same encoding, just in different units.
+/// When the MSB nibble is 0xF, the LSB byte is in exponent-multiplier notation: EEEEEEMM.
+/// Size then equals 2^E * (MM * 2 + 1) bytes.
+fn rom_size(lsb: u8, msb_nibble: u8, unit: u128) -> u128 {
+    if msb_nibble == 0xF {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as u128 * 2 + 1;
+
+        2u128.pow(exponent) * multiplier
+    } else {
+        (((msb_nibble as u128) << 8) | lsb as u128) * unit
+    }
+}
+
+/// RAM sizes are stored as shift counts, where the size is 64 << shift_count, or nothing when 0.
+fn ram_size(shift_count: u8) -> Option<NonZeroU32> {
+    if shift_count == 0 {
+        None
+    } else {
+        NonZeroU32::new(64 << shift_count)
+    }
+}
+
+impl iNESInfo {
+    pub fn parse(header: &[u8; HEADER_SIZE]) -> Result<iNESInfo, RomError> {
+        let version = check_ver(header)?;
+
+        let flags_6 = header[6];
+        let flags_7 = header[7];
+
+        let hardwired_nametable_mirroring = get_bit(flags_6, 0);
+        let nonvolatile_memory = get_bit(flags_6, 1);
+        let has_trainer = get_bit(flags_6, 2);
+        let hardwired_fourscreen_mode = get_bit(flags_6, 3);
+
+        let basic_console_type = flags_7 & 0b11;
+
+        match version {
+            iNESVersion::Ver1 => {
+                // Some old dumping tools wrote their signature into the padding at the end of the header.
+                // ("DiskDude!" being the most infamous one.) When that happens, byte 7 is garbage too.
+                let has_junk = header[12..16].iter().any(|&byte| byte != 0);
+                let mapper_high = if has_junk { 0 } else { flags_7 & 0xF0 };
+
+                let chr_rom_size = header[5] as u128 * 0x2000;
+
+                // iNES only specifies PRG-RAM in 8 KB units, with 0 implying 8 KB for compatibility.
+                // The battery flag decides whether that RAM is non-volatile.
+                let prg_ram = NonZeroU32::new(header[8].max(1) as u32 * 0x2000);
+
+                Ok(iNESInfo {
+                    version,
+                    mapper: (mapper_high | (flags_6 >> 4)) as u16,
+                    submapper: 0,
+                    prg_rom_size: header[4] as u128 * 0x4000,
+                    chr_rom_size,
+                    hardwired_nametable_mirroring,
+                    nonvolatile_memory,
+                    has_trainer,
+                    hardwired_fourscreen_mode,
+                    console_type: match basic_console_type {
+                        1 if !has_junk => ConsoleType::VsSystem,
+                        2 if !has_junk => ConsoleType::Playchoice10,
+                        _ => ConsoleType::NES,
+                    },
+                    vs_ppu_type: None,
+                    vs_hardware_type: None,
+                    timing: if get_bit(header[9], 0) {
+                        Timing::PAL
+                    } else {
+                        Timing::NTSC
+                    },
+                    prg_ram_size: if nonvolatile_memory { None } else { prg_ram },
+                    prg_nvram_size: if nonvolatile_memory { prg_ram } else { None },
+                    // No CHR-ROM means the board has 8 KB of CHR-RAM instead.
+                    chr_ram_size: if chr_rom_size == 0 {
+                        NonZeroU32::new(0x2000)
+                    } else {
+                        None
+                    },
+                    chr_nvram_size: None,
+                    misc_roms: 0,
+                    default_expansion_device: 0,
+                })
+            }
+            iNESVersion::Ver2 => {
+                let console_type = match basic_console_type {
+                    0 => ConsoleType::NES,
+                    1 => ConsoleType::VsSystem,
+                    2 => ConsoleType::Playchoice10,
+                    _ => ConsoleType::from_extended(header[13] & 0x0F).ok_or(
+                        RomError::InconsistentHeader("reserved extended console type"),
+                    )?,
+                };
+
+                let (vs_ppu_type, vs_hardware_type) =
+                    if console_type == ConsoleType::VsSystem {
+                        (
+                            Some(VsPPUType::from_nibble(header[13] & 0x0F).ok_or(
+                                RomError::InconsistentHeader("reserved Vs. System PPU type"),
+                            )?),
+                            Some(VsHardwareType::from_nibble(header[13] >> 4).ok_or(
+                                RomError::InconsistentHeader("reserved Vs. System hardware type"),
+                            )?),
+                        )
+                    } else {
+                        (None, None)
+                    };
+
+                let prg_nvram_size = ram_size(header[10] >> 4);
+                let chr_nvram_size = ram_size(header[11] >> 4);
+
+                // The battery flag and the non-volatile RAM sizes have to agree with each other.
+                // Note that NVRAM can't be sized in the header for mapper-internal memory (e.g. Namco 163),
+                // so only the opposite direction can be checked.
+                if !nonvolatile_memory && (prg_nvram_size.is_some() || chr_nvram_size.is_some()) {
+                    return Err(RomError::InconsistentHeader(
+                        "non-volatile RAM size given, but the battery flag is clear",
+                    ));
+                }
+
+                Ok(iNESInfo {
+                    version,
+                    mapper: ((header[8] as u16 & 0x0F) << 8)
+                        | (flags_7 & 0xF0) as u16
+                        | (flags_6 >> 4) as u16,
+                    submapper: header[8] >> 4,
+                    prg_rom_size: rom_size(header[4], header[9] & 0x0F, 0x4000),
+                    chr_rom_size: rom_size(header[5], header[9] >> 4, 0x2000),
+                    hardwired_nametable_mirroring,
+                    nonvolatile_memory,
+                    has_trainer,
+                    hardwired_fourscreen_mode,
+                    console_type,
+                    vs_ppu_type,
+                    vs_hardware_type,
+                    timing: match header[12] & 0b11 {
+                        0 => Timing::NTSC,
+                        1 => Timing::PAL,
+                        2 => Timing::MultipleRegion,
+                        _ => Timing::Dendy,
+                    },
+                    prg_ram_size: ram_size(header[10] & 0x0F),
+                    prg_nvram_size,
+                    chr_ram_size: ram_size(header[11] & 0x0F),
+                    chr_nvram_size,
+                    misc_roms: header[14] & 0b11,
+                    default_expansion_device: header[15] & 0b11_1111,
+                })
+            }
+        }
+    }
+}
+
+/// Splits off the next `size` bytes of the file, or returns None if there aren't enough of them.
+fn take_section<'a>(data: &mut &'a [u8], size: u128) -> Option<&'a [u8]> {
+    let size = usize::try_from(size).ok()?;
+
+    if data.len() < size {
+        return None;
+    }
+
+    let (section, rest) = data.split_at(size);
+    *data = rest;
+
+    Some(section)
 }
 
 impl ROM {
-    pub fn load_ines() {
-        
+    /// Parses a whole iNES / NES 2.0 file into its header info and ROM sections.
+    /// The file is laid out as: header, trainer (optional), PRG-ROM, CHR-ROM, misc ROMs (optional).
+    pub(super) fn load_ines(data: &[u8]) -> Result<ROM, RomError> {
+        let header: &[u8; HEADER_SIZE] = data
+            .get(..HEADER_SIZE)
+            .and_then(|header| header.try_into().ok())
+            .ok_or(RomError::BadMagic)?;
+
+        let info = iNESInfo::parse(header)?;
+
+        // Without PRG-ROM there's nothing to run, not even the reset vector.
+        if info.prg_rom_size == 0 {
+            return Err(RomError::InconsistentHeader("no PRG-ROM"));
+        }
+
+        let mut rest = &data[HEADER_SIZE..];
+
+        let trainer = if info.has_trainer {
+            Some(
+                take_section(&mut rest, TRAINER_SIZE as u128)
+                    .ok_or(RomError::TruncatedTrainer)?
+                    .to_vec(),
+            )
+        } else {
+            None
+        };
+
+        let prg_rom = take_section(&mut rest, info.prg_rom_size)
+            .ok_or(RomError::TruncatedPrgRom {
+                expected: info.prg_rom_size,
+                actual: rest.len(),
+            })?
+            .to_vec();
+
+        let chr_rom = take_section(&mut rest, info.chr_rom_size)
+            .ok_or(RomError::TruncatedChrRom {
+                expected: info.chr_rom_size,
+                actual: rest.len(),
+            })?
+            .to_vec();
+
+        // Misc ROMs have no size in the header, they simply take up the rest of the file.
+        let misc_rom = if info.misc_roms > 0 {
+            rest.to_vec()
+        } else {
+            Vec::new()
+        };
+
+        Ok(ROM {
+            info,
+            trainer,
+            prg_rom,
+            chr_rom,
+            misc_rom,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An iNES header with the given PRG-ROM and CHR-ROM bank counts and flags 6/7.
+    fn header(prg_banks: u8, chr_banks: u8, flags_6: u8, flags_7: u8) -> [u8; HEADER_SIZE] {
+        let mut header = [0; HEADER_SIZE];
+        header[..4].copy_from_slice(b"NES\x1A");
+        header[4] = prg_banks;
+        header[5] = chr_banks;
+        header[6] = flags_6;
+        header[7] = flags_7;
+
+        header
+    }
+
+    /// The header followed by `len` bytes of ROM data.
+    fn image(header: [u8; HEADER_SIZE], len: usize) -> Vec<u8> {
+        let mut data = header.to_vec();
+        data.resize(HEADER_SIZE + len, 0);
+
+        data
+    }
+
+    #[test]
+    fn loads_ines_sections() {
+        let mut data = image(header(2, 1, 0b0100, 0), TRAINER_SIZE + 0x8000 + 0x2000);
+        data[HEADER_SIZE + TRAINER_SIZE] = 0xAA;
+        data[HEADER_SIZE + TRAINER_SIZE + 0x8000] = 0xBB;
+
+        let rom = ROM::from_bytes(&data).unwrap();
+
+        assert_eq!(rom.info.version, iNESVersion::Ver1);
+        assert_eq!(rom.trainer.map(|trainer| trainer.len()), Some(TRAINER_SIZE));
+        assert_eq!(rom.prg_rom.len(), 0x8000);
+        assert_eq!(rom.prg_rom[0], 0xAA);
+        assert_eq!(rom.chr_rom.len(), 0x2000);
+        assert_eq!(rom.chr_rom[0], 0xBB);
+    }
+
+    #[test]
+    fn ines_defaults() {
+        let info = iNESInfo::parse(&header(1, 0, 0x12, 0x40)).unwrap();
+
+        assert_eq!(info.mapper, 0x41);
+        assert_eq!(info.prg_rom_size, 0x4000);
+        // No CHR-ROM means 8 KB of CHR-RAM, and PRG-RAM defaults to 8 KB, kept by the battery.
+        assert_eq!(info.chr_ram_size, NonZeroU32::new(0x2000));
+        assert_eq!(info.prg_ram_size, None);
+        assert_eq!(info.prg_nvram_size, NonZeroU32::new(0x2000));
+        assert_eq!(info.timing, Timing::NTSC);
+    }
+
+    #[test]
+    fn ignores_byte_7_with_junk_in_the_padding() {
+        let mut header = header(1, 1, 0x10, 0);
+        header[7..16].copy_from_slice(b"DiskDude!");
+
+        let info = iNESInfo::parse(&header).unwrap();
+
+        assert_eq!(info.version, iNESVersion::Ver1);
+        assert_eq!(info.mapper, 1);
+        assert_eq!(info.console_type, ConsoleType::NES);
+    }
+
+    #[test]
+    fn nes2_fields() {
+        let mut header = header(2, 1, 0x40, 0x08 | 0x10);
+        header[8] = 0x21;
+        header[10] = 0x07;
+        header[11] = 0x09;
+        header[12] = 0x01;
+        header[15] = 0x02;
+
+        let info = iNESInfo::parse(&header).unwrap();
+
+        assert_eq!(info.version, iNESVersion::Ver2);
+        assert_eq!(info.mapper, 0x114);
+        assert_eq!(info.submapper, 2);
+        assert_eq!(info.prg_rom_size, 0x8000);
+        assert_eq!(info.chr_rom_size, 0x2000);
+        assert_eq!(info.prg_ram_size, NonZeroU32::new(0x2000));
+        assert_eq!(info.chr_ram_size, NonZeroU32::new(0x8000));
+        assert_eq!(info.timing, Timing::PAL);
+        assert_eq!(info.default_expansion_device, 2);
+    }
+
+    #[test]
+    fn nes2_rom_sizes() {
+        let mut header = header(0x34, 0x12, 0, 0x08);
+        header[9] = 0x21;
+
+        let info = iNESInfo::parse(&header).unwrap();
+
+        assert_eq!(info.prg_rom_size, 0x134 * 0x4000);
+        assert_eq!(info.chr_rom_size, 0x212 * 0x2000);
+    }
+
+    #[test]
+    fn nes2_exponent_multiplier_sizes() {
+        // 2^10 * 3 and 2^4 * 7
+        let mut header = header((10 << 2) | 0b01, (4 << 2) | 0b11, 0, 0x08);
+        header[9] = 0xFF;
+
+        let info = iNESInfo::parse(&header).unwrap();
+
+        assert_eq!(info.prg_rom_size, 3072);
+        assert_eq!(info.chr_rom_size, 112);
+    }
+
+    #[test]
+    fn nes2_vs_system() {
+        let mut header = header(1, 1, 0, 0x08 | 0x01);
+        header[13] = 0x14;
+
+        let info = iNESInfo::parse(&header).unwrap();
+
+        assert_eq!(info.console_type, ConsoleType::VsSystem);
+        assert_eq!(info.vs_ppu_type, Some(VsPPUType::RP2C04_0003));
+        assert_eq!(info.vs_hardware_type, Some(VsHardwareType::UnisystemRBI));
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut data = image(header(1, 0, 0, 0), 0x4000);
+        data[3] = 0;
+
+        assert!(matches!(ROM::from_bytes(&data), Err(RomError::BadMagic)));
+        assert!(matches!(
+            ROM::from_bytes(&data[..8]),
+            Err(RomError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn truncated_sections() {
+        let data = image(header(1, 0, 0b0100, 0), 100);
+        assert!(matches!(
+            ROM::from_bytes(&data),
+            Err(RomError::TruncatedTrainer)
+        ));
+
+        let data = image(header(2, 0, 0, 0), 0x4000);
+        assert!(matches!(
+            ROM::from_bytes(&data),
+            Err(RomError::TruncatedPrgRom {
+                expected: 0x8000,
+                actual: 0x4000
+            })
+        ));
+
+        let data = image(header(1, 1, 0, 0), 0x4000 + 0x1000);
+        assert!(matches!(
+            ROM::from_bytes(&data),
+            Err(RomError::TruncatedChrRom {
+                expected: 0x2000,
+                actual: 0x1000
+            })
+        ));
+    }
+
+    #[test]
+    fn inconsistent_headers() {
+        let data = image(header(0, 1, 0, 0), 0x2000);
+        assert!(matches!(
+            ROM::from_bytes(&data),
+            Err(RomError::InconsistentHeader("no PRG-ROM"))
+        ));
+
+        // Extended console type 0xF is reserved.
+        let mut reserved_console = header(1, 0, 0, 0x08 | 0x03);
+        reserved_console[13] = 0x0F;
+
+        // Vs. System PPU type 0xF and hardware type 0xF are reserved.
+        let mut reserved_ppu = header(1, 0, 0, 0x08 | 0x01);
+        reserved_ppu[13] = 0x0F;
+        let mut reserved_hardware = header(1, 0, 0, 0x08 | 0x01);
+        reserved_hardware[13] = 0xF0;
+
+        // NVRAM without the battery flag.
+        let mut nvram_without_battery = header(1, 0, 0, 0x08);
+        nvram_without_battery[10] = 0x70;
+
+        for header in [
+            reserved_console,
+            reserved_ppu,
+            reserved_hardware,
+            nvram_without_battery,
+        ] {
+            assert!(matches!(
+                iNESInfo::parse(&header),
+                Err(RomError::InconsistentHeader(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn unsupported_mapper() {
+        let data = image(header(1, 1, 0xF0, 0xF0), 0x4000 + 0x2000);
+        let rom = ROM::from_bytes(&data).unwrap();
+
+        assert!(matches!(
+            crate::mapper::create(rom),
+            Err(RomError::UnsupportedMapper {
+                mapper: 0xFF,
+                submapper: 0
+            })
+        ));
     }
 }
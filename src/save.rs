@@ -0,0 +1,168 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::mapper::Mapper;
+
+pub mod error;
+
+pub use error::SaveError;
+
+/// Battery-backed cartridge memory, kept in a raw `.sav` file between sessions.
+/// The file holds exactly what [Mapper::battery_ram] returns, so it's compatible with most other emulators.
+pub struct SaveFile {
+    path: PathBuf,
+}
+
+impl SaveFile {
+    pub fn new(path: impl Into<PathBuf>) -> SaveFile {
+        SaveFile { path: path.into() }
+    }
+
+    /// The usual place for saves: next to the ROM, with the extension swapped for `.sav`.
+    pub fn beside(rom_path: impl AsRef<Path>) -> SaveFile {
+        SaveFile::new(rom_path.as_ref().with_extension("sav"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Restores the mapper's battery-backed memory from the file.
+    /// A missing file is fine, that just means the game hasn't been played yet.
+    /// A file of any size other than what the header says is rejected, instead of being loaded halfway.
+    pub fn load(&self, mapper: &mut dyn Mapper) -> Result<(), SaveError> {
+        let Some(battery_ram) = mapper.battery_ram() else {
+            return Ok(());
+        };
+
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+
+        if data.len() != battery_ram.len() {
+            return Err(SaveError::SizeMismatch {
+                expected: battery_ram.len(),
+                actual: data.len(),
+            });
+        }
+
+        mapper.load_battery_ram(&data);
+
+        Ok(())
+    }
+
+    /// Writes the mapper's battery-backed memory out.
+    /// Goes through a temporary file, so a crash halfway through can't leave a corrupted save behind.
+    pub fn save(&self, mapper: &dyn Mapper) -> io::Result<()> {
+        let Some(battery_ram) = mapper.battery_ram() else {
+            return Ok(());
+        };
+
+        let temp_path = self.path.with_extension("sav.tmp");
+
+        fs::write(&temp_path, battery_ram)?;
+        fs::rename(&temp_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::{bus::Bus, mapper::test_rom, memory::Memory};
+
+    /// An NROM cartridge with 8 KB of battery-backed PRG-RAM.
+    fn battery_cartridge() -> Box<dyn Mapper> {
+        let mut rom = test_rom(0, 0);
+        rom.info.nonvolatile_memory = true;
+        rom.info.prg_ram_size = None;
+        rom.info.prg_nvram_size = NonZeroU32::new(0x2000);
+
+        crate::mapper::create(rom).unwrap()
+    }
+
+    /// A save file of its own for every test, in the temporary directory.
+    fn save_file(name: &str) -> SaveFile {
+        let path = std::env::temp_dir().join(format!("fenes-{}-{name}.sav", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        SaveFile::new(path)
+    }
+
+    #[test]
+    fn missing_file_is_a_new_game() {
+        let save_file = save_file("missing");
+        let mut cartridge = battery_cartridge();
+
+        assert!(save_file.load(cartridge.as_mut()).is_ok());
+        assert_eq!(cartridge.cpu_peek(0x6000), Some(0));
+    }
+
+    #[test]
+    fn loads_a_file_the_size_of_prg_nvram() {
+        let save_file = save_file("exact");
+        let mut cartridge = battery_cartridge();
+
+        let mut data = vec![0; 0x2000];
+        data[0] = 0x42;
+        data[0x1FFF] = 0x24;
+        fs::write(save_file.path(), &data).unwrap();
+
+        save_file.load(cartridge.as_mut()).unwrap();
+        fs::remove_file(save_file.path()).unwrap();
+
+        assert_eq!(cartridge.cpu_peek(0x6000), Some(0x42));
+        assert_eq!(cartridge.cpu_peek(0x7FFF), Some(0x24));
+    }
+
+    #[test]
+    fn rejects_short_and_long_files() {
+        let save_file = save_file("mismatch");
+
+        for len in [0, 0x0400, 0x1FFF, 0x2001, 0x4000] {
+            let mut cartridge = battery_cartridge();
+            fs::write(save_file.path(), vec![0xFF; len]).unwrap();
+
+            assert!(matches!(
+                save_file.load(cartridge.as_mut()),
+                Err(SaveError::SizeMismatch {
+                    expected: 0x2000,
+                    actual
+                }) if actual == len
+            ));
+            // Nothing gets loaded halfway.
+            assert_eq!(cartridge.cpu_peek(0x6000), Some(0));
+        }
+
+        fs::remove_file(save_file.path()).unwrap();
+    }
+
+    #[test]
+    fn saves_only_when_battery_ram_changes() {
+        let save_file = save_file("dirty");
+        let path = save_file.path().to_path_buf();
+
+        let mut memory = Memory::new();
+        memory.insert_cartridge(battery_cartridge());
+        memory.attach_save_file(save_file).unwrap();
+
+        memory.save().unwrap();
+        assert!(!path.exists());
+
+        memory.write(0x6000, 0x42);
+        memory.save().unwrap();
+        assert_eq!(fs::read(&path).unwrap()[0], 0x42);
+
+        // Writing the same value again doesn't change anything.
+        fs::remove_file(&path).unwrap();
+        memory.write(0x6000, 0x42);
+        memory.save().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+/// Everything that can go wrong while loading or writing a save file.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The save file doesn't match the battery-backed memory the header describes.
+    SizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    Io(std::io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::SizeMismatch { expected, actual } => write!(
+                f,
+                "save file has the wrong size: expected {expected} bytes, got {actual}"
+            ),
+            SaveError::Io(error) => write!(f, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}